@@ -0,0 +1,4 @@
+-- Record the format yt-dlp actually selected for each download
+ALTER TABLE downloads ADD COLUMN format_id TEXT;
+ALTER TABLE downloads ADD COLUMN width INTEGER;
+ALTER TABLE downloads ADD COLUMN height INTEGER;
@@ -0,0 +1,2 @@
+-- Track which videos have been watched
+ALTER TABLE videos ADD COLUMN watched INTEGER NOT NULL DEFAULT 0;
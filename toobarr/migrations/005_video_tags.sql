@@ -0,0 +1,3 @@
+-- Tags and categories reported by yt-dlp, stored as JSON arrays
+ALTER TABLE videos ADD COLUMN tags TEXT;
+ALTER TABLE videos ADD COLUMN categories TEXT;
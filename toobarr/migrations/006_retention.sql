@@ -0,0 +1,14 @@
+-- Per-channel retention policy; NULL disables the corresponding rule
+ALTER TABLE channels ADD COLUMN retention_keep_latest INTEGER;
+ALTER TABLE channels ADD COLUMN retention_max_age_days INTEGER;
+ALTER TABLE channels ADD COLUMN retention_delete_videos INTEGER NOT NULL DEFAULT 0;
+
+-- Pruned downloads keep their row so the video is not downloaded again
+ALTER TABLE downloads ADD COLUMN pruned_at TEXT;
+
+-- Videos removed by retention, skipped when the channel is synced again
+CREATE TABLE IF NOT EXISTS pruned_videos (
+    youtube_id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    pruned_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,2 @@
+-- yt-dlp live_status (is_live, is_upcoming, was_live, ...); NULL for regular videos
+ALTER TABLE videos ADD COLUMN live_status TEXT;
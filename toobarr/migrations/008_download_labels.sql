@@ -0,0 +1,9 @@
+-- User-defined labels on downloads, e.g. "favorite" or "to-watch"
+CREATE TABLE IF NOT EXISTS download_labels (
+    download_id TEXT NOT NULL REFERENCES downloads(id) ON DELETE CASCADE,
+    label TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (download_id, label)
+);
+
+CREATE INDEX IF NOT EXISTS idx_download_labels_label ON download_labels(label);
@@ -0,0 +1,4 @@
+-- Per-channel upload date bounds for syncs in yt-dlp's --dateafter/--datebefore
+-- syntax; relative forms such as now-1month are resolved on every sync
+ALTER TABLE channels ADD COLUMN date_after TEXT;
+ALTER TABLE channels ADD COLUMN date_before TEXT;
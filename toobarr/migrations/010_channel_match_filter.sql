@@ -0,0 +1,3 @@
+-- Per-channel yt-dlp --match-filter expression and a switch to skip Shorts
+ALTER TABLE channels ADD COLUMN match_filter TEXT;
+ALTER TABLE channels ADD COLUMN no_shorts INTEGER NOT NULL DEFAULT 0;
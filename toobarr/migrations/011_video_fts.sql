@@ -0,0 +1,29 @@
+-- Full-text index over video titles and descriptions. It keeps its own copy
+-- of the text keyed by video id, since the implicit rowid of videos may
+-- change on VACUUM
+CREATE VIRTUAL TABLE IF NOT EXISTS videos_fts USING fts5(
+    video_id UNINDEXED,
+    title,
+    description,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO videos_fts (video_id, title, description)
+SELECT id, title, COALESCE(description, '') FROM videos;
+
+CREATE TRIGGER IF NOT EXISTS videos_fts_insert AFTER INSERT ON videos BEGIN
+    INSERT INTO videos_fts (video_id, title, description)
+    VALUES (new.id, new.title, COALESCE(new.description, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS videos_fts_delete AFTER DELETE ON videos BEGIN
+    DELETE FROM videos_fts WHERE video_id = old.id;
+END;
+
+-- Syncs rewrite every row, so only reindex when the text actually changed
+CREATE TRIGGER IF NOT EXISTS videos_fts_update AFTER UPDATE OF title, description ON videos
+WHEN old.title IS NOT new.title OR old.description IS NOT new.description BEGIN
+    DELETE FROM videos_fts WHERE video_id = old.id;
+    INSERT INTO videos_fts (video_id, title, description)
+    VALUES (new.id, new.title, COALESCE(new.description, ''));
+END;
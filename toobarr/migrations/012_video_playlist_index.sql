@@ -0,0 +1,2 @@
+-- Position in the channel or playlist listing at the last sync, used for NFO episode numbers
+ALTER TABLE videos ADD COLUMN playlist_index INTEGER;
@@ -0,0 +1,2 @@
+-- Requested format selector when yt-dlp fell back to the best available format
+ALTER TABLE downloads ADD COLUMN format_fallback TEXT;
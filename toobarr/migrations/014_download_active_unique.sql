@@ -0,0 +1,12 @@
+-- At most one pending or downloading row per video, so concurrent queue
+-- requests cannot start the same video twice. Older duplicates are failed first.
+UPDATE downloads SET status = 'failed', error_message = 'Duplicate download'
+WHERE status IN ('pending', 'downloading')
+  AND rowid NOT IN (
+    SELECT MAX(rowid) FROM downloads
+    WHERE status IN ('pending', 'downloading')
+    GROUP BY video_id
+  );
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_downloads_active_video
+ON downloads(video_id) WHERE status IN ('pending', 'downloading');
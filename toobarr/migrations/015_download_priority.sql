@@ -0,0 +1,4 @@
+-- Pending downloads start in order of priority (highest first), then age
+ALTER TABLE downloads ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_downloads_queue ON downloads(status, priority DESC, created_at);
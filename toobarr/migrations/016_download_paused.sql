@@ -0,0 +1,50 @@
+-- Adds the 'paused' status. SQLite cannot alter a CHECK constraint, so the
+-- table is rebuilt. Migrations run in a transaction where foreign keys
+-- cannot be turned off, and dropping the old table cascades into
+-- download_labels, so the labels are set aside and restored.
+CREATE TEMP TABLE download_labels_backup AS SELECT * FROM download_labels;
+
+CREATE TABLE downloads_new (
+    id TEXT PRIMARY KEY,
+    video_id TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'downloading', 'paused', 'completed', 'failed')),
+    file_path TEXT,
+    file_size_bytes INTEGER,
+    progress_percent REAL,
+    error_message TEXT,
+    started_at TEXT,
+    completed_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    format_id TEXT,
+    width INTEGER,
+    height INTEGER,
+    pruned_at TEXT,
+    format_fallback TEXT,
+    priority INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO downloads_new (
+    id, video_id, status, file_path, file_size_bytes, progress_percent, error_message,
+    started_at, completed_at, created_at, updated_at, format_id, width, height, pruned_at,
+    format_fallback, priority
+)
+SELECT
+    id, video_id, status, file_path, file_size_bytes, progress_percent, error_message,
+    started_at, completed_at, created_at, updated_at, format_id, width, height, pruned_at,
+    format_fallback, priority
+FROM downloads ORDER BY rowid;
+
+DROP TABLE downloads;
+ALTER TABLE downloads_new RENAME TO downloads;
+
+CREATE INDEX IF NOT EXISTS idx_downloads_video_id ON downloads(video_id);
+CREATE INDEX IF NOT EXISTS idx_downloads_status ON downloads(status);
+CREATE INDEX IF NOT EXISTS idx_downloads_queue ON downloads(status, priority DESC, created_at);
+-- A paused download still holds the video's one active slot
+CREATE UNIQUE INDEX IF NOT EXISTS idx_downloads_active_video
+ON downloads(video_id) WHERE status IN ('pending', 'downloading', 'paused');
+
+INSERT INTO download_labels (download_id, label, created_at)
+SELECT download_id, label, created_at FROM download_labels_backup;
+DROP TABLE download_labels_backup;
@@ -0,0 +1,2 @@
+-- Failed attempts of a download, for automatic retries of transient errors
+ALTER TABLE downloads ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Per-channel switch to keep only the audio track, e.g. for talk and podcast channels
+ALTER TABLE channels ADD COLUMN audio_only INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Videos the channel's match filter rejected were stored as failures
+UPDATE downloads SET status = 'skipped'
+WHERE status = 'failed' AND error_message LIKE 'Skipped by the channel''s match filter%';
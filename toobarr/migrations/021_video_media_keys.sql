@@ -0,0 +1,55 @@
+-- Channels and videos from other sites were keyed by their bare id before
+-- keys carried the site. The extractor is not stored, so the site is taken
+-- from the registrable domain of the stored URL: https://soundcloud.com/...
+-- and https://www.bbc.co.uk/... become soundcloud:<id> and bbc:<id>.
+-- Otherwise the next sync would insert every such row a second time. Rows
+-- whose site cannot be told keep their key.
+CREATE TEMP TABLE media_authorities AS
+SELECT kind, id, substr(rest, 1, instr(rest || '/', '/') - 1) AS authority
+FROM (
+    SELECT 'video' AS kind, id, substr(webpage_url, instr(webpage_url, '://') + 3) AS rest FROM videos
+    WHERE instr(youtube_id, ':') = 0 AND instr(webpage_url, '://') > 0
+    UNION ALL
+    SELECT 'channel', id, substr(url, instr(url, '://') + 3) FROM channels
+    WHERE instr(youtube_id, ':') = 0 AND instr(url, '://') > 0
+);
+
+-- Labels are peeled off the host down to the last two. When those are a
+-- country code after a generic label (co.uk, com.au, ne.jp) they are the
+-- public suffix, and the site is the label before them.
+CREATE TEMP TABLE media_sites AS
+WITH RECURSIVE hosts(kind, id, host, parent) AS (
+    SELECT kind, id, lower(substr(authority, 1, instr(authority || ':', ':') - 1)), NULL
+    FROM media_authorities
+    UNION ALL
+    SELECT kind, id, substr(host, instr(host, '.') + 1), host FROM hosts WHERE host LIKE '%.%.%'
+)
+SELECT kind, id, CASE
+    WHEN length(host) - instr(host, '.') = 2
+         AND substr(host, 1, instr(host, '.') - 1) IN ('ac', 'co', 'com', 'edu', 'gov', 'ne', 'net', 'or', 'org')
+    THEN substr(parent, 1, instr(parent, '.') - 1)
+    ELSE substr(host, 1, instr(host, '.') - 1)
+END AS site
+FROM hosts
+WHERE host LIKE '%.%' AND host NOT LIKE '%.%.%';
+
+DELETE FROM media_sites WHERE site IS NULL OR site IN ('', 'youtube', 'youtu');
+
+UPDATE videos
+SET youtube_id = (SELECT site FROM media_sites s WHERE s.kind = 'video' AND s.id = videos.id) || ':' || youtube_id
+WHERE id IN (SELECT id FROM media_sites WHERE kind = 'video')
+  AND NOT EXISTS (
+      SELECT 1 FROM videos other, media_sites s
+      WHERE s.kind = 'video' AND s.id = videos.id AND other.youtube_id = s.site || ':' || videos.youtube_id
+  );
+
+UPDATE channels
+SET youtube_id = (SELECT site FROM media_sites s WHERE s.kind = 'channel' AND s.id = channels.id) || ':' || youtube_id
+WHERE id IN (SELECT id FROM media_sites WHERE kind = 'channel')
+  AND NOT EXISTS (
+      SELECT 1 FROM channels other, media_sites s
+      WHERE s.kind = 'channel' AND s.id = channels.id AND other.youtube_id = s.site || ':' || channels.youtube_id
+  );
+
+DROP TABLE media_sites;
+DROP TABLE media_authorities;
@@ -1,9 +1,35 @@
-use sqlx::{Pool, Sqlite, sqlite::SqlitePoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::future::Future;
 use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
 
 pub type DbPool = Pool<Sqlite>;
 
-pub async fn init_pool(database_path: &str) -> Result<DbPool, Box<dyn std::error::Error + Send + Sync>> {
+/// How long a connection waits on a locked database before returning `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The database serializes writers no matter how many connections are open, so the
+/// pool mostly adds concurrent readers. A handful covers the web handlers plus
+/// the download and retention workers.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 8;
+
+/// Beyond this, extra connections only add file handles and memory; they
+/// cannot speed up writes.
+const MAX_CONNECTIONS_LIMIT: u32 = 64;
+
+/// Attempts made by [`retry_on_busy`] before giving up.
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
+const BUSY_RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Opens the database in WAL mode so readers don't block the download
+/// worker's writes, with a busy timeout for writer contention. The pragmas
+/// are set on every pooled connection.
+pub async fn init_pool(
+    database_path: &str,
+    max_connections: u32
+) -> Result<DbPool, Box<dyn std::error::Error + Send + Sync>> {
     let db_path = Path::new(database_path);
     if let Some(parent) = db_path.parent() {
         if !parent.as_os_str().is_empty() {
@@ -11,16 +37,168 @@ pub async fn init_pool(database_path: &str) -> Result<DbPool, Box<dyn std::error
         }
     }
 
-    let database_url = format!("sqlite:{database_path}?mode=rwc");
+    let options = SqliteConnectOptions::from_str(&format!("sqlite:{database_path}"))?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(BUSY_TIMEOUT)
+        .foreign_keys(true);
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await?;
-
-    sqlx::query("PRAGMA foreign_keys = ON")
-        .execute(&pool)
+        .max_connections(max_connections)
+        .connect_with(options)
         .await?;
 
     Ok(pool)
 }
+
+/// A migrated in-memory database on a single connection, so every query in
+/// a test sees the same data.
+#[cfg(test)]
+pub async fn test_pool() -> DbPool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
+
+/// Reads `DATABASE_MAX_CONNECTIONS`, falling back to the default when it is
+/// unset or invalid.
+pub fn max_connections_from_env() -> u32 {
+    let value = std::env::var("DATABASE_MAX_CONNECTIONS").ok();
+    parse_max_connections(value.as_deref()).unwrap_or_else(|e| {
+        tracing::warn!("{}, using {}", e, DEFAULT_MAX_CONNECTIONS);
+        DEFAULT_MAX_CONNECTIONS
+    })
+}
+
+fn parse_max_connections(value: Option<&str>) -> Result<u32, String> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(DEFAULT_MAX_CONNECTIONS);
+    };
+    match value.parse::<u32>() {
+        Ok(n) if (1..=MAX_CONNECTIONS_LIMIT).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "Invalid DATABASE_MAX_CONNECTIONS \"{value}\"; expected 1 to {MAX_CONNECTIONS_LIMIT}"
+        ))
+    }
+}
+
+/// Runs a write, retrying with backoff while the database is reported as
+/// busy or locked. Other errors are returned immediately.
+pub async fn retry_on_busy<T, F, Fut>(mut op: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRY_ATTEMPTS => {
+                tracing::debug!("Database busy (attempt {}), retrying: {}", attempt, e);
+                tokio::time::sleep(BUSY_RETRY_BASE_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return result
+        }
+    }
+}
+
+/// `SQLITE_BUSY` (5) and `SQLITE_LOCKED` (6), including extended codes.
+fn is_busy(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_err) = err else {
+        return false;
+    };
+    let primary = db_err
+        .code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .map(|code| code & 0xff);
+    matches!(primary, Some(5 | 6)) || db_err.message().contains("database is locked")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_writes_do_not_fail() {
+        let dir = std::env::temp_dir().join(format!(
+            "toobarr-db-stress-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos())
+        ));
+        let db_path = dir.join("stress.db");
+        let pool = init_pool(db_path.to_str().unwrap(), DEFAULT_MAX_CONNECTIONS).await.unwrap();
+
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        sqlx::query("CREATE TABLE counters (task INTEGER NOT NULL, n INTEGER NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut handles = Vec::new();
+        for task in 0..20 {
+            let pool = pool.clone();
+            handles.push(tokio::spawn(async move {
+                for n in 0..25 {
+                    retry_on_busy(|| async {
+                        let mut tx = pool.begin().await?;
+                        sqlx::query("INSERT INTO counters (task, n) VALUES (?, ?)")
+                            .bind(task)
+                            .bind(n)
+                            .execute(&mut *tx)
+                            .await?;
+                        tx.commit().await
+                    })
+                    .await
+                    .unwrap();
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM counters")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 500);
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_busy_passes_through_other_errors() {
+        let mut calls = 0;
+        let result: Result<(), sqlx::Error> = retry_on_busy(|| {
+            calls += 1;
+            async { Err(sqlx::Error::RowNotFound) }
+        })
+        .await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_parse_max_connections() {
+        assert_eq!(parse_max_connections(None), Ok(DEFAULT_MAX_CONNECTIONS));
+        assert_eq!(parse_max_connections(Some("  ")), Ok(DEFAULT_MAX_CONNECTIONS));
+        assert_eq!(parse_max_connections(Some("16")), Ok(16));
+        assert_eq!(parse_max_connections(Some(" 1 ")), Ok(1));
+        assert!(parse_max_connections(Some("0")).is_err());
+        assert!(parse_max_connections(Some("65")).is_err());
+        assert!(parse_max_connections(Some("-3")).is_err());
+        assert!(parse_max_connections(Some("many")).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::models::{Channel, FeedEntry};
+
+const ITUNES_NS: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "rss")]
+struct Rss {
+    #[serde(rename = "@version")]
+    version: &'static str,
+    #[serde(rename = "@xmlns:itunes")]
+    xmlns_itunes: &'static str,
+    #[serde(rename = "@xmlns:atom")]
+    xmlns_atom: &'static str,
+    channel: RssChannel
+}
+
+/// Podcast validators expect a self link, language, author, category and
+/// explicit flag on top of plain RSS 2.0.
+#[derive(Debug, Serialize)]
+struct RssChannel {
+    #[serde(rename = "atom:link")]
+    self_link: AtomLink,
+    title: String,
+    link: String,
+    description: String,
+    language: &'static str,
+    #[serde(rename = "itunes:author")]
+    author: String,
+    #[serde(rename = "itunes:explicit")]
+    explicit: &'static str,
+    #[serde(rename = "itunes:category")]
+    category: ItunesCategory,
+    #[serde(rename = "itunes:image", skip_serializing_if = "Option::is_none")]
+    image: Option<ItunesImage>,
+    #[serde(rename = "item")]
+    items: Vec<Item>
+}
+
+#[derive(Debug, Serialize)]
+struct AtomLink {
+    #[serde(rename = "@href")]
+    href: String,
+    #[serde(rename = "@rel")]
+    rel: &'static str,
+    #[serde(rename = "@type")]
+    link_type: &'static str
+}
+
+#[derive(Debug, Serialize)]
+struct ItunesCategory {
+    #[serde(rename = "@text")]
+    text: &'static str
+}
+
+#[derive(Debug, Serialize)]
+struct Item {
+    title: String,
+    description: String,
+    guid: Guid,
+    #[serde(rename = "pubDate", skip_serializing_if = "Option::is_none")]
+    pub_date: Option<String>,
+    enclosure: Enclosure,
+    #[serde(rename = "itunes:image", skip_serializing_if = "Option::is_none")]
+    image: Option<ItunesImage>,
+    #[serde(rename = "itunes:duration", skip_serializing_if = "Option::is_none")]
+    duration: Option<i64>
+}
+
+#[derive(Debug, Serialize)]
+struct Guid {
+    #[serde(rename = "@isPermaLink")]
+    is_permalink: bool,
+    #[serde(rename = "$text")]
+    value: String
+}
+
+#[derive(Debug, Serialize)]
+struct Enclosure {
+    #[serde(rename = "@url")]
+    url: String,
+    #[serde(rename = "@length")]
+    length: i64,
+    #[serde(rename = "@type")]
+    mime_type: &'static str
+}
+
+#[derive(Debug, Serialize)]
+struct ItunesImage {
+    #[serde(rename = "@href")]
+    href: String
+}
+
+/// Renders a channel's completed downloads as an RSS 2.0 podcast feed.
+/// `base_url` is the externally visible origin, e.g. `http://nas:8000`; media
+/// enclosures point at `/media/{download_id}`, the self link at
+/// `/channels/{id}/rss` and local thumbnails are made absolute against it.
+pub fn channel_feed_xml(channel: &Channel, entries: &[FeedEntry], base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+
+    let items = entries
+        .iter()
+        .map(|entry| Item {
+            title: entry.title.clone(),
+            description: entry.description.clone().unwrap_or_default(),
+            guid: Guid { is_permalink: false, value: entry.youtube_id.clone() },
+            pub_date: pub_date(entry),
+            enclosure: Enclosure {
+                url: format!("{base_url}/media/{}", entry.download_id),
+                length: entry.file_size_bytes.unwrap_or(0),
+                mime_type: mime_type(&entry.file_path)
+            },
+            image: entry
+                .thumbnail_url
+                .as_deref()
+                .map(|url| ItunesImage { href: absolute_url(base_url, url) }),
+            duration: entry.duration_seconds
+        })
+        .collect();
+
+    let description = channel
+        .description
+        .clone()
+        .filter(|d| !d.trim().is_empty())
+        .unwrap_or_else(|| format!("Downloads from {}", channel.name));
+
+    let rss = Rss {
+        version: "2.0",
+        xmlns_itunes: ITUNES_NS,
+        xmlns_atom: ATOM_NS,
+        channel: RssChannel {
+            self_link: AtomLink {
+                href: format!("{base_url}/channels/{}/rss", channel.id),
+                rel: "self",
+                link_type: "application/rss+xml"
+            },
+            title: channel.name.clone(),
+            link: channel.url.clone(),
+            description,
+            language: "en",
+            author: channel.name.clone(),
+            explicit: "false",
+            category: ItunesCategory { text: "TV & Film" },
+            image: channel
+                .thumbnail_url
+                .as_deref()
+                .map(|url| ItunesImage { href: absolute_url(base_url, url) }),
+            items
+        }
+    };
+
+    let body = quick_xml::se::to_string(&rss).unwrap_or_else(|e| {
+        tracing::error!("Failed to serialize feed XML: {}", e);
+        String::new()
+    });
+
+    format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n{body}\n")
+}
+
+/// RFC 2822 date from the `YYYYMMDD` upload date, falling back to when the
+/// download completed.
+fn pub_date(entry: &FeedEntry) -> Option<String> {
+    let uploaded = entry
+        .upload_date
+        .as_deref()
+        .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0));
+    let completed = || {
+        entry
+            .completed_at
+            .as_deref()
+            .and_then(|at| chrono::NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S").ok())
+    };
+    uploaded
+        .or_else(completed)
+        .map(|datetime| datetime.and_utc().to_rfc2822())
+}
+
+fn absolute_url(base_url: &str, url: &str) -> String {
+    if url.starts_with('/') {
+        format!("{base_url}{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+fn mime_type(file_path: &str) -> &'static str {
+    let extension = Path::new(file_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mkv") => "video/x-matroska",
+        Some("mov") => "video/quicktime",
+        Some("m4a") => "audio/mp4",
+        Some("mp3") => "audio/mpeg",
+        Some("opus" | "ogg") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> Channel {
+        Channel {
+            id: "channel-1".to_string(),
+            youtube_id: "UC123".to_string(),
+            name: "Test & Channel".to_string(),
+            url: "https://www.youtube.com/@test".to_string(),
+            thumbnail_url: Some("/static/thumbnails/channels/UC123.jpg".to_string()),
+            description: None,
+            video_count: None,
+            last_synced_at: None,
+            retention_keep_latest: None,
+            retention_max_age_days: None,
+            retention_delete_videos: false,
+            date_after: None,
+            date_before: None,
+            match_filter: None,
+            no_shorts: false,
+            audio_only: false,
+            created_at: String::new(),
+            updated_at: String::new()
+        }
+    }
+
+    fn entry() -> FeedEntry {
+        FeedEntry {
+            download_id: "dl-1".to_string(),
+            file_path: "/downloads/Test/Episode [abc123].m4a".to_string(),
+            file_size_bytes: Some(1234),
+            completed_at: Some("2024-05-02 10:00:00".to_string()),
+            youtube_id: "abc123".to_string(),
+            title: "Episode <1>".to_string(),
+            description: Some("About things".to_string()),
+            thumbnail_url: Some("https://i.ytimg.com/vi/abc123/hq.jpg".to_string()),
+            duration_seconds: Some(125),
+            upload_date: Some("20240501".to_string())
+        }
+    }
+
+    #[test]
+    fn test_channel_feed_xml() {
+        let xml = channel_feed_xml(&channel(), &[entry()], "http://nas:8000/");
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rss version=\"2.0\""));
+        assert!(xml.contains("<title>Test &amp; Channel</title>"));
+        assert!(xml.contains(
+            "<atom:link href=\"http://nas:8000/channels/channel-1/rss\" rel=\"self\" type=\"application/rss+xml\"/>"
+        ));
+        assert!(xml.contains("<description>Downloads from Test &amp; Channel</description>"));
+        assert!(xml.contains("<language>en</language>"));
+        assert!(xml.contains("<itunes:author>Test &amp; Channel</itunes:author>"));
+        assert!(xml.contains("<itunes:explicit>false</itunes:explicit>"));
+        assert!(xml.contains("<itunes:category text=\"TV &amp; Film\"/>"));
+        assert!(xml.contains(
+            "<itunes:image href=\"http://nas:8000/static/thumbnails/channels/UC123.jpg\"/>"
+        ));
+        assert!(xml.contains("<title>Episode &lt;1&gt;</title>"));
+        assert!(xml.contains("<guid isPermaLink=\"false\">abc123</guid>"));
+        assert!(xml.contains("<pubDate>Wed, 1 May 2024 00:00:00 +0000</pubDate>"));
+        assert!(xml.contains(
+            "<enclosure url=\"http://nas:8000/media/dl-1\" length=\"1234\" type=\"audio/mp4\"/>"
+        ));
+        assert!(xml.contains("<itunes:image href=\"https://i.ytimg.com/vi/abc123/hq.jpg\"/>"));
+        assert!(xml.contains("<itunes:duration>125</itunes:duration>"));
+    }
+
+    #[test]
+    fn test_pub_date_falls_back_to_completed_at() {
+        let mut entry = entry();
+        entry.upload_date = None;
+        assert_eq!(pub_date(&entry).as_deref(), Some("Thu, 2 May 2024 10:00:00 +0000"));
+
+        entry.completed_at = None;
+        assert_eq!(pub_date(&entry), None);
+    }
+
+    #[test]
+    fn test_mime_type() {
+        assert_eq!(mime_type("a/b.MP4"), "video/mp4");
+        assert_eq!(mime_type("a/b.mkv"), "video/x-matroska");
+        assert_eq!(mime_type("a/b.opus"), "audio/ogg");
+        assert_eq!(mime_type("a/b"), "application/octet-stream");
+    }
+}
@@ -1,23 +1,72 @@
 use std::path::PathBuf;
 
+use askama::Template;
 use axum::{
-    extract::{Form, Multipart, Path, State},
-    http::StatusCode,
+    extract::{Form, Multipart, Path, Query, State},
+    http::{header, StatusCode},
     response::{Html, IntoResponse, Json, Redirect, Response}
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+use yt_dlp::{DateRange, DownloadOptions};
 
 use crate::error::AppError;
-use crate::models::{Channel, CreateChannel, Download, DownloadStatus, Settings, Video};
-use crate::state::AppState;
-use crate::thumbnail;
-use crate::workers::download::{DownloadCommand, VideoMeta};
+use crate::metrics::{self, Gauges};
+use crate::models::{
+    normalize_label, parse_clock_time, parse_subtitle_langs, Channel, ChannelSettings, CreateChannel, Download, DownloadStatus,
+    DownloadWithVideo, DirectoryLayout, EpisodeScheme, QueueEntry, RetentionPolicy, Settings, Video, AUDIO_FORMATS,
+    REMUX_FORMATS, SUBTITLE_FORMATS, THUMBNAIL_FORMATS
+};
+use crate::opml;
+use crate::purge;
+use crate::self_test::{self, SelfTestReport};
+use crate::state::{AppState, DownloadSummary};
+use crate::workers::download::{refresh_sidecars, remove_partial_files, DownloadCommand, DownloadFilter, VideoMeta};
+use crate::workers::thumbnails::{self, ThumbnailJob};
 
 #[derive(Debug, Deserialize)]
 pub struct SettingsForm {
     download_path: String,
     max_concurrent_downloads: String,
+    concurrent_fragments: Option<String>,
+    rate_limit: Option<String>,
+    night_rate_limit: Option<String>,
+    night_start: Option<String>,
+    night_end: Option<String>,
+    throttled_rate: Option<String>,
+    max_filesize: Option<String>,
+    cache_dir: Option<String>,
+    ytdlp_config: Option<String>,
+    temp_dir: Option<String>,
     extractor_args: Option<String>,
+    impersonate: Option<String>,
+    env_vars: Option<String>,
+    output_template: Option<String>,
+    max_height: Option<String>,
+    remux_video: Option<String>,
+    recode_video_fallback: Option<String>,
+    audio_format: Option<String>,
+    audio_only_format: Option<String>,
+    audio_quality: Option<String>,
+    download_subtitles: Option<String>,
+    subtitle_langs: Option<String>,
+    subtitle_format: Option<String>,
+    include_auto_subs: Option<String>,
+    restrict_filenames: Option<String>,
+    windows_filenames: Option<String>,
+    trim_filenames: Option<String>,
+    integrity_tolerance: Option<String>,
+    retry_max_attempts: Option<String>,
+    retry_base_delay: Option<String>,
+    keep_info_json: Option<String>,
+    embed_thumbnail: Option<String>,
+    thumbnail_format: Option<String>,
+    nfo_episode_scheme: Option<String>,
+    directory_layout: Option<String>,
+    record_live_streams: Option<String>,
+    set_mtime: Option<String>,
+    no_continue: Option<String>,
+    estimate_size: Option<String>,
     ffmpeg_path: Option<String>,
     ffprobe_path: Option<String>,
     ytdlp_path: Option<String>,
@@ -29,18 +78,54 @@ pub async fn create_channel(
     State(state): State<AppState>,
     Form(input): Form<CreateChannel>
 ) -> Result<Response, AppError> {
-    tracing::info!("Fetching channel info for URL: {}", input.url);
+    let (AddChannelOutcome::Created(id) | AddChannelOutcome::Existing(id)) =
+        add_channel(&state, &input.url, None).await?;
+    Ok(Redirect::to(&format!("/channels/{id}")).into_response())
+}
+
+/// The channel's row id, whether it was just created or already tracked.
+enum AddChannelOutcome {
+    Created(String),
+    Existing(String)
+}
+
+/// Fetches the channel listing, stores the channel with its videos and starts
+/// the thumbnail fetch. A channel that is already tracked is recognised from
+/// its first entry, before the whole listing is enumerated.
+/// `settings` are stored before the first sync so its date range applies.
+async fn add_channel(
+    state: &AppState,
+    url: &str,
+    settings: Option<&ChannelSettings>
+) -> Result<AddChannelOutcome, AppError> {
+    tracing::info!("Fetching channel info for URL: {}", url);
+    let date_range = match settings {
+        Some(settings) => DateRange::parse(settings.date_after.as_deref(), settings.date_before.as_deref())
+            .map_err(|e| AppError::bad_request(format!("Invalid {e}")))?,
+        None => DateRange::default()
+    };
 
     let yt_dlp = state.yt_dlp.read().await.clone();
+    match yt_dlp.get_playlist_metadata(url).await {
+        Ok(metadata) => {
+            let channel_id = channel_key(&metadata);
+            if let Some(existing) = Channel::find_by_youtube_id(&state.pool, &channel_id).await? {
+                tracing::info!("Channel {} is already tracked", channel_id);
+                return Ok(AddChannelOutcome::Existing(existing.id));
+            }
+        }
+        Err(e) => tracing::debug!("Metadata fetch for {} failed, fetching full listing: {}", url, e)
+    }
+
     let playlist_info = yt_dlp
-        .get_playlist_info(&input.url)
+        .get_playlist_info(url)
         .await
         .map_err(|e| AppError::bad_request(format!("Failed to fetch channel: {e}")))?;
 
-    let channel_id = playlist_info.channel_id.clone().unwrap_or_else(|| playlist_info.id.clone());
+    let channel_id = channel_key(&playlist_info);
 
     if let Some(existing) = Channel::find_by_youtube_id(&state.pool, &channel_id).await? {
-        return Ok(Redirect::to(&format!("/channels/{}", existing.id)).into_response());
+        return Ok(AddChannelOutcome::Existing(existing.id));
     }
 
     let id = uuid7::uuid7().to_string();
@@ -53,47 +138,199 @@ pub async fn create_channel(
     let thumbnail_url = playlist_info
         .entries
         .first()
-        .and_then(|v| v.best_thumbnail().map(String::from));
+        .and_then(|v| preferred_thumbnail(v).map(String::from));
 
     Channel::insert(
         &state.pool,
         &id,
         &channel_id,
         &name,
-        &input.url,
+        url,
         None,
         playlist_info.description.as_deref()
     )
     .await?;
+    if let Some(settings) = settings {
+        Channel::update_settings(&state.pool, &id, settings).await?;
+    }
 
-    if let Some(thumb_url) = thumbnail_url {
-        match thumbnail::download_channel_thumbnail(&id, &thumb_url).await {
-            Ok(local_path) => {
-                if let Err(e) = Channel::update_thumbnail(&state.pool, &id, &local_path).await {
-                    tracing::warn!("Failed to update channel thumbnail: {}", e);
-                }
-            }
+    let entries = tokio_stream::iter(playlist_info.entries.into_iter().map(Ok));
+    let SyncedVideos { synced: video_count, mut thumbnails, .. } =
+        sync_channel_videos(state, &id, entries, SyncMode::Full, &date_range).await?;
+    if let Some(url) = thumbnail_url {
+        thumbnails.insert(0, ThumbnailJob::Channel { channel_id: id.clone(), url });
+    }
+    thumbnails::spawn_fetch(
+        state.pool.clone(),
+        state.thumbnail_progress.clone(),
+        id.clone(),
+        thumbnails
+    )
+    .await;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    Channel::update_sync_info(&state.pool, &id, video_count, &now).await?;
+
+    tracing::info!("Created channel {} with {} videos", name, video_count);
+
+    Ok(AddChannelOutcome::Created(id))
+}
+
+/// The key a channel is stored under, as derived from its listing.
+fn channel_key(playlist_info: &yt_dlp::PlaylistInfo) -> String {
+    media_key(
+        playlist_info.extractor.as_deref().or(playlist_info.extractor_key.as_deref()),
+        playlist_info.channel_id.as_deref().unwrap_or(&playlist_info.id)
+    )
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    added: usize,
+    skipped: usize,
+    failed: Vec<ImportFailure>
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    url: String,
+    error: String
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn export_channels(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let channels = Channel::find_all(&state.pool).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/x-opml; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"toobarr.opml\"")
+        ],
+        opml::export_channels(&channels)
+    ))
+}
+
+/// Every channel with its settings as JSON, for [`import_channels`] on
+/// another server.
+#[tracing::instrument(skip(state))]
+pub async fn export_channels_json(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let channels = Channel::find_all(&state.pool).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"toobarr-channels.json\"")
+        ],
+        opml::export_channels_json(&channels)
+    ))
+}
+
+/// Accepts a JSON channel export, an OPML file or a plain list of URLs in
+/// the `import_file` field. Channels are added and synced one at a time; a
+/// URL that fails is reported and the rest of the import carries on.
+/// Channels already tracked under the same URL or id are skipped, so
+/// importing twice changes nothing.
+#[tracing::instrument(skip(state, multipart))]
+pub async fn import_channels(
+    State(state): State<AppState>,
+    mut multipart: Multipart
+) -> Result<Json<ImportReport>, AppError> {
+    let mut contents = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::bad_request(format!("Invalid upload: {e}")))?
+    {
+        if field.name() == Some("import_file") {
+            contents = Some(
+                field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::bad_request(format!("Failed to read file: {e}")))?
+            );
+            break;
+        }
+    }
+    let contents = contents.ok_or_else(|| AppError::bad_request("No import file in upload"))?;
+
+    let entries = opml::parse_import(&contents).map_err(AppError::bad_request)?;
+    if entries.is_empty() {
+        return Err(AppError::bad_request("No channel URLs found"));
+    }
+
+    let known = Channel::find_all(&state.pool).await?;
+    let known_urls: std::collections::HashSet<String> = known.iter().map(|c| c.url.clone()).collect();
+    let known_ids: std::collections::HashSet<String> = known.into_iter().map(|c| c.youtube_id).collect();
+
+    let mut report = ImportReport::default();
+    for entry in entries {
+        if known_urls.contains(&entry.url) || entry.youtube_id.as_ref().is_some_and(|id| known_ids.contains(id)) {
+            report.skipped += 1;
+            continue;
+        }
+        match import_channel(&state, &entry).await {
+            Ok(AddChannelOutcome::Created(_)) => report.added += 1,
+            Ok(AddChannelOutcome::Existing(_)) => report.skipped += 1,
             Err(e) => {
-                tracing::warn!("Failed to download channel thumbnail: {}", e);
+                tracing::warn!("Failed to import channel {}: {}", entry.url, e.message);
+                report.failed.push(ImportFailure { url: entry.url, error: e.message });
             }
         }
     }
 
-    let video_count = sync_channel_videos(&state, &id, &playlist_info.entries).await?;
+    tracing::info!(
+        "Channel import finished: {} added, {} skipped, {} failed",
+        report.added,
+        report.skipped,
+        report.failed.len()
+    );
 
-    let now = chrono::Utc::now().to_rfc3339();
-    Channel::update_sync_info(&state.pool, &id, video_count, &now).await?;
+    Ok(Json(report))
+}
 
-    tracing::info!("Created channel {} with {} videos", name, video_count);
+/// Adds one imported channel and applies the settings it was exported with.
+/// Settings are checked first so a bad entry adds nothing.
+async fn import_channel(state: &AppState, entry: &opml::ImportEntry) -> Result<AddChannelOutcome, AppError> {
+    if let Some(ref settings) = entry.settings {
+        validate_channel_settings(settings).map_err(AppError::bad_request)?;
+    }
+    add_channel(state, &entry.url, entry.settings.as_ref()).await
+}
 
-    Ok(Redirect::to(&format!("/channels/{id}")).into_response())
+/// The checks the channel settings forms apply, for imported settings.
+fn validate_channel_settings(settings: &ChannelSettings) -> Result<(), String> {
+    if settings.retention_keep_latest.is_some_and(|n| n < 0)
+        || settings.retention_max_age_days.is_some_and(|n| n < 0)
+    {
+        return Err("Retention values must be non-negative".to_string());
+    }
+    DateRange::parse(settings.date_after.as_deref(), settings.date_before.as_deref())
+        .map_err(|e| format!("Invalid {e}"))?;
+    if settings.match_filter.as_deref().is_some_and(|f| f.contains(['\n', '\r'])) {
+        return Err("Match filter must be a single line".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteChannelQuery {
+    #[serde(default)]
+    purge: bool
 }
 
+/// With `?purge=true` the channel's downloaded files are removed as well.
 #[tracing::instrument(skip(state))]
 pub async fn delete_channel(
     State(state): State<AppState>,
-    Path(id): Path<String>
+    Path(id): Path<String>,
+    Query(params): Query<DeleteChannelQuery>
 ) -> Result<Response, AppError> {
+    if params.purge {
+        let channel = Channel::find_by_id(&state.pool, &id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Channel not found"))?;
+        let removed = purge::purge_channel_files(&state.pool, &channel).await?;
+        tracing::info!("Purged {} files for channel {}", removed, channel.name);
+    }
+
     let deleted = Channel::delete(&state.pool, &id).await?;
 
     if deleted {
@@ -103,41 +340,356 @@ pub async fn delete_channel(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RetentionForm {
+    keep_latest: Option<String>,
+    max_age_days: Option<String>,
+    delete_videos: Option<String>
+}
+
+/// Blank fields disable the corresponding retention rule.
+#[tracing::instrument(skip(state))]
+pub async fn update_retention(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(input): Form<RetentionForm>
+) -> Result<impl IntoResponse, AppError> {
+    let policy = RetentionPolicy {
+        keep_latest: parse_retention_value(input.keep_latest.as_deref(), "Keep latest")?,
+        max_age_days: parse_retention_value(input.max_age_days.as_deref(), "Maximum age")?,
+        delete_videos: input.delete_videos.is_some()
+    };
+
+    if !Channel::update_retention(&state.pool, &id, &policy).await? {
+        return Err(AppError::not_found("Channel not found"));
+    }
+
+    tracing::info!("Updated retention for channel {}: {:?}", id, policy);
+
+    Ok((StatusCode::OK, Html("Retention saved")))
+}
+
+fn parse_retention_value(value: Option<&str>, label: &str) -> Result<Option<i64>, AppError> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    match value.parse::<i64>() {
+        Ok(n) if n >= 0 => Ok(Some(n)),
+        _ => Err(AppError::bad_request(format!("{label} must be a non-negative number")))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DateRangeForm {
+    date_after: Option<String>,
+    date_before: Option<String>
+}
+
+/// Stores the expressions as entered so relative bounds like `now-1month`
+/// keep moving; blank fields leave that side of the range open.
+#[tracing::instrument(skip(state))]
+pub async fn update_date_range(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(input): Form<DateRangeForm>
+) -> Result<impl IntoResponse, AppError> {
+    let date_after = input.date_after.as_deref().map(str::trim).filter(|v| !v.is_empty());
+    let date_before = input.date_before.as_deref().map(str::trim).filter(|v| !v.is_empty());
+    let range = DateRange::parse(date_after, date_before)
+        .map_err(|e| AppError::bad_request(format!("Invalid {e}")))?;
+
+    if !Channel::update_date_range(&state.pool, &id, date_after, date_before).await? {
+        return Err(AppError::not_found("Channel not found"));
+    }
+
+    tracing::info!("Updated sync date range for channel {}: {}", id, range);
+
+    Ok((StatusCode::OK, Html("Date range saved")))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchFilterForm {
+    match_filter: Option<String>,
+    no_shorts: Option<String>
+}
+
+/// The filter is handed to yt-dlp's `--match-filter` verbatim; a blank one
+/// downloads everything, subject to the Shorts switch.
+#[tracing::instrument(skip(state))]
+pub async fn update_match_filter(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(input): Form<MatchFilterForm>
+) -> Result<impl IntoResponse, AppError> {
+    let match_filter = input.match_filter.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    if match_filter.is_some_and(|f| f.contains(['\n', '\r'])) {
+        return Err(AppError::bad_request("Match filter must be a single line"));
+    }
+    let no_shorts = input.no_shorts.is_some();
+
+    if !Channel::update_match_filter(&state.pool, &id, match_filter, no_shorts).await? {
+        return Err(AppError::not_found("Channel not found"));
+    }
+
+    tracing::info!("Updated match filter for channel {}: {:?} (no shorts: {})", id, match_filter, no_shorts);
+
+    Ok((StatusCode::OK, Html("Download filter saved")))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioOnlyForm {
+    audio_only: Option<String>
+}
+
+/// Takes effect for downloads queued from now on; finished videos are kept.
+#[tracing::instrument(skip(state))]
+pub async fn update_audio_only(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(input): Form<AudioOnlyForm>
+) -> Result<impl IntoResponse, AppError> {
+    let audio_only = input.audio_only.is_some();
+    if !Channel::update_audio_only(&state.pool, &id, audio_only).await? {
+        return Err(AppError::not_found("Channel not found"));
+    }
+
+    tracing::info!("Updated download mode for channel {}: audio only {}", id, audio_only);
+
+    Ok((StatusCode::OK, Html("Download mode saved")))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    #[serde(default)]
+    full: bool,
+    #[serde(default)]
+    detailed: bool,
+    limit: Option<u32>
+}
+
+/// Incremental by default, ending the listing once it reaches videos already
+/// in the library; `?full=true` re-scans every entry of the channel.
+/// `?detailed=true` extracts each video instead of using the flat listing so
+/// duration, views and thumbnails are filled in, at the cost of a much slower sync.
+/// `?limit=N` lists only the channel's newest N videos.
 #[tracing::instrument(skip(state))]
 pub async fn sync_channel(
     State(state): State<AppState>,
-    Path(id): Path<String>
+    Path(id): Path<String>,
+    Query(params): Query<SyncQuery>
 ) -> Result<impl IntoResponse, AppError> {
+    if params.limit == Some(0) {
+        return Err(AppError::bad_request("Limit must be at least 1"));
+    }
     let channel = Channel::find_by_id(&state.pool, &id)
         .await?
         .ok_or_else(|| AppError::not_found("Channel not found"))?;
 
     tracing::info!("Syncing channel: {}", channel.name);
 
+    // Listings are newest first, so the end of the range keeps the newest
+    let mut selection = DownloadOptions::new();
+    if let Some(limit) = params.limit {
+        selection = selection.playlist_end(limit);
+    }
     let yt_dlp = state.yt_dlp.read().await.clone();
-    let playlist_info = yt_dlp
-        .get_playlist_info(&channel.url)
-        .await
-        .map_err(|e| AppError::internal(format!("Failed to fetch channel: {e}")))?;
-
-    let video_count = sync_channel_videos(&state, &id, &playlist_info.entries).await?;
+    let entries = yt_dlp.stream_playlist(&channel.url, params.detailed, &selection);
+
+    let mode = if params.full { SyncMode::Full } else { SyncMode::Incremental };
+    let date_range = channel.date_range();
+    let SyncedVideos { synced, listed, stopped_early, playlist_count, thumbnails } =
+        sync_channel_videos(&state, &id, entries, mode, &date_range).await?;
+    thumbnails::spawn_fetch(
+        state.pool.clone(),
+        state.thumbnail_progress.clone(),
+        id.clone(),
+        thumbnails
+    )
+    .await;
 
+    // A listing that was limited or ended early has not counted the whole channel
+    let video_count = if stopped_early || params.limit.is_some() {
+        playlist_count.map(i64::from).or(channel.video_count).map_or(listed, |count| count.max(listed))
+    } else {
+        listed
+    };
     let now = chrono::Utc::now().to_rfc3339();
     Channel::update_sync_info(&state.pool, &id, video_count, &now).await?;
 
-    tracing::info!("Synced {} videos for channel {}", video_count, channel.name);
+    tracing::info!("Synced {} of {} videos for channel {} ({:?})", synced, video_count, channel.name, mode);
 
     Ok((StatusCode::OK, Html("Sync complete")))
 }
 
+/// Key stored in the `youtube_id` columns. `YouTube` ids stay bare so existing
+/// rows keep matching; other platforms are prefixed with their site so
+/// numeric ids from different sites cannot collide.
+pub fn media_key(extractor: Option<&str>, id: &str) -> String {
+    match extractor.map(extractor_site) {
+        Some(site) if !is_youtube_extractor(&site) => format!("{site}:{id}"),
+        _ => id.to_string()
+    }
+}
+
+/// The site part of an extractor name, so a channel listing
+/// (`soundcloud:user`) and its tracks (`soundcloud`) share one.
+fn extractor_site(extractor: &str) -> String {
+    extractor.split(':').next().unwrap_or(extractor).to_ascii_lowercase()
+}
+
+/// The platform's own id for a key built by [`media_key`].
+pub fn source_id(key: &str) -> &str {
+    key.split_once(':').map_or(key, |(_, id)| id)
+}
+
+/// The site prefix of a key built by [`media_key`], `None` for `YouTube`.
+pub fn media_site(key: &str) -> Option<&str> {
+    key.split_once(':').map(|(site, _)| site)
+}
+
+fn is_youtube_extractor(key: &str) -> bool {
+    key.to_ascii_lowercase().starts_with("youtube")
+}
+
+/// JPEG where offered: cached thumbnails are reused as the `-thumb.jpg`
+/// sidecar when yt-dlp wrote none, and some players reject WebP.
+fn preferred_thumbnail(entry: &yt_dlp::VideoInfo) -> Option<&str> {
+    entry.best_thumbnail_by_format(&["jpg"]).or_else(|| entry.best_thumbnail())
+}
+
+/// Dedup key and page URL for a playlist entry. The key is taken from the
+/// extractor name, which flat and detailed listings both report, falling back
+/// to the playlist's and then `ie_key`; only `YouTube` ids can be turned into
+/// a URL.
+fn entry_identity(
+    entry: &yt_dlp::VideoInfo,
+    playlist_extractor: Option<&str>
+) -> Option<(String, String)> {
+    let extractor = entry
+        .extractor
+        .as_deref()
+        .or(playlist_extractor)
+        .or(entry.ie_key.as_deref());
+    let key = media_key(extractor, &entry.id);
+    let webpage_url = entry.webpage_url.clone().or_else(|| entry.url.clone()).or_else(|| {
+        extractor
+            .is_none_or(is_youtube_extractor)
+            .then(|| format!("https://www.youtube.com/watch?v={}", entry.id))
+    })?;
+    Some((key, webpage_url))
+}
+
+/// Incremental syncs stop after this many consecutive entries that are
+/// already in the library, since channel listings are newest first.
+const INCREMENTAL_STOP_AFTER: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncMode {
+    Incremental,
+    Full
+}
+
+struct EarlyStop {
+    mode: SyncMode,
+    known_streak: usize
+}
+
+impl EarlyStop {
+    fn new(mode: SyncMode) -> Self {
+        Self { mode, known_streak: 0 }
+    }
+
+    /// Records whether the next entry is already known and reports whether
+    /// enumeration should end before it.
+    fn should_stop(&mut self, known: bool) -> bool {
+        if self.mode == SyncMode::Full {
+            return false;
+        }
+        if known {
+            self.known_streak += 1;
+        } else {
+            self.known_streak = 0;
+        }
+        self.known_streak > INCREMENTAL_STOP_AFTER
+    }
+}
+
+/// yt-dlp's `live_status`, falling back to `is_live` for extractors that only
+/// report the flag.
+fn live_status(entry: &yt_dlp::VideoInfo) -> Option<String> {
+    entry
+        .live_status
+        .clone()
+        .or_else(|| (entry.is_live == Some(true)).then(|| "is_live".to_string()))
+}
+
+/// What [`sync_channel_videos`] wrote and how much of the listing it read.
+struct SyncedVideos {
+    synced: i64,
+    listed: i64,
+    /// An incremental sync ended the listing at videos already in the library.
+    stopped_early: bool,
+    /// The channel's size when yt-dlp reported it.
+    playlist_count: Option<u32>,
+    /// Still to fetch; callers hand them to the background fetcher.
+    thumbnails: Vec<ThumbnailJob>
+}
+
+/// Writes the video rows as `entries` are listed. An incremental sync stops
+/// reading once it reaches known videos, and dropping a streamed listing
+/// there ends yt-dlp's enumeration as well. Entries uploaded outside
+/// `date_range` are skipped; flat listings often lack an upload date, and
+/// such entries are kept.
 async fn sync_channel_videos(
     state: &AppState,
     channel_id: &str,
-    entries: &[yt_dlp::VideoInfo]
-) -> Result<i64, AppError> {
-    let mut count = 0i64;
+    entries: impl tokio_stream::Stream<Item = yt_dlp::Result<yt_dlp::VideoInfo>>,
+    mode: SyncMode,
+    date_range: &DateRange
+) -> Result<SyncedVideos, AppError> {
+    tokio::pin!(entries);
+    let mut synced = SyncedVideos {
+        synced: 0,
+        listed: 0,
+        stopped_early: false,
+        playlist_count: None,
+        thumbnails: Vec::new()
+    };
+    // The first entry stands in for the playlist, as in a PlaylistInfo
+    let mut playlist_extractor = None;
+    let mut early_stop = EarlyStop::new(mode);
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry.map_err(|e| AppError::internal(format!("Failed to fetch channel: {e}")))?;
+        if synced.listed == 0 {
+            playlist_extractor.clone_from(&entry.extractor);
+        }
+        synced.listed += 1;
+        synced.playlist_count = synced.playlist_count.or(entry.playlist_count);
+
+        let Some((media_id, webpage_url)) = entry_identity(&entry, playlist_extractor.as_deref())
+        else {
+            tracing::warn!("Skipping entry {} without a page URL", entry.id);
+            continue;
+        };
+
+        if Video::is_pruned(&state.pool, &media_id).await? {
+            continue;
+        }
+
+        if !in_date_range(date_range, entry.upload_date.as_deref()) {
+            tracing::debug!("Skipping {} uploaded outside {}", media_id, date_range);
+            continue;
+        }
+
+        let known = mode == SyncMode::Incremental
+            && Video::find_by_youtube_id(&state.pool, &media_id).await?.is_some();
+        if early_stop.should_stop(known) {
+            tracing::debug!("Stopping incremental sync at known video {}", media_id);
+            synced.stopped_early = true;
+            break;
+        }
 
-    for entry in entries {
         let video_id = uuid7::uuid7().to_string();
 
         #[allow(clippy::cast_possible_truncation)]
@@ -145,94 +697,373 @@ async fn sync_channel_videos(
         #[allow(clippy::cast_possible_wrap)]
         let view_count = entry.view_count.map(|v| v as i64);
 
-        let webpage_url = entry
-            .webpage_url
-            .clone()
-            .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", entry.id));
-
-        let local_thumbnail = if let Some(thumb_url) = entry.best_thumbnail() {
-            match thumbnail::download_video_thumbnail(&entry.id, thumb_url).await {
-                Ok(path) => Some(path),
-                Err(e) => {
-                    tracing::warn!("Failed to download thumbnail for {}: {}", entry.id, e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
-
         Video::upsert(
             &state.pool,
             &video_id,
             channel_id,
-            &entry.id,
+            &media_id,
             &entry.title,
             entry.description.as_deref(),
-            local_thumbnail.as_deref(),
+            None,
             duration_seconds,
             entry.upload_date.as_deref(),
             view_count,
-            &webpage_url
+            &webpage_url,
+            live_status(&entry).as_deref(),
+            entry.playlist_index.map(i64::from),
+            &entry.tags,
+            &entry.categories
         )
         .await?;
 
-        count += 1;
+        // Full syncs list every video again; only new or changed images are fetched
+        if let Some(url) = preferred_thumbnail(&entry) {
+            if thumbnails::video_needs_fetch(&media_id, url).await {
+                synced.thumbnails.push(ThumbnailJob::Video { youtube_id: media_id, url: url.to_string() });
+            }
+        }
+        synced.synced += 1;
     }
 
-    Ok(count)
+    Ok(synced)
 }
 
+/// Flat listings often lack upload dates; those entries are kept here and
+/// checked by the download's match filter once yt-dlp has the full metadata.
+fn in_date_range(date_range: &DateRange, upload_date: Option<&str>) -> bool {
+    upload_date.is_none_or(|date| date_range.contains(date))
+}
+
+/// Polled by the channel page while thumbnails are fetched; once finished the
+/// page is refreshed so the images replace their placeholders.
 #[tracing::instrument(skip(state))]
-pub async fn start_download(
+pub async fn thumbnail_progress(
+    State(state): State<AppState>,
+    Path(id): Path<String>
+) -> Response {
+    match state.thumbnail_progress.read().await.get(&id) {
+        Some(progress) => {
+            Html(thumbnail_progress_fragment(&id, progress.done, progress.total)).into_response()
+        }
+        None => ([("HX-Refresh", "true")], Html(String::new())).into_response()
+    }
+}
+
+fn thumbnail_progress_fragment(channel_id: &str, done: usize, total: usize) -> String {
+    format!(
+        r#"<p hx-get="/api/channels/{channel_id}/thumbnails" hx-trigger="every 2s" hx-swap="outerHTML" aria-busy="true">Fetching thumbnails {done}/{total}</p>"#
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchedQuery {
+    pub watched: Option<bool>
+}
+
+/// Sets the watched flag when `?watched=` is given, otherwise flips it.
+#[tracing::instrument(skip(state))]
+pub async fn toggle_watched(
+    State(state): State<AppState>,
+    Path(video_id): Path<String>,
+    Query(params): Query<WatchedQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let watched = if let Some(watched) = params.watched {
+        if !Video::set_watched(&state.pool, &video_id, watched).await? {
+            return Err(AppError::not_found("Video not found"));
+        }
+        watched
+    } else {
+        Video::toggle_watched(&state.pool, &video_id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Video not found"))?
+    };
+
+    tracing::info!("Marked video {} as {}", video_id, if watched { "watched" } else { "unwatched" });
+
+    let label = if watched { "Mark Unwatched" } else { "Mark Watched" };
+    Ok((
+        StatusCode::OK,
+        Html(format!(
+            r#"<button hx-post="/api/videos/{video_id}/watched?watched={}" hx-swap="outerHTML" class="secondary outline">{label}</button>"#,
+            !watched
+        ))
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "partials/video_info.html")]
+struct VideoInfoTemplate {
+    video: Video
+}
+
+/// Re-fetches a video's metadata into its row and cached thumbnail. For a
+/// completed download the NFO and `-thumb` sidecar are rewritten too; the
+/// media file is left alone. Responds with the refreshed title and metadata
+/// for HTMX to swap in.
+#[tracing::instrument(skip(state))]
+pub async fn refresh_video(
     State(state): State<AppState>,
     Path(video_id): Path<String>
 ) -> Result<impl IntoResponse, AppError> {
     let video = Video::find_by_id(&state.pool, &video_id)
         .await?
         .ok_or_else(|| AppError::not_found("Video not found"))?;
+    let yt_dlp = state.yt_dlp.read().await.clone();
+    let info = yt_dlp
+        .get_video_info(&video.webpage_url)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to fetch video info: {e}")))?;
+
+    #[allow(clippy::cast_possible_truncation)]
+    let duration_seconds = info.duration.map(|d| d as i64);
+    #[allow(clippy::cast_possible_wrap)]
+    let view_count = info.view_count.map(|v| v as i64);
+    Video::upsert(
+        &state.pool,
+        &video.id,
+        &video.channel_id,
+        &video.youtube_id,
+        &info.title,
+        info.description.as_deref(),
+        None,
+        duration_seconds,
+        info.upload_date.as_deref(),
+        view_count,
+        &video.webpage_url,
+        live_status(&info).as_deref(),
+        None,
+        &info.tags,
+        &info.categories
+    )
+    .await?;
+
+    let thumbnail_url = preferred_thumbnail(&info).map(String::from);
+    if let Some(ref url) = thumbnail_url {
+        let cache_id = video.youtube_id.replace(':', "_");
+        match crate::thumbnail::download_video_thumbnail(&cache_id, url).await {
+            Ok(path) => Video::update_thumbnail_by_youtube_id(&state.pool, &video.youtube_id, &path).await?,
+            Err(e) => tracing::warn!("Failed to refresh thumbnail for {}: {}", video.youtube_id, e)
+        }
+    }
+
+    let video = Video::find_by_id(&state.pool, &video_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Video not found"))?;
+
+    let completed = Download::find_by_video_id(&state.pool, &video_id)
+        .await?
+        .filter(|d| d.status_enum() == DownloadStatus::Completed);
+    if let Some(file_path) = completed.and_then(|d| d.file_path) {
+        let channel_name = Channel::find_by_id(&state.pool, &video.channel_id)
+            .await?
+            .map(|c| c.name)
+            .unwrap_or_default();
+        let mut meta = video_meta(&video);
+        meta.thumbnail_url = thumbnail_url;
+        if let Err(e) = refresh_sidecars(&state.pool, &file_path, meta, channel_name, &info).await {
+            tracing::warn!("Failed to rewrite sidecars for {}: {}", file_path, e);
+        }
+    }
+
+    tracing::info!("Refreshed metadata for video {}", video.title);
+    Ok(Html(VideoInfoTemplate { video }.render()?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubtitlesForm {
+    langs: Option<String>
+}
+
+/// Fetches subtitles for an already downloaded video without touching the
+/// media. They are saved next to it as `<video>.<lang>.<ext>`, where the
+/// player and media servers look for them. Languages default to the subtitle
+/// setting; a video without subtitles responds with an empty list.
+#[tracing::instrument(skip(state))]
+pub async fn download_video_subtitles(
+    State(state): State<AppState>,
+    Path(video_id): Path<String>,
+    Form(input): Form<SubtitlesForm>
+) -> Result<Json<serde_json::Value>, AppError> {
+    let video = Video::find_by_id(&state.pool, &video_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Video not found"))?;
+    let media = Download::find_by_video_id(&state.pool, &video_id)
+        .await?
+        .filter(|d| d.status_enum() == DownloadStatus::Completed)
+        .and_then(|d| d.file_path)
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::bad_request("Video has not been downloaded"))?;
+    let (Some(dir), Some(stem)) = (media.parent(), media.file_stem()) else {
+        return Err(AppError::internal(format!("Invalid media path: {}", media.display())));
+    };
+
+    let langs = match input.langs.as_deref().map(parse_subtitle_langs) {
+        Some(langs) if !langs.is_empty() => langs,
+        _ => Settings::get_subtitle_langs(&state.pool).await?
+    };
+    let yt_dlp = state.yt_dlp.read().await.clone();
+    let written = yt_dlp
+        .download_subtitles(&video.webpage_url, &langs, dir)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to download subtitles: {e}")))?;
+
+    // yt-dlp names the files by the platform's id, not the stored key
+    let prefix = format!("{}.", source_id(&video.youtube_id));
+    let mut files = Vec::new();
+    for path in written {
+        let Some(suffix) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_prefix(&prefix)) else {
+            continue;
+        };
+        let file_name = format!("{}.{suffix}", stem.to_string_lossy());
+        tokio::fs::rename(&path, dir.join(&file_name))
+            .await
+            .map_err(|e| AppError::internal(format!("Failed to move subtitle {}: {e}", path.display())))?;
+        files.push(file_name);
+    }
+
+    tracing::info!("Downloaded {} subtitle file(s) for video {}", files.len(), video.title);
+    Ok(Json(serde_json::json!({ "files": files })))
+}
+
+#[derive(Template)]
+#[template(path = "partials/download_item.html")]
+struct DownloadItemTemplate {
+    dl: DownloadWithVideo
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelForm {
+    label: String
+}
+
+/// Responds with the re-rendered download row so HTMX can swap it in place.
+#[tracing::instrument(skip(state))]
+pub async fn add_download_label(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>,
+    Form(input): Form<LabelForm>
+) -> Result<Html<String>, AppError> {
+    let label = normalize_label(&input.label).map_err(AppError::bad_request)?;
+    if Download::find_by_id(&state.pool, &download_id).await?.is_none() {
+        return Err(AppError::not_found("Download not found"));
+    }
+    Download::add_label(&state.pool, &download_id, &label).await?;
+    render_download_item(&state, &download_id).await
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn remove_download_label(
+    State(state): State<AppState>,
+    Path((download_id, label)): Path<(String, String)>
+) -> Result<Html<String>, AppError> {
+    if !Download::remove_label(&state.pool, &download_id, &label).await? {
+        return Err(AppError::not_found("Label not found"));
+    }
+    render_download_item(&state, &download_id).await
+}
+
+async fn render_download_item(state: &AppState, download_id: &str) -> Result<Html<String>, AppError> {
+    let dl = Download::find_with_video_by_id(&state.pool, download_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Download not found"))?;
+    Ok(Html(DownloadItemTemplate { dl }.render()?))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn start_download(
+    State(state): State<AppState>,
+    Path(video_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let response = match queue_download(&state, &video_id).await? {
+        QueueOutcome::InProgress => (StatusCode::OK, Html("Download already queued")),
+        QueueOutcome::AlreadyDownloaded => (StatusCode::OK, Html("Video already downloaded")),
+        QueueOutcome::Queued(_) => (StatusCode::ACCEPTED, Html("Download queued"))
+    };
+    Ok(response)
+}
+
+/// Live and upcoming streams only download with "Record live streams" on;
+/// otherwise premieres would pile up as failed downloads.
+async fn ensure_recordable(state: &AppState, video: &Video) -> Result<(), AppError> {
+    if (video.is_live() || video.is_upcoming())
+        && !Settings::get_bool(&state.pool, "record_live_streams").await?
+    {
+        return Err(AppError::bad_request(
+            "This video is a live stream or has not premiered yet. Enable \"Record live streams\" in Settings to download it."
+        ));
+    }
+    Ok(())
+}
+
+/// Result of asking for a video to be downloaded.
+pub enum QueueOutcome {
+    Queued(String),
+    InProgress,
+    AlreadyDownloaded
+}
+
+fn video_meta(video: &Video) -> VideoMeta {
+    VideoMeta {
+        tags: video.tag_list(),
+        categories: video.category_list(),
+        youtube_id: source_id(&video.youtube_id).to_string(),
+        site: media_site(&video.youtube_id).map(String::from),
+        title: video.title.clone(),
+        description: video.description.clone(),
+        duration_seconds: video.duration_seconds,
+        upload_date: video.upload_date.clone(),
+        thumbnail_url: video.thumbnail_url.clone(),
+        live_status: video.live_status.clone(),
+        video_id: video.id.clone()
+    }
+}
+
+/// Queues a download for `video_id` unless one is already pending, running
+/// or completed. Failed and skipped downloads are queued again.
+pub async fn queue_download(state: &AppState, video_id: &str) -> Result<QueueOutcome, AppError> {
+    let video = Video::find_by_id(&state.pool, video_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Video not found"))?;
 
     let channel = Channel::find_by_id(&state.pool, &video.channel_id)
         .await?
         .ok_or_else(|| AppError::not_found("Channel not found"))?;
 
-    if let Some(existing) = Download::find_by_video_id(&state.pool, &video_id).await? {
+    ensure_recordable(state, &video).await?;
+
+    if let Some(existing) = Download::find_by_video_id(&state.pool, video_id).await? {
         match existing.status_enum() {
-            DownloadStatus::Pending | DownloadStatus::Downloading => {
-                return Ok((StatusCode::OK, Html("Download already in progress")));
-            }
-            DownloadStatus::Completed => {
-                return Ok((StatusCode::OK, Html("Video already downloaded")));
+            DownloadStatus::Pending | DownloadStatus::Downloading | DownloadStatus::Paused => {
+                return Ok(QueueOutcome::InProgress);
             }
-            DownloadStatus::Failed => {}
+            DownloadStatus::Completed => return Ok(QueueOutcome::AlreadyDownloaded),
+            DownloadStatus::Failed | DownloadStatus::Skipped => {}
         }
     }
 
     let download_id = uuid7::uuid7().to_string();
-    Download::insert(&state.pool, &download_id, &video_id).await?;
+    // The check above races with concurrent requests; the insert does not
+    if !Download::insert(&state.pool, &download_id, video_id).await? {
+        return Ok(QueueOutcome::InProgress);
+    }
 
-    let video_meta = VideoMeta {
-        youtube_id: video.youtube_id,
-        title: video.title.clone(),
-        description: video.description,
-        duration_seconds: video.duration_seconds,
-        upload_date: video.upload_date
-    };
+    let video_meta = video_meta(&video);
 
     state
         .download_tx
         .send(DownloadCommand::Start {
             download_id: download_id.clone(),
             video_url: video.webpage_url,
+            filter: DownloadFilter::for_channel(&channel),
             channel_name: channel.name,
-            video_meta
+            video_meta: Box::new(video_meta)
         })
         .await
         .map_err(|e| AppError::internal(format!("Failed to queue download: {e}")))?;
 
     tracing::info!("Queued download {} for video {}", download_id, video.title);
 
-    Ok((StatusCode::ACCEPTED, Html("Download queued")))
+    Ok(QueueOutcome::Queued(download_id))
 }
 
 #[tracing::instrument(skip(state))]
@@ -244,22 +1075,129 @@ pub async fn cancel_download(
         .await?
         .ok_or_else(|| AppError::not_found("Download not found"))?;
 
-    if download.status_enum() != DownloadStatus::Downloading {
+    if !matches!(
+        download.status_enum(),
+        DownloadStatus::Pending | DownloadStatus::Downloading | DownloadStatus::Paused
+    ) {
         return Err(AppError::bad_request("Download is not in progress"));
     }
 
+    cancel_one(&state, &download_id).await?;
+
+    Ok((StatusCode::OK, Html("Download cancelled")))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn cancel_active_downloads(
+    State(state): State<AppState>
+) -> Result<impl IntoResponse, AppError> {
+    let mut downloads = Download::find_by_status(&state.pool, DownloadStatus::Downloading).await?;
+    downloads.extend(Download::find_by_status(&state.pool, DownloadStatus::Pending).await?);
+    downloads.extend(Download::find_by_status(&state.pool, DownloadStatus::Paused).await?);
+
+    let mut cancelled = 0;
+    for download in &downloads {
+        match cancel_one(&state, &download.id).await {
+            Ok(()) => cancelled += 1,
+            Err(e) => tracing::warn!("Failed to cancel download {}: {}", download.id, e.message)
+        }
+    }
+
+    tracing::info!("Cancelled {} of {} active downloads", cancelled, downloads.len());
+
+    Ok((StatusCode::OK, Html(format!("Cancelled {cancelled} downloads"))))
+}
+
+/// A queued download pauses right away, a running one once yt-dlp has exited;
+/// its partial files stay so a resume continues where it stopped.
+#[tracing::instrument(skip(state))]
+pub async fn pause_download(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let download = Download::find_by_id(&state.pool, &download_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Download not found"))?;
+
+    if !matches!(download.status_enum(), DownloadStatus::Pending | DownloadStatus::Downloading) {
+        return Err(AppError::bad_request("Download is not queued or in progress"));
+    }
+
+    let (ack, held) = tokio::sync::oneshot::channel();
+    state
+        .download_tx
+        .send(DownloadCommand::Pause { download_id: download_id.clone(), ack })
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to pause download: {e}")))?;
+
+    // Not yet handed to the worker; a Start that arrives later is dropped
+    // because the row is no longer pending
+    if !held.await.unwrap_or(false) {
+        Download::update_status(&state.pool, &download_id, DownloadStatus::Paused).await?;
+    }
+
+    Ok((StatusCode::OK, Html("Download paused")))
+}
+
+/// Queues a paused download again; yt-dlp continues its partial files unless
+/// the `no_continue` setting is on.
+#[tracing::instrument(skip(state))]
+pub async fn resume_download(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let download = Download::find_by_id(&state.pool, &download_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Download not found"))?;
+
+    if download.status_enum() != DownloadStatus::Paused {
+        return Err(AppError::bad_request("Download is not paused"));
+    }
+
+    Download::update_status(&state.pool, &download_id, DownloadStatus::Pending).await?;
+    let (ack, held) = tokio::sync::oneshot::channel();
+    state
+        .download_tx
+        .send(DownloadCommand::Resume { download_id: download_id.clone(), ack })
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to resume download: {e}")))?;
+
+    // Paused before a restart, so the worker no longer has it
+    if !held.await.unwrap_or(false) {
+        retry_one(&state, &download).await?;
+    }
+
+    Ok((StatusCode::OK, Html("Download resumed")))
+}
+
+/// A running download is marked failed by the worker once yt-dlp has exited
+/// and its partial files are gone; anything else is marked failed here, with
+/// the partial files a pause left behind removed.
+async fn cancel_one(state: &AppState, download_id: &str) -> Result<(), AppError> {
+    let (ack, running) = tokio::sync::oneshot::channel();
     state
         .download_tx
         .send(DownloadCommand::Cancel {
-            download_id: download_id.clone()
+            download_id: download_id.to_string(),
+            ack
         })
         .await
         .map_err(|e| AppError::internal(format!("Failed to cancel download: {e}")))?;
 
-    Download::update_status(&state.pool, &download_id, DownloadStatus::Failed).await?;
-    Download::update_failed(&state.pool, &download_id, "Cancelled by user").await?;
+    if running.await.unwrap_or(false) {
+        return Ok(());
+    }
+    let paused_destination = Download::find_by_id(&state.pool, download_id)
+        .await?
+        .filter(|d| d.status_enum() == DownloadStatus::Paused)
+        .and_then(|d| d.file_path);
+    if let Some(destination) = paused_destination {
+        remove_partial_files(std::path::Path::new(&destination)).await;
+    }
+    Download::update_status(&state.pool, download_id, DownloadStatus::Failed).await?;
+    Download::update_failed(&state.pool, download_id, "Cancelled by user").await?;
 
-    Ok((StatusCode::OK, Html("Download cancelled")))
+    Ok(())
 }
 
 #[tracing::instrument(skip(state))]
@@ -271,62 +1209,264 @@ pub async fn retry_download(
         .await?
         .ok_or_else(|| AppError::not_found("Download not found"))?;
 
-    if download.status_enum() != DownloadStatus::Failed {
+    if !matches!(download.status_enum(), DownloadStatus::Failed | DownloadStatus::Skipped) {
         return Err(AppError::bad_request("Download has not failed"));
     }
 
+    retry_one(&state, &download).await?;
+
+    Ok((StatusCode::OK, Html("Download retrying")))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn retry_failed_downloads(
+    State(state): State<AppState>
+) -> Result<impl IntoResponse, AppError> {
+    let downloads = Download::find_by_status(&state.pool, DownloadStatus::Failed).await?;
+
+    let mut retried = 0;
+    for download in &downloads {
+        match retry_one(&state, download).await {
+            Ok(()) => retried += 1,
+            Err(e) => tracing::warn!("Failed to retry download {}: {}", download.id, e.message)
+        }
+    }
+
+    tracing::info!("Retrying {} of {} failed downloads", retried, downloads.len());
+
+    Ok((StatusCode::OK, Html(format!("Retrying {retried} downloads"))))
+}
+
+/// Queues the downloads a previous run left pending or downloading, so a
+/// restart does not lose them. yt-dlp resumes their partial files unless
+/// the `no_continue` setting is on. Returns how many were queued.
+pub async fn resume_unfinished_downloads(state: &AppState) -> Result<usize, AppError> {
+    let interrupted = Download::reset_interrupted(&state.pool).await?;
+    let downloads = Download::find_pending(&state.pool).await?;
+
+    let mut resumed = 0;
+    for download in &downloads {
+        match retry_one(state, download).await {
+            Ok(()) => resumed += 1,
+            Err(e) => {
+                tracing::warn!("Failed to resume download {}: {}", download.id, e.message);
+                let _ = Download::update_failed(&state.pool, &download.id, &e.message).await;
+            }
+        }
+    }
+
+    tracing::info!(
+        "Resumed {} of {} unfinished downloads ({} interrupted mid-download)",
+        resumed,
+        downloads.len(),
+        interrupted
+    );
+    Ok(resumed)
+}
+
+async fn retry_one(state: &AppState, download: &Download) -> Result<(), AppError> {
     let video = Video::find_by_id(&state.pool, &download.video_id)
         .await?
         .ok_or_else(|| AppError::not_found("Video not found"))?;
 
+    ensure_recordable(state, &video).await?;
+
     let channel = Channel::find_by_id(&state.pool, &video.channel_id)
         .await?
         .ok_or_else(|| AppError::not_found("Channel not found"))?;
 
-    Download::update_status(&state.pool, &download_id, DownloadStatus::Pending).await?;
+    match Download::update_status(&state.pool, &download.id, DownloadStatus::Pending).await {
+        Ok(()) => {}
+        Err(e) if e.as_database_error().is_some_and(sqlx::error::DatabaseError::is_unique_violation) => {
+            return Err(AppError::bad_request("Download already queued"));
+        }
+        Err(e) => return Err(e.into())
+    }
+    if matches!(download.status_enum(), DownloadStatus::Failed | DownloadStatus::Skipped) {
+        Download::reset_attempts(&state.pool, &download.id).await?;
+    }
 
-    let video_meta = VideoMeta {
-        youtube_id: video.youtube_id,
-        title: video.title,
-        description: video.description,
-        duration_seconds: video.duration_seconds,
-        upload_date: video.upload_date
-    };
+    let video_meta = video_meta(&video);
 
     state
         .download_tx
         .send(DownloadCommand::Start {
-            download_id: download_id.clone(),
+            download_id: download.id.clone(),
             video_url: video.webpage_url,
+            filter: DownloadFilter::for_channel(&channel),
             channel_name: channel.name,
-            video_meta
+            video_meta: Box::new(video_meta)
         })
         .await
         .map_err(|e| AppError::internal(format!("Failed to retry download: {e}")))?;
 
-    Ok((StatusCode::OK, Html("Download retrying")))
+    Ok(())
+}
+
+/// Pending downloads in the order they will start.
+pub async fn download_queue(State(state): State<AppState>) -> Result<Json<Vec<QueueEntry>>, AppError> {
+    Ok(Json(Download::queue(&state.pool).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriorityForm {
+    priority: String
+}
+
+/// `top` and `bottom` move the download to the front or back of the queue;
+/// anything else must be an integer, higher starting sooner (the default is
+/// 0). Responds with the updated queue.
+#[tracing::instrument(skip(state))]
+pub async fn set_download_priority(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>,
+    Form(input): Form<PriorityForm>
+) -> Result<Json<Vec<QueueEntry>>, AppError> {
+    let priority = match input.priority.trim() {
+        "top" => Download::queue_priority_bounds(&state.pool).await?.0.saturating_add(1),
+        "bottom" => Download::queue_priority_bounds(&state.pool).await?.1.saturating_sub(1),
+        value => value
+            .parse()
+            .map_err(|_| AppError::bad_request("Priority must be top, bottom or an integer"))?
+    };
+    if !Download::set_priority(&state.pool, &download_id, priority).await? {
+        return Err(match Download::find_by_id(&state.pool, &download_id).await? {
+            Some(_) => AppError::bad_request("Download is not queued"),
+            None => AppError::not_found("Download not found")
+        });
+    }
+    Ok(Json(Download::queue(&state.pool).await?))
+}
+
+/// Combined speed and size-weighted percent of the in-flight downloads.
+pub async fn download_summary(State(state): State<AppState>) -> Json<DownloadSummary> {
+    let states = state.download_states.read().await;
+    Json(DownloadSummary::from_states(states.values()))
 }
 
 pub async fn active_downloads(
     State(state): State<AppState>
 ) -> Json<serde_json::Value> {
     let states = state.download_states.read().await;
-    let active_count = states.values().filter(|s| {
-        s.status == "started" || s.status == "progress" || s.status == "processing"
-    }).count();
+    let active_count = states.values().filter(|s| s.is_active()).count();
     Json(serde_json::json!({
         "downloads": *states,
         "active_count": active_count
     }))
 }
 
+#[derive(Debug, Serialize)]
+pub struct BinaryHealth {
+    available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>
+}
+
+impl BinaryHealth {
+    fn from_version(version: Option<String>) -> Self {
+        Self { available: version.is_some(), version, error: None }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    healthy: bool,
+    database: bool,
+    yt_dlp: BinaryHealth,
+    ffmpeg: BinaryHealth,
+    ffprobe: BinaryHealth,
+    active_downloads: usize
+}
+
+/// Liveness/readiness probe. Responds `503` when yt-dlp or the database is
+/// unavailable; missing ffmpeg or ffprobe is reported but not fatal.
+#[tracing::instrument(skip(state))]
+pub async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    let database = sqlx::query("SELECT 1").execute(&state.pool).await.is_ok();
+
+    // The same cache as the settings page, so both report the same version
+    let ytdlp_bin = Settings::get_ytdlp_path(&state.pool)
+        .await
+        .unwrap_or_else(|_| "yt-dlp".to_string());
+    let yt_dlp = match state.version_cache.get(&ytdlp_bin).await {
+        Some(version) => BinaryHealth::from_version(Some(version)),
+        None => BinaryHealth {
+            available: false,
+            version: None,
+            error: Some(format!("yt-dlp not found or not executable: {ytdlp_bin}"))
+        }
+    };
+
+    let ffmpeg_bin = Settings::get_ffmpeg_path(&state.pool)
+        .await
+        .unwrap_or_else(|_| "ffmpeg".to_string());
+    let ffprobe_bin = Settings::get_ffprobe_path(&state.pool)
+        .await
+        .unwrap_or_else(|_| "ffprobe".to_string());
+    let ffmpeg = BinaryHealth::from_version(state.version_cache.get(&ffmpeg_bin).await);
+    let ffprobe = BinaryHealth::from_version(state.version_cache.get(&ffprobe_bin).await);
+
+    let active_downloads = state
+        .download_states
+        .read()
+        .await
+        .values()
+        .filter(|s| s.is_active())
+        .count();
+
+    let healthy = database && yt_dlp.available;
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(HealthReport { healthy, database, yt_dlp, ffmpeg, ffprobe, active_downloads })
+    )
+}
+
+/// Prometheus scrape endpoint.
+#[tracing::instrument(skip(state))]
+pub async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let channels: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channels")
+        .fetch_one(&state.pool)
+        .await?;
+    let videos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM videos")
+        .fetch_one(&state.pool)
+        .await?;
+    let downloads_by_status = Download::count_by_status(&state.pool).await?;
+    let active_downloads = state
+        .download_states
+        .read()
+        .await
+        .values()
+        .filter(|s| s.is_active())
+        .count();
+
+    let gauges = Gauges { channels, videos, downloads_by_status, active_downloads };
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(&state.metrics, &gauges)
+    ))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn self_test(
+    State(state): State<AppState>
+) -> Result<Json<SelfTestReport>, AppError> {
+    let yt_dlp = state.yt_dlp.read().await.clone();
+    let ffprobe_bin = Settings::get_ffprobe_path(&state.pool).await?;
+
+    let report = self_test::run(&yt_dlp, &ffprobe_bin).await;
+    tracing::info!("Self-test finished (passed: {})", report.passed);
+
+    Ok(Json(report))
+}
+
 pub async fn download_count(
     State(state): State<AppState>
 ) -> Html<String> {
     let states = state.download_states.read().await;
-    let count = states.values().filter(|s| {
-        s.status == "started" || s.status == "progress" || s.status == "processing"
-    }).count();
+    let count = states.values().filter(|s| s.is_active()).count();
     if count > 0 {
         Html(format!(r#"<span class="badge">{count}</span>"#))
     } else {
@@ -334,11 +1474,206 @@ pub async fn download_count(
     }
 }
 
+/// Rejects invalid settings before anything is saved, returning the parsed
+/// environment variables if the form included them.
+fn validate_settings_form(input: &SettingsForm) -> Result<Option<Vec<(String, String)>>, String> {
+    if let Some(ref template) = input.output_template {
+        validate_output_template(template)?;
+    }
+    if let Some(ref fragments) = input.concurrent_fragments {
+        if !fragments.trim().is_empty() && !fragments.trim().parse::<u32>().is_ok_and(|n| n > 0) {
+            return Err("Concurrent fragments must be a positive number".to_string());
+        }
+    }
+    if let Some(ref length) = input.trim_filenames {
+        if !length.trim().is_empty() && !length.trim().parse::<u32>().is_ok_and(|n| n > 0) {
+            return Err("Maximum filename length must be a positive number".to_string());
+        }
+    }
+    if let Some(ref tolerance) = input.integrity_tolerance {
+        if !tolerance.trim().is_empty() && !tolerance.trim().parse::<u32>().is_ok_and(|n| n <= 100) {
+            return Err("Integrity tolerance must be a percentage from 0 to 100".to_string());
+        }
+    }
+    if let Some(ref attempts) = input.retry_max_attempts {
+        if !attempts.trim().is_empty() && !attempts.trim().parse::<u32>().is_ok_and(|n| (1..=10).contains(&n)) {
+            return Err("Download attempts must be a number from 1 to 10".to_string());
+        }
+    }
+    if let Some(ref delay) = input.retry_base_delay {
+        if !delay.trim().is_empty() && !delay.trim().parse::<u64>().is_ok_and(|n| (1..=3600).contains(&n)) {
+            return Err("Retry delay must be from 1 to 3600 seconds".to_string());
+        }
+    }
+    if let Some(ref height) = input.max_height {
+        if !height.trim().is_empty() && !height.trim().parse::<u32>().is_ok_and(|n| n > 0) {
+            return Err("Maximum resolution must be a positive number".to_string());
+        }
+    }
+    if let Some(ref rate) = input.rate_limit {
+        validate_rate(rate)?;
+    }
+    if let Some(ref rate) = input.night_rate_limit {
+        validate_rate(rate)?;
+    }
+    validate_night_window(input.night_start.as_deref(), input.night_end.as_deref())?;
+    if let Some(ref rate) = input.throttled_rate {
+        validate_rate(rate)?;
+    }
+    if let Some(ref scheme) = input.nfo_episode_scheme {
+        if EpisodeScheme::parse(scheme).is_none() {
+            return Err(format!("Unknown episode numbering: {}", scheme.trim()));
+        }
+    }
+    if let Some(ref layout) = input.directory_layout {
+        if DirectoryLayout::parse(layout).is_none() {
+            return Err(format!("Unknown directory layout: {}", layout.trim()));
+        }
+    }
+    if let Some(ref format) = input.thumbnail_format {
+        if !THUMBNAIL_FORMATS.contains(&format.trim()) {
+            return Err(format!("Unsupported thumbnail format: {}", format.trim()));
+        }
+    }
+    for (name, value, allowed) in [
+        ("remux format", &input.remux_video, &REMUX_FORMATS[..]),
+        ("audio format", &input.audio_format, &AUDIO_FORMATS[..]),
+        ("audio-only format", &input.audio_only_format, &AUDIO_FORMATS[..]),
+        ("subtitle format", &input.subtitle_format, &SUBTITLE_FORMATS[..])
+    ] {
+        if let Some(value) = value.as_deref().map(str::trim) {
+            if !value.is_empty() && !allowed.contains(&value) {
+                return Err(format!("Unsupported {name}: {value}"));
+            }
+        }
+    }
+    if let Some(ref size) = input.max_filesize {
+        if !size.trim().is_empty() && !yt_dlp::is_valid_filesize(size.trim()) {
+            return Err(format!("Invalid max file size: {}", size.trim()));
+        }
+    }
+    if let Some(ref quality) = input.audio_quality {
+        validate_audio_quality(quality)?;
+    }
+    input.env_vars.as_deref().map(parse_env_vars).transpose()
+}
+
+/// Checks the yt-dlp the form configures, with its binary and environment,
+/// can use the impersonation target; without `curl_cffi` every request would
+/// fail.
+async fn check_impersonate(
+    state: &AppState,
+    input: &SettingsForm,
+    env_vars: Option<&[(String, String)]>,
+    target: &str
+) -> Result<(), AppError> {
+    let mut yt_dlp = state.yt_dlp.read().await.clone();
+    if let Some(path) = input.ytdlp_path.as_deref().filter(|path| !path.is_empty()) {
+        yt_dlp.set_binary(PathBuf::from(path));
+    }
+    for (key, value) in env_vars.unwrap_or_default() {
+        yt_dlp.set_env(key.clone(), value.clone());
+    }
+    match yt_dlp.check_impersonate(target).await {
+        Ok(()) => Ok(()),
+        Err(e @ yt_dlp::Error::ImpersonationUnavailable(_)) => {
+            Err(AppError::bad_request(format!("Cannot impersonate: {e}")))
+        }
+        Err(e) => {
+            tracing::warn!("Could not verify impersonate target {}: {}", target, e);
+            Ok(())
+        }
+    }
+}
+
+/// Plain values that only take effect on the next download, stored trimmed.
+async fn save_values(pool: &sqlx::SqlitePool, input: &SettingsForm) -> Result<(), sqlx::Error> {
+    let values = [
+        ("concurrent_fragments", &input.concurrent_fragments),
+        ("trim_filenames", &input.trim_filenames),
+        ("integrity_tolerance", &input.integrity_tolerance),
+        ("retry_max_attempts", &input.retry_max_attempts),
+        ("retry_base_delay", &input.retry_base_delay),
+        ("rate_limit", &input.rate_limit),
+        ("night_rate_limit", &input.night_rate_limit),
+        ("night_start", &input.night_start),
+        ("night_end", &input.night_end),
+        ("throttled_rate", &input.throttled_rate),
+        ("max_filesize", &input.max_filesize),
+        ("output_template", &input.output_template),
+        ("max_height", &input.max_height),
+        ("remux_video", &input.remux_video),
+        ("audio_format", &input.audio_format),
+        ("audio_only_format", &input.audio_only_format),
+        ("audio_quality", &input.audio_quality),
+        ("subtitle_langs", &input.subtitle_langs),
+        ("subtitle_format", &input.subtitle_format),
+        ("thumbnail_format", &input.thumbnail_format),
+        ("nfo_episode_scheme", &input.nfo_episode_scheme),
+        ("directory_layout", &input.directory_layout)
+    ];
+    for (key, value) in values {
+        if let Some(value) = value {
+            Settings::set(pool, key, value.trim()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Unchecked checkboxes are omitted from the form submission, so every toggle
+/// is written on each save.
+async fn save_toggles(pool: &sqlx::SqlitePool, input: &SettingsForm) -> Result<(), sqlx::Error> {
+    let toggles = [
+        ("download_subtitles", &input.download_subtitles),
+        ("include_auto_subs", &input.include_auto_subs),
+        ("restrict_filenames", &input.restrict_filenames),
+        ("windows_filenames", &input.windows_filenames),
+        ("recode_video_fallback", &input.recode_video_fallback),
+        ("keep_info_json", &input.keep_info_json),
+        ("embed_thumbnail", &input.embed_thumbnail),
+        ("record_live_streams", &input.record_live_streams),
+        ("set_mtime", &input.set_mtime),
+        ("no_continue", &input.no_continue),
+        ("estimate_size", &input.estimate_size)
+    ];
+    for (key, value) in toggles {
+        Settings::set(pool, key, if value.is_some() { "true" } else { "false" }).await?;
+    }
+    Ok(())
+}
+
 #[tracing::instrument(skip(state))]
 pub async fn update_settings(
     State(state): State<AppState>,
     Form(input): Form<SettingsForm>
 ) -> Result<impl IntoResponse, AppError> {
+    let env_vars = validate_settings_form(&input).map_err(AppError::bad_request)?;
+
+    // Every check that can reject the form runs before anything is saved, so
+    // a 400 never leaves the settings half-written
+    let cache_dir = input.cache_dir.as_deref().map(str::trim);
+    let cache_path = match cache_dir {
+        Some(dir) if !dir.is_empty() => Some(ensure_cache_dir(dir).await.map_err(AppError::bad_request)?),
+        _ => None
+    };
+    let config = input.ytdlp_config.as_deref().map(str::trim);
+    let config_path = match config {
+        Some(config) if !config.is_empty() => {
+            Some(check_ytdlp_config(config).await.map_err(AppError::bad_request)?)
+        }
+        _ => None
+    };
+    let temp_dir = input.temp_dir.as_deref().map(str::trim);
+    if let Some(temp_dir) = temp_dir.filter(|dir| !dir.is_empty()) {
+        tokio::fs::create_dir_all(temp_dir)
+            .await
+            .map_err(|e| AppError::bad_request(format!("Failed to create temp directory {temp_dir}: {e}")))?;
+    }
+    let impersonate = input.impersonate.as_deref().map(str::trim);
+    if let Some(target) = impersonate.filter(|target| !target.is_empty()) {
+        check_impersonate(&state, &input, env_vars.as_deref(), target).await?;
+    }
+
     Settings::set(&state.pool, "download_path", &input.download_path).await?;
     Settings::set(
         &state.pool,
@@ -347,6 +1682,29 @@ pub async fn update_settings(
     )
     .await?;
 
+    if let Some(cache_dir) = cache_dir {
+        Settings::set(&state.pool, "cache_dir", cache_dir).await?;
+        state.yt_dlp.write().await.set_cache_dir(cache_path);
+    }
+
+    if let Some(config) = config {
+        Settings::set(&state.pool, "ytdlp_config", config).await?;
+        state.yt_dlp.write().await.set_config_location(config_path);
+    }
+
+    if let Some(temp_dir) = temp_dir {
+        Settings::set(&state.pool, "temp_dir", temp_dir).await?;
+    }
+
+    if let Some(target) = impersonate {
+        Settings::set(&state.pool, "impersonate", target).await?;
+        state
+            .yt_dlp
+            .write()
+            .await
+            .set_impersonate((!target.is_empty()).then(|| target.to_string()));
+    }
+
     if let Some(ref args_str) = input.extractor_args {
         Settings::set(&state.pool, "extractor_args", args_str).await?;
         let parsed = parse_extractor_args(args_str);
@@ -354,6 +1712,32 @@ pub async fn update_settings(
         yt_dlp.set_extra_args(parsed);
     }
 
+    if let (Some(ref raw), Some(vars)) = (&input.env_vars, env_vars) {
+        let previous = parse_env_vars(&Settings::get_env_vars(&state.pool).await?).unwrap_or_default();
+        Settings::set(&state.pool, "env_vars", raw).await?;
+        let mut yt_dlp = state.yt_dlp.write().await;
+        for (key, _) in previous {
+            yt_dlp.remove_env(&key);
+        }
+        for (key, value) in vars {
+            yt_dlp.set_env(key, value);
+        }
+    }
+
+    save_values(&state.pool, &input).await?;
+    save_toggles(&state.pool, &input).await?;
+
+    save_binary_paths(&state, &input).await?;
+
+    state.version_cache.invalidate().await;
+
+    tracing::info!("Updated settings");
+
+    Ok((StatusCode::OK, Html("Settings saved")))
+}
+
+/// Points the shared client at the configured ffmpeg, yt-dlp and deno.
+async fn save_binary_paths(state: &AppState, input: &SettingsForm) -> Result<(), sqlx::Error> {
     if let Some(ref path) = input.ffmpeg_path {
         Settings::set(&state.pool, "ffmpeg_path", path).await?;
         let mut yt_dlp = state.yt_dlp.write().await;
@@ -387,10 +1771,7 @@ pub async fn update_settings(
             }
         }
     }
-
-    tracing::info!("Updated settings");
-
-    Ok((StatusCode::OK, Html("Settings saved")))
+    Ok(())
 }
 
 #[tracing::instrument(skip(state, multipart))]
@@ -458,6 +1839,65 @@ pub async fn delete_cookies(
     Ok((StatusCode::OK, Html("Cookies deleted")))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CookiesBrowserForm {
+    browser: String,
+    profile: Option<String>
+}
+
+/// Reads cookies straight from a local browser profile. An uploaded cookies
+/// file still takes precedence while it is loaded.
+#[tracing::instrument(skip(state))]
+pub async fn update_cookies_browser(
+    State(state): State<AppState>,
+    Form(input): Form<CookiesBrowserForm>
+) -> Result<impl IntoResponse, AppError> {
+    let spec = cookies_browser_spec(&input.browser, input.profile.as_deref());
+    if let Some(ref spec) = spec {
+        if !yt_dlp::is_valid_browser_spec(spec) {
+            return Err(AppError::bad_request(format!("Unsupported browser: {spec}")));
+        }
+    }
+
+    Settings::set(&state.pool, "cookies_from_browser", spec.as_deref().unwrap_or_default()).await?;
+    state.yt_dlp.write().await.set_cookies_from_browser(spec);
+
+    tracing::info!("Updated browser cookies");
+
+    Ok((StatusCode::OK, Html("Browser cookies saved")))
+}
+
+/// Joins a browser and optional profile into `BROWSER[:PROFILE]`; a blank
+/// browser turns browser cookies off.
+fn cookies_browser_spec(browser: &str, profile: Option<&str>) -> Option<String> {
+    let browser = browser.trim();
+    if browser.is_empty() {
+        return None;
+    }
+    match profile.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(profile) => Some(format!("{browser}:{profile}")),
+        None => Some(browser.to_string())
+    }
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn update_ytdlp(
+    State(state): State<AppState>
+) -> Result<impl IntoResponse, AppError> {
+    let yt_dlp = state.yt_dlp.read().await.clone();
+
+    let version = yt_dlp.update_binary().await.map_err(|e| match e {
+        yt_dlp::Error::UpdateUnsupported(_) => AppError::bad_request(e.to_string()),
+        _ => AppError::internal(e.to_string())
+    })?;
+
+    state.version_cache.invalidate().await;
+
+    tracing::info!("yt-dlp updated to {}", version);
+
+    Ok((StatusCode::OK, Html(format!("yt-dlp is now at {version}"))))
+}
+
 pub fn parse_extractor_args(input: &str) -> Vec<String> {
     let joined: Vec<&str> = input
         .lines()
@@ -473,23 +1913,704 @@ pub fn parse_extractor_args(input: &str) -> Vec<String> {
     ]
 }
 
-pub async fn check_binary_version(binary: &str) -> Option<String> {
-    let output = tokio::process::Command::new(binary)
-        .arg("--version")
-        .output()
+/// Parses `KEY=VALUE` lines for the yt-dlp subprocess environment. Blank
+/// lines and `#` comments are skipped; values may be empty.
+pub fn parse_env_vars(input: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("Line {}: expected KEY=VALUE", index + 1));
+        };
+        let key = key.trim();
+        let valid_key = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_key {
+            return Err(format!("Line {}: invalid variable name \"{key}\"", index + 1));
+        }
+        vars.push((key.to_string(), value.trim().to_string()));
+    }
+    Ok(vars)
+}
+
+/// Creates the yt-dlp cache directory if it does not exist yet.
+pub async fn ensure_cache_dir(path: &str) -> Result<PathBuf, String> {
+    let dir = resolve_cache_dir(path, std::env::var_os("HOME").map(PathBuf::from))?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create cache directory {path}: {e}"))?;
+    Ok(dir)
+}
+
+/// Expands a leading `~` against `home`; anything else must be absolute,
+/// since the server's working directory is not something users pick.
+fn resolve_cache_dir(path: &str, home: Option<PathBuf>) -> Result<PathBuf, String> {
+    let dir = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => home
+            .ok_or_else(|| format!("Cannot expand {path}: HOME is not set"))?
+            .join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path)
+    };
+    if dir.is_absolute() {
+        Ok(dir)
+    } else {
+        Err(format!("Cache directory must be an absolute path: {path}"))
+    }
+}
+
+/// A yt-dlp config must be an existing file, or a directory yt-dlp looks
+/// for `yt-dlp.conf` in.
+pub async fn check_ytdlp_config(path: &str) -> Result<PathBuf, String> {
+    tokio::fs::metadata(path)
         .await
-        .ok()?;
-    if output.status.success() {
-        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .map(|_| PathBuf::from(path))
+        .map_err(|e| format!("Cannot read yt-dlp config {path}: {e}"))
+}
+
+/// Accepts yt-dlp rates such as `50K`, `4.2M` or plain bytes per second.
+/// An empty value is allowed and means unset.
+pub fn validate_rate(rate: &str) -> Result<(), String> {
+    let rate = rate.trim();
+    if rate.is_empty() {
+        return Ok(());
+    }
+    let number = rate
+        .strip_suffix(|c: char| matches!(c.to_ascii_uppercase(), 'K' | 'M' | 'G'))
+        .unwrap_or(rate);
+    if number.parse::<f64>().is_ok_and(|n| n > 0.0 && n.is_finite()) {
+        Ok(())
+    } else {
+        Err(format!("Invalid rate \"{rate}\"; use a number with an optional K, M or G suffix"))
+    }
+}
+
+/// The night window needs both ends as `HH:MM`, or neither to turn it off.
+pub fn validate_night_window(start: Option<&str>, end: Option<&str>) -> Result<(), String> {
+    let start = start.map(str::trim).unwrap_or_default();
+    let end = end.map(str::trim).unwrap_or_default();
+    if start.is_empty() && end.is_empty() {
+        return Ok(());
+    }
+    if parse_clock_time(start).is_some() && parse_clock_time(end).is_some() {
+        Ok(())
+    } else {
+        Err("The night window needs both a start and an end time as HH:MM".to_string())
+    }
+}
+
+/// Accepts yt-dlp's VBR scale `0` (best) to `10` (worst) or a bitrate such
+/// as `128K`. An empty value is allowed and leaves yt-dlp's default.
+pub fn validate_audio_quality(quality: &str) -> Result<(), String> {
+    let quality = quality.trim();
+    if quality.is_empty() {
+        return Ok(());
+    }
+    let valid = match quality.strip_suffix(['K', 'k']) {
+        Some(bitrate) => bitrate.parse::<u32>().is_ok_and(|n| n > 0),
+        None => quality.parse::<u32>().is_ok_and(|n| n <= 10)
+    };
+    if valid {
+        Ok(())
     } else {
-        None
+        Err(format!("Invalid audio quality \"{quality}\"; use 0 (best) to 10 (worst) or a bitrate like 128K"))
     }
 }
 
+pub fn validate_output_template(template: &str) -> Result<(), String> {
+    let template = template.trim();
+    if template.is_empty() {
+        return Ok(());
+    }
+    if !template.contains("%(ext)s") {
+        return Err("Output template must contain %(ext)s".to_string());
+    }
+    if template.starts_with('/') || template.split('/').any(|part| part == "..") {
+        return Err("Output template must be relative to the channel folder".to_string());
+    }
+    yt_dlp::DownloadOptions::default()
+        .with_validated_template(template)
+        .map(|_| ())
+        .map_err(|e| match e {
+            yt_dlp::Error::InvalidTemplate(reason) => format!("Invalid output template: {reason}"),
+            other => other.to_string()
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A stand-in yt-dlp that answers the first-entry metadata fetch, a single
+    /// video's info and the impersonation listing (without `curl_cffi`) and
+    /// fails anything else, so a full enumeration shows up as an error.
+    #[cfg(unix)]
+    async fn state_with_fake_ytdlp(dir: &std::path::Path) -> AppState {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = dir.join("yt-dlp");
+        tokio::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             case \"$*\" in\n\
+             *--list-impersonate-targets*) printf 'Client OS Source\\n------\\nChrome - curl_cffi (unavailable)\\n' ;;\n\
+             *'--playlist-items 1'*) echo '{\"id\": \"v1\", \"title\": \"First\", \"playlist_id\": \"UC123\", \"channel_id\": \"UC123\", \"extractor_key\": \"YoutubeTab\"}' ;;\n\
+             *'--sub-langs xx'*) echo '[info] There are no subtitles for the requested languages' ;;\n\
+             *--write-subs*) while [ \"$1\" != -o ]; do shift; done; out=$(dirname \"$2\"); \
+               echo WEBVTT > \"$out/yt1.en.vtt\"; echo \"[info] Writing video subtitles to: $out/yt1.en.vtt\" ;;\n\
+             *--no-playlist*) echo '{\"id\": \"yt1\", \"title\": \"Edited\", \"description\": \"New plot\", \"upload_date\": \"20240102\", \"view_count\": 7}' ;;\n\
+             *) echo 'full enumeration' >&2; exit 1 ;;\n\
+             esac\n"
+        )
+        .await
+        .unwrap();
+        tokio::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).await.unwrap();
+
+        test_state(yt_dlp::YtDlp::with_binary(script)).await
+    }
+
+    /// Incremental syncs end the listing itself: yt-dlp is killed before it
+    /// lists the video after the known ones, which only a full sync reaches.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_full_reaches_entries_incremental_does_not() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("toobarr-sync-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let listed_all = dir.join("listed-all");
+        let script = dir.join("yt-dlp");
+        tokio::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\n\
+                 for i in 1 2 3 4 5 6 7; do printf '{{\"id\": \"old%s\", \"title\": \"Old\"}}\\n' $i; done\n\
+                 sleep 1\n\
+                 echo '{{\"id\": \"new1\", \"title\": \"New\"}}'\n\
+                 touch '{}'\n",
+                listed_all.display()
+            )
+        )
+        .await
+        .unwrap();
+        tokio::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).await.unwrap();
+        let state = test_state(yt_dlp::YtDlp::with_binary(script)).await;
+
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        for i in 1..=7 {
+            let id = format!("old{i}");
+            Video::upsert(
+                &state.pool, &id, "c1", &id, "Old", None, None, None, None, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+        }
+        let sync = |full: bool| {
+            sync_channel(
+                State(state.clone()),
+                Path("c1".to_string()),
+                Query(SyncQuery { full, detailed: false, limit: None })
+            )
+        };
+
+        sync(false).await.unwrap();
+        assert!(Video::find_by_youtube_id(&state.pool, "new1").await.unwrap().is_none());
+        assert!(!listed_all.exists());
+
+        sync(true).await.unwrap();
+        assert!(Video::find_by_youtube_id(&state.pool, "new1").await.unwrap().is_some());
+        assert!(listed_all.exists());
+        let channel = Channel::find_by_id(&state.pool, "c1").await.unwrap().unwrap();
+        assert_eq!(channel.video_count, Some(8));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_limit_lists_newest_entries() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("toobarr-sync-limit-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let script = dir.join("yt-dlp");
+        tokio::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             case \"$*\" in\n\
+             *'--playlist-end 2'*) n=2 ;;\n\
+             *) n=3 ;;\n\
+             esac\n\
+             for i in $(seq $n); do printf '{\"id\": \"v%s\", \"title\": \"Video\"}\\n' $i; done\n"
+        )
+        .await
+        .unwrap();
+        tokio::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).await.unwrap();
+        let state = test_state(yt_dlp::YtDlp::with_binary(script)).await;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        let sync = |limit: u32| {
+            sync_channel(
+                State(state.clone()),
+                Path("c1".to_string()),
+                Query(SyncQuery { full: false, detailed: false, limit: Some(limit) })
+            )
+        };
+
+        assert_eq!(sync(0).await.err().unwrap().status, StatusCode::BAD_REQUEST);
+        sync(2).await.unwrap();
+        assert!(Video::find_by_youtube_id(&state.pool, "v2").await.unwrap().is_some());
+        assert!(Video::find_by_youtube_id(&state.pool, "v3").await.unwrap().is_none());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_download_concurrent_calls_queue_once() {
+        let mut state = test_state(yt_dlp::YtDlp::default()).await;
+        let (download_tx, mut download_rx) = tokio::sync::mpsc::channel(8);
+        state.download_tx = download_tx;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Video", None, None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+
+        let outcomes = futures::future::join_all((0..4).map(|_| queue_download(&state, "v1"))).await;
+
+        let queued = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Ok(QueueOutcome::Queued(_))))
+            .count();
+        let in_progress = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Ok(QueueOutcome::InProgress)))
+            .count();
+        assert_eq!((queued, in_progress), (1, 3));
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM downloads WHERE video_id = 'v1'")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        assert!(download_rx.try_recv().is_ok());
+        assert!(download_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resume_unfinished_downloads() {
+        let mut state = test_state(yt_dlp::YtDlp::default()).await;
+        let (download_tx, mut download_rx) = tokio::sync::mpsc::channel(8);
+        state.download_tx = download_tx;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        for (video, download) in [("v1", "d1"), ("v2", "d2"), ("v3", "d3")] {
+            Video::upsert(
+                &state.pool, video, "c1", video, "Video", None, None, None, None, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+            Download::insert(&state.pool, download, video).await.unwrap();
+        }
+        Download::update_status(&state.pool, "d1", DownloadStatus::Downloading).await.unwrap();
+        Download::update_completed(&state.pool, "d3", "/tmp/v3.mp4", None).await.unwrap();
+
+        assert_eq!(resume_unfinished_downloads(&state).await.unwrap(), 2);
+
+        let mut started = Vec::new();
+        while let Ok(DownloadCommand::Start { download_id, .. }) = download_rx.try_recv() {
+            started.push(download_id);
+        }
+        assert_eq!(started, ["d1", "d2"]);
+        let pending = Download::find_pending(&state.pool).await.unwrap();
+        assert_eq!(pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_after_restart() {
+        let mut state = test_state(yt_dlp::YtDlp::default()).await;
+        let (download_tx, mut download_rx) = tokio::sync::mpsc::channel(8);
+        state.download_tx = download_tx;
+        // A worker holding nothing, as after a restart
+        let worker = tokio::spawn(async move {
+            let mut started = Vec::new();
+            while let Some(cmd) = download_rx.recv().await {
+                match cmd {
+                    DownloadCommand::Start { download_id, .. } => started.push(download_id),
+                    DownloadCommand::Cancel { ack, .. }
+                    | DownloadCommand::Pause { ack, .. }
+                    | DownloadCommand::Resume { ack, .. } => {
+                        let _ = ack.send(false);
+                    }
+                }
+            }
+            started
+        });
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Video", None, None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+        Download::insert(&state.pool, "d1", "v1").await.unwrap();
+        let status = |state: AppState| async move {
+            Download::find_by_id(&state.pool, "d1").await.unwrap().unwrap().status_enum()
+        };
+
+        pause_download(State(state.clone()), Path("d1".to_string())).await.unwrap();
+        assert_eq!(status(state.clone()).await, DownloadStatus::Paused);
+        let err = pause_download(State(state.clone()), Path("d1".to_string())).await.err().unwrap();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert!(matches!(queue_download(&state, "v1").await, Ok(QueueOutcome::InProgress)));
+
+        resume_download(State(state.clone()), Path("d1".to_string())).await.unwrap();
+        assert_eq!(status(state.clone()).await, DownloadStatus::Pending);
+        let err = resume_download(State(state.clone()), Path("d1".to_string())).await.err().unwrap();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+
+        // Pending, e.g. waiting out a retry delay
+        cancel_download(State(state.clone()), Path("d1".to_string())).await.unwrap();
+        assert_eq!(status(state.clone()).await, DownloadStatus::Failed);
+
+        drop(state);
+        assert_eq!(worker.await.unwrap(), ["d1"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_active_downloads_removes_paused_partials() {
+        let dir = std::env::temp_dir().join(format!("toobarr-cancel-paused-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let destination = dir.join("Video [yt1].f137.mp4");
+        let partial = dir.join("Video [yt1].f137.mp4.part");
+        tokio::fs::write(&partial, b"partial").await.unwrap();
+
+        let mut state = test_state(yt_dlp::YtDlp::default()).await;
+        let (download_tx, mut download_rx) = tokio::sync::mpsc::channel(8);
+        state.download_tx = download_tx;
+        // Paused before a restart, so the worker no longer holds it
+        tokio::spawn(async move {
+            while let Some(cmd) = download_rx.recv().await {
+                if let DownloadCommand::Cancel { ack, .. } = cmd {
+                    let _ = ack.send(false);
+                }
+            }
+        });
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Video", None, None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+        Download::insert(&state.pool, "d1", "v1").await.unwrap();
+        Download::update_paused(&state.pool, "d1", Some(&destination.to_string_lossy())).await.unwrap();
+
+        cancel_active_downloads(State(state.clone())).await.unwrap();
+
+        let download = Download::find_by_id(&state.pool, "d1").await.unwrap().unwrap();
+        assert_eq!(download.status_enum(), DownloadStatus::Failed);
+        assert_eq!(download.file_path, None);
+        assert!(!partial.exists());
+        // The video can be queued again
+        assert!(Download::insert(&state.pool, "d2", "v1").await.unwrap());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_resolve_cache_dir() {
+        let home = Some(PathBuf::from("/home/toobarr"));
+        assert_eq!(
+            resolve_cache_dir("~/.cache/yt-dlp", home.clone()).unwrap(),
+            PathBuf::from("/home/toobarr/.cache/yt-dlp")
+        );
+        assert_eq!(resolve_cache_dir("~", home.clone()).unwrap(), PathBuf::from("/home/toobarr"));
+        assert_eq!(resolve_cache_dir("/var/cache/yt-dlp", None).unwrap(), PathBuf::from("/var/cache/yt-dlp"));
+        assert!(resolve_cache_dir("~/.cache", None).unwrap_err().contains("HOME is not set"));
+        assert!(resolve_cache_dir("cache", home.clone()).unwrap_err().contains("absolute"));
+        assert!(resolve_cache_dir("~other/cache", home).unwrap_err().contains("absolute"));
+    }
+
+    #[tokio::test]
+    async fn test_check_ytdlp_config() {
+        let path = std::env::temp_dir().join(format!("toobarr-ytdlp-config-{}.conf", uuid7::uuid7()));
+        let path_str = path.to_string_lossy().to_string();
+        assert!(check_ytdlp_config(&path_str).await.unwrap_err().contains("Cannot read yt-dlp config"));
+
+        tokio::fs::write(&path, "--no-mtime\n").await.unwrap();
+        assert_eq!(check_ytdlp_config(&path_str).await.unwrap(), path);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_download_priority() {
+        let state = test_state(yt_dlp::YtDlp::default()).await;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        for (video, download) in [("v1", "d1"), ("v2", "d2"), ("v3", "d3")] {
+            Video::upsert(
+                &state.pool, video, "c1", video, "Video", None, None, None, None, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+            Download::insert(&state.pool, download, video).await.unwrap();
+        }
+        let set = |id: &str, priority: &str| {
+            set_download_priority(
+                State(state.clone()),
+                Path(id.to_string()),
+                Form(PriorityForm { priority: priority.to_string() })
+            )
+        };
+        let order = |queue: Vec<QueueEntry>| queue.into_iter().map(|e| e.download_id).collect::<Vec<_>>();
+
+        assert_eq!(order(set("d3", "top").await.unwrap().0), ["d3", "d1", "d2"]);
+        assert_eq!(order(set("d3", "bottom").await.unwrap().0), ["d1", "d2", "d3"]);
+        assert_eq!(order(set("d2", "7").await.unwrap().0), ["d2", "d1", "d3"]);
+        assert_eq!(set("d1", "soon").await.unwrap_err().status, StatusCode::BAD_REQUEST);
+        assert_eq!(set("missing", "1").await.unwrap_err().status, StatusCode::NOT_FOUND);
+
+        Download::update_status(&state.pool, "d1", DownloadStatus::Downloading).await.unwrap();
+        assert_eq!(set("d1", "top").await.unwrap_err().status, StatusCode::BAD_REQUEST);
+    }
+
+    async fn test_state(yt_dlp: yt_dlp::YtDlp) -> AppState {
+        let pool = crate::db::test_pool().await;
+
+        AppState {
+            pool,
+            yt_dlp: std::sync::Arc::new(tokio::sync::RwLock::new(yt_dlp)),
+            download_tx: tokio::sync::mpsc::channel(1).0,
+            download_states: std::sync::Arc::default(),
+            version_cache: crate::version_cache::VersionCache::new(std::time::Duration::ZERO),
+            metrics: crate::metrics::Metrics::default(),
+            thumbnail_progress: std::sync::Arc::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_update_settings_rejects_unavailable_impersonate_target() {
+        let dir = std::env::temp_dir().join(format!("toobarr-impersonate-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let state = state_with_fake_ytdlp(&dir).await;
+        let form = |impersonate: &str| -> SettingsForm {
+            serde_json::from_value(serde_json::json!({
+                "download_path": "/new/downloads",
+                "max_concurrent_downloads": "3",
+                "impersonate": impersonate
+            }))
+            .unwrap()
+        };
+
+        let Err(err) = update_settings(State(state.clone()), Form(form("chrome"))).await else {
+            panic!("unavailable target accepted");
+        };
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert!(err.message.contains("curl_cffi"), "{}", err.message);
+        // Nothing from the rejected form was saved
+        assert_eq!(Settings::get(&state.pool, "impersonate").await.unwrap(), None);
+        assert_eq!(
+            Settings::get(&state.pool, "download_path").await.unwrap().as_deref(),
+            Some("./downloads")
+        );
+
+        assert!(update_settings(State(state.clone()), Form(form(""))).await.is_ok());
+        assert_eq!(Settings::get(&state.pool, "impersonate").await.unwrap().as_deref(), Some(""));
+        assert_eq!(
+            Settings::get(&state.pool, "download_path").await.unwrap().as_deref(),
+            Some("/new/downloads")
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_add_channel_short_circuits_existing_channel() {
+        let dir = std::env::temp_dir().join(format!("toobarr-add-channel-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let state = state_with_fake_ytdlp(&dir).await;
+        let url = "https://www.youtube.com/@test";
+
+        assert!(add_channel(&state, url, None).await.is_err());
+
+        Channel::insert(&state.pool, "c1", "UC123", "Test", url, None, None).await.unwrap();
+        match add_channel(&state, url, None).await {
+            Ok(AddChannelOutcome::Existing(id)) => assert_eq!(id, "c1"),
+            Ok(AddChannelOutcome::Created(_)) => panic!("channel was created again"),
+            Err(e) => panic!("full listing was fetched: {}", e.message)
+        }
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    /// The first sync of an imported channel honours its imported date range.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_import_channel_first_sync_uses_imported_range() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("toobarr-import-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let script = dir.join("yt-dlp");
+        tokio::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             echo '{\"id\": \"recent\", \"title\": \"Recent\", \"upload_date\": \"20240601\", \"channel_id\": \"UC9\"}'\n\
+             echo '{\"id\": \"old\", \"title\": \"Old\", \"upload_date\": \"20200101\", \"channel_id\": \"UC9\"}'\n"
+        )
+        .await
+        .unwrap();
+        tokio::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).await.unwrap();
+        let state = test_state(yt_dlp::YtDlp::with_binary(script)).await;
+
+        let entry = opml::ImportEntry {
+            url: "https://www.youtube.com/@imported".to_string(),
+            youtube_id: None,
+            settings: Some(ChannelSettings { date_after: Some("20240101".to_string()), ..ChannelSettings::default() })
+        };
+        let Ok(AddChannelOutcome::Created(id)) = import_channel(&state, &entry).await else {
+            panic!("channel was not created");
+        };
+
+        let channel = Channel::find_by_id(&state.pool, &id).await.unwrap().unwrap();
+        assert_eq!(channel.date_after.as_deref(), Some("20240101"));
+        assert!(Video::find_by_youtube_id(&state.pool, "recent").await.unwrap().is_some());
+        assert!(Video::find_by_youtube_id(&state.pool, "old").await.unwrap().is_none());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_refresh_video_rewrites_row_and_nfo() {
+        let dir = std::env::temp_dir().join(format!("toobarr-refresh-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let state = state_with_fake_ytdlp(&dir).await;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Original", Some("Old plot"), None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+        let media = dir.join("Original [yt1].mp4");
+        tokio::fs::write(&media, b"media").await.unwrap();
+        Download::insert(&state.pool, "d1", "v1").await.unwrap();
+        Download::update_completed(&state.pool, "d1", &media.to_string_lossy(), Some(5))
+            .await
+            .unwrap();
+
+        let response = refresh_video(State(state.clone()), Path("v1".to_string()))
+            .await
+            .unwrap()
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8_lossy(&body);
+        assert!(html.contains("id=\"video-info-v1\""));
+        assert!(html.contains("<header>Edited</header>"));
+
+        let video = Video::find_by_id(&state.pool, "v1").await.unwrap().unwrap();
+        assert_eq!(video.title, "Edited");
+        assert_eq!(video.description.as_deref(), Some("New plot"));
+        assert_eq!(video.view_count, Some(7));
+
+        let nfo = tokio::fs::read_to_string(dir.join("Original [yt1].nfo")).await.unwrap();
+        assert!(nfo.contains("<title>Edited</title>"));
+        assert!(nfo.contains("New plot"));
+        assert_eq!(tokio::fs::read(&media).await.unwrap(), b"media");
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_download_video_subtitles_next_to_media() {
+        let dir = std::env::temp_dir().join(format!("toobarr-subtitles-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let state = state_with_fake_ytdlp(&dir).await;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Original", None, None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+        let form = |langs: &str| Form(SubtitlesForm { langs: Some(langs.to_string()) });
+
+        let err = download_video_subtitles(State(state.clone()), Path("v1".to_string()), form("en"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+
+        let media = dir.join("Original [yt1].mp4");
+        tokio::fs::write(&media, b"media").await.unwrap();
+        Download::insert(&state.pool, "d1", "v1").await.unwrap();
+        Download::update_completed(&state.pool, "d1", &media.to_string_lossy(), Some(5))
+            .await
+            .unwrap();
+
+        let Json(body) = download_video_subtitles(State(state.clone()), Path("v1".to_string()), form("en"))
+            .await
+            .unwrap();
+        assert_eq!(body["files"], serde_json::json!(["Original [yt1].en.vtt"]));
+        assert!(dir.join("Original [yt1].en.vtt").exists());
+        assert!(!dir.join("yt1.en.vtt").exists());
+
+        let Json(body) = download_video_subtitles(State(state.clone()), Path("v1".to_string()), form("xx"))
+            .await
+            .unwrap();
+        assert_eq!(body["files"], serde_json::json!([]));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upcoming_stream_needs_record_live_streams() {
+        let state = test_state(yt_dlp::YtDlp::new()).await;
+        Channel::insert(&state.pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &state.pool, "v1", "c1", "yt1", "Premiere", None, None, None, None, None,
+            "https://example.com/v", Some("is_upcoming"), None, &[], &[]
+        )
+        .await
+        .unwrap();
+        let video = Video::find_by_id(&state.pool, "v1").await.unwrap().unwrap();
+
+        let err = ensure_recordable(&state, &video).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert!(queue_download(&state, "v1").await.is_err());
+        assert!(Download::find_by_video_id(&state.pool, "v1").await.unwrap().is_none());
+
+        Settings::set(&state.pool, "record_live_streams", "true").await.unwrap();
+        assert!(ensure_recordable(&state, &video).await.is_ok());
+    }
+
     #[test]
     fn test_parse_extractor_args_basic() {
         let input = "youtube:player-client=default,mweb\nyoutubepot-bgutilhttp:base_url=http://bgutil:4416";
@@ -500,12 +2621,101 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_cookies_browser_spec() {
+        assert_eq!(cookies_browser_spec("firefox", None).as_deref(), Some("firefox"));
+        assert_eq!(
+            cookies_browser_spec(" chrome ", Some(" Profile 1 ")).as_deref(),
+            Some("chrome:Profile 1")
+        );
+        assert_eq!(cookies_browser_spec("edge", Some("")).as_deref(), Some("edge"));
+        assert_eq!(cookies_browser_spec("", Some("Default")), None);
+    }
+
+    #[test]
+    fn test_validate_settings_form_formats() {
+        let form = |field: &str, value: &str| -> SettingsForm {
+            serde_json::from_value(serde_json::json!({
+                "download_path": "/downloads",
+                "max_concurrent_downloads": "3",
+                field: value
+            }))
+            .unwrap()
+        };
+
+        for format in ["mp3", "flac", ""] {
+            assert!(validate_settings_form(&form("audio_only_format", format)).is_ok(), "{format}");
+        }
+        assert_eq!(
+            validate_settings_form(&form("audio_only_format", "wav")).unwrap_err(),
+            "Unsupported audio-only format: wav"
+        );
+        for (field, valid, invalid) in [
+            ("remux_video", "mkv", "avi"),
+            ("audio_format", "opus", "wav"),
+            ("subtitle_format", "srt", "--exec=id")
+        ] {
+            assert!(validate_settings_form(&form(field, valid)).is_ok(), "{field}");
+            assert!(validate_settings_form(&form(field, "")).is_ok(), "{field}");
+            assert!(validate_settings_form(&form(field, invalid)).is_err(), "{field}");
+        }
+    }
+
+    #[test]
+    fn test_validate_channel_settings() {
+        assert!(validate_channel_settings(&ChannelSettings::default()).is_ok());
+
+        let settings = ChannelSettings {
+            retention_keep_latest: Some(5),
+            date_after: Some("now-1month".to_string()),
+            match_filter: Some("duration > 60".to_string()),
+            ..ChannelSettings::default()
+        };
+        assert!(validate_channel_settings(&settings).is_ok());
+
+        for invalid in [
+            ChannelSettings { retention_max_age_days: Some(-1), ..settings.clone() },
+            ChannelSettings { date_before: Some("soon".to_string()), ..settings.clone() },
+            ChannelSettings { match_filter: Some("a\nb".to_string()), ..settings.clone() }
+        ] {
+            assert!(validate_channel_settings(&invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_env_vars() {
+        let input = "HTTP_PROXY=http://proxy:3128\n\n# cache\n XDG_CACHE_HOME = /cache \nEMPTY=";
+        assert_eq!(parse_env_vars(input).unwrap(), vec![
+            ("HTTP_PROXY".to_string(), "http://proxy:3128".to_string()),
+            ("XDG_CACHE_HOME".to_string(), "/cache".to_string()),
+            ("EMPTY".to_string(), String::new())
+        ]);
+        assert!(parse_env_vars("").unwrap().is_empty());
+        assert!(parse_env_vars("NO_EQUALS").is_err());
+        assert!(parse_env_vars("1BAD=x").is_err());
+        assert!(parse_env_vars("BAD-KEY=x").is_err());
+        assert!(parse_env_vars("=value").is_err());
+    }
+
     #[test]
     fn test_parse_extractor_args_empty() {
         assert!(parse_extractor_args("").is_empty());
         assert!(parse_extractor_args("  \n  \n  ").is_empty());
     }
 
+    #[test]
+    fn test_validate_output_template() {
+        assert!(validate_output_template("%(upload_date)s - %(title)s [%(id)s].%(ext)s").is_ok());
+        assert!(validate_output_template("").is_ok());
+        assert!(validate_output_template("%(title)s.mp4").is_err());
+        assert!(validate_output_template("/etc/%(title)s.%(ext)s").is_err());
+        assert!(validate_output_template("../%(title)s.%(ext)s").is_err());
+        assert_eq!(
+            validate_output_template("%(titel)s.%(ext)s").unwrap_err(),
+            "Invalid output template: unknown field \"titel\""
+        );
+    }
+
     #[test]
     fn test_parse_extractor_args_whitespace() {
         let input = "  youtube:player-client=mweb  \n\n  youtube:po_token=abc  ";
@@ -515,4 +2725,103 @@ mod tests {
             "youtube:player-client=mweb;youtube:po_token=abc"
         ]);
     }
+
+    #[test]
+    fn test_entry_identity_soundcloud_playlist() {
+        let playlist: yt_dlp::PlaylistInfo = serde_json::from_str(
+            r#"{
+                "id": "12345",
+                "title": "artist (Tracks)",
+                "extractor": "soundcloud:user",
+                "extractor_key": "SoundcloudUser",
+                "entries": [
+                    {"id": "987654321", "title": "Track One", "ie_key": "Soundcloud",
+                     "url": "https://api.soundcloud.com/tracks/987654321"},
+                    {"id": "555", "title": "Track Two",
+                     "webpage_url": "https://soundcloud.com/artist/track-two"},
+                    {"id": "777", "title": "No URL"}
+                ]
+            }"#
+        )
+        .unwrap();
+        let extractor = playlist.extractor.as_deref();
+
+        let (key, url) = entry_identity(&playlist.entries[0], extractor).unwrap();
+        assert_eq!(key, "soundcloud:987654321");
+        assert_eq!(url, "https://api.soundcloud.com/tracks/987654321");
+        assert_eq!(source_id(&key), "987654321");
+        assert_eq!(media_site(&key), Some("soundcloud"));
+
+        let (key, url) = entry_identity(&playlist.entries[1], extractor).unwrap();
+        assert_eq!(key, "soundcloud:555");
+        assert_eq!(url, "https://soundcloud.com/artist/track-two");
+
+        assert!(entry_identity(&playlist.entries[2], extractor).is_none());
+        assert_eq!(channel_key(&playlist), "soundcloud:12345");
+    }
+
+    #[test]
+    fn test_entry_identity_youtube_keeps_bare_id() {
+        let entry: yt_dlp::VideoInfo =
+            serde_json::from_str(r#"{"id": "dQw4w9WgXcQ", "title": "Video", "ie_key": "Youtube"}"#).unwrap();
+        let (key, url) = entry_identity(&entry, Some("YoutubeTab")).unwrap();
+        assert_eq!(key, "dQw4w9WgXcQ");
+        assert_eq!(url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!(source_id(&key), "dQw4w9WgXcQ");
+        assert_eq!(media_site(&key), None);
+    }
+
+    #[test]
+    fn test_early_stop_incremental() {
+        let mut stop = EarlyStop::new(SyncMode::Incremental);
+        let known = [false, false, true, true, false];
+        assert!(known.iter().all(|&k| !stop.should_stop(k)));
+        for _ in 0..INCREMENTAL_STOP_AFTER {
+            assert!(!stop.should_stop(true));
+        }
+        assert!(stop.should_stop(true));
+    }
+
+    #[test]
+    fn test_validate_night_window() {
+        assert!(validate_night_window(None, None).is_ok());
+        assert!(validate_night_window(Some(""), Some(" ")).is_ok());
+        assert!(validate_night_window(Some("23:00"), Some("07:00")).is_ok());
+        assert!(validate_night_window(Some("23:00"), Some("")).is_err());
+        assert!(validate_night_window(Some("11pm"), Some("07:00")).is_err());
+    }
+
+    #[test]
+    fn test_validate_rate() {
+        assert!(validate_rate("").is_ok());
+        assert!(validate_rate("100K").is_ok());
+        assert!(validate_rate("4.2m").is_ok());
+        assert!(validate_rate("1048576").is_ok());
+        assert!(validate_rate("fast").is_err());
+        assert!(validate_rate("0K").is_err());
+        assert!(validate_rate("K").is_err());
+        assert!(validate_rate("10KB").is_err());
+    }
+
+    #[test]
+    fn test_validate_audio_quality() {
+        assert!(validate_audio_quality("").is_ok());
+        assert!(validate_audio_quality("0").is_ok());
+        assert!(validate_audio_quality("10").is_ok());
+        assert!(validate_audio_quality("128K").is_ok());
+        assert!(validate_audio_quality("192k").is_ok());
+        assert!(validate_audio_quality("11").is_err());
+        assert!(validate_audio_quality("0K").is_err());
+        assert!(validate_audio_quality("best").is_err());
+    }
+
+    #[test]
+    fn test_in_date_range_keeps_undated_entries() {
+        let range = DateRange::parse(Some("20240101"), Some("20241231")).unwrap();
+        assert!(in_date_range(&range, Some("20240615")));
+        assert!(!in_date_range(&range, Some("20231231")));
+        assert!(!in_date_range(&range, Some("20250101")));
+        assert!(in_date_range(&range, None));
+        assert!(in_date_range(&DateRange::default(), Some("19990101")));
+    }
 }
@@ -0,0 +1,305 @@
+use std::path::PathBuf;
+
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response}
+};
+use tower::Service;
+use tower_http::services::ServeFile;
+
+use crate::error::AppError;
+use crate::feed;
+use crate::models::{Channel, Download, DownloadStatus};
+use crate::purge;
+use crate::state::AppState;
+
+#[tracing::instrument(skip(state, headers))]
+pub async fn channel_feed(
+    State(state): State<AppState>,
+    Path(channel_id): Path<String>,
+    headers: HeaderMap
+) -> Result<impl IntoResponse, AppError> {
+    let channel = Channel::find_by_id(&state.pool, &channel_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Channel not found"))?;
+
+    let entries = Download::find_feed_entries(&state.pool, &channel.id).await?;
+    let xml = feed::channel_feed_xml(&channel, &entries, &base_url(&headers));
+
+    Ok(([(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")], xml))
+}
+
+/// A subtitle file next to the media, e.g. `Video [abc].en.srt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleTrack {
+    pub lang: String,
+    pub file_name: String
+}
+
+/// Serves a completed download's media file, with range support so podcast
+/// clients can seek. Files outside the download path are refused.
+#[tracing::instrument(skip(state, request))]
+pub async fn serve_media(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>,
+    request: Request
+) -> Result<Response, AppError> {
+    let media = completed_media(&state, &download_id).await?;
+    serve_file(media, request).await
+}
+
+/// The thumbnail saved alongside the media, used as the player's poster.
+#[tracing::instrument(skip(state, request))]
+pub async fn serve_poster(
+    State(state): State<AppState>,
+    Path(download_id): Path<String>,
+    request: Request
+) -> Result<Response, AppError> {
+    let media = completed_media(&state, &download_id).await?;
+    let poster = poster_path(&media)
+        .await
+        .ok_or_else(|| AppError::not_found("Poster not found"))?;
+    serve_file(poster, request).await
+}
+
+/// A subtitle sidecar as VTT, the only format `<track>` accepts; SRT files
+/// are converted on the fly. Only files found by [`subtitle_tracks`] are served.
+#[tracing::instrument(skip(state))]
+pub async fn serve_subtitle(
+    State(state): State<AppState>,
+    Path((download_id, file_name)): Path<(String, String)>
+) -> Result<impl IntoResponse, AppError> {
+    let media = completed_media(&state, &download_id).await?;
+    if !subtitle_tracks(&media).await.iter().any(|track| track.file_name == file_name) {
+        return Err(AppError::not_found("Subtitle not found"));
+    }
+    let path = media.with_file_name(&file_name);
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|_| AppError::not_found("Subtitle not found"))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let vtt = if has_extension(&file_name, "srt") { srt_to_vtt(&text) } else { text.into_owned() };
+    Ok(([(header::CONTENT_TYPE, "text/vtt; charset=utf-8")], vtt))
+}
+
+/// The canonical media path of a completed download, refused when it lies
+/// outside the download path.
+pub async fn completed_media(state: &AppState, download_id: &str) -> Result<PathBuf, AppError> {
+    let download = Download::find_by_id(&state.pool, download_id)
+        .await?
+        .filter(|d| d.status_enum() == DownloadStatus::Completed)
+        .ok_or_else(|| AppError::not_found("Download not found"))?;
+    let file_path = download
+        .file_path
+        .ok_or_else(|| AppError::not_found("Download has no file"))?;
+
+    let download_root = purge::download_root(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Media file not found"))?;
+    purge::resolve_within(&download_root, std::path::Path::new(&file_path))
+        .await
+        .ok_or_else(|| AppError::not_found("Media file not found"))
+}
+
+async fn serve_file(path: PathBuf, request: Request) -> Result<Response, AppError> {
+    let response = ServeFile::new(path)
+        .call(request)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to serve media: {e}")))?;
+    Ok(response.map(Body::new))
+}
+
+/// `.srt` and `.vtt` siblings named `<stem>.<lang>.<ext>`, sorted by
+/// language. A language with both formats is listed once, preferring VTT.
+pub async fn subtitle_tracks(media: &std::path::Path) -> Vec<SubtitleTrack> {
+    let (Some(dir), Some(stem)) = (media.parent(), media.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut tracks: Vec<SubtitleTrack> = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(lang) = subtitle_lang(&stem, &file_name) else {
+            continue;
+        };
+        match tracks.iter_mut().find(|track| track.lang == lang) {
+            Some(track) if has_extension(&file_name, "vtt") => track.file_name = file_name,
+            Some(_) => {}
+            None => tracks.push(SubtitleTrack { lang: lang.to_string(), file_name })
+        }
+    }
+    tracks.sort_by(|a, b| a.lang.cmp(&b.lang));
+    tracks
+}
+
+/// The sibling `<stem>-thumb.<ext>` written by the download worker.
+pub async fn poster_path(media: &std::path::Path) -> Option<PathBuf> {
+    let stem = media.file_stem()?.to_string_lossy();
+    for ext in ["jpg", "png", "webp"] {
+        let path = media.with_file_name(format!("{stem}-thumb.{ext}"));
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+fn subtitle_lang<'a>(stem: &str, file_name: &'a str) -> Option<&'a str> {
+    let rest = file_name.strip_prefix(stem)?.strip_prefix('.')?;
+    let lang = rest.strip_suffix(".srt").or_else(|| rest.strip_suffix(".vtt"))?;
+    (!lang.is_empty() && !lang.contains(['.', '/', '\\'])).then_some(lang)
+}
+
+fn has_extension(file_name: &str, ext: &str) -> bool {
+    std::path::Path::new(file_name).extension().is_some_and(|e| e == ext)
+}
+
+/// SRT and VTT cues differ only in the header and the millisecond
+/// separator of the timings.
+fn srt_to_vtt(srt: &str) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in srt.trim_start_matches('\u{feff}').lines() {
+        if line.contains("-->") {
+            vtt.push_str(&line.replace(',', "."));
+        } else {
+            vtt.push_str(line);
+        }
+        vtt.push('\n');
+    }
+    vtt
+}
+
+/// The origin the client used to reach us, honouring `X-Forwarded-Proto`
+/// from a reverse proxy.
+fn base_url(headers: &HeaderMap) -> String {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("http");
+    format!("{scheme}://{host}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(base_url(&headers), "http://localhost");
+
+        headers.insert(header::HOST, "nas:8000".parse().unwrap());
+        assert_eq!(base_url(&headers), "http://nas:8000");
+
+        headers.insert("x-forwarded-proto", "https".parse().unwrap());
+        assert_eq!(base_url(&headers), "https://nas:8000");
+    }
+
+    #[test]
+    fn test_subtitle_lang() {
+        let stem = "Video [abc]";
+        assert_eq!(subtitle_lang(stem, "Video [abc].en.srt"), Some("en"));
+        assert_eq!(subtitle_lang(stem, "Video [abc].pt-BR.vtt"), Some("pt-BR"));
+        assert_eq!(subtitle_lang(stem, "Video [abc].srt"), None);
+        assert_eq!(subtitle_lang(stem, "Video [abc].info.json"), None);
+        assert_eq!(subtitle_lang(stem, "Video [abc] 2.en.srt"), None);
+        assert_eq!(subtitle_lang(stem, "Video [abc].part.en.srt"), None);
+    }
+
+    #[test]
+    fn test_srt_to_vtt() {
+        let srt = "\u{feff}1\n00:00:01,000 --> 00:00:02,500\nHello, world\n\n2\n00:00:03,000 --> 00:00:04,000\nBye\n";
+        assert_eq!(
+            srt_to_vtt(srt),
+            "WEBVTT\n\n1\n00:00:01.000 --> 00:00:02.500\nHello, world\n\n2\n00:00:03.000 --> 00:00:04.000\nBye\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subtitle_tracks_and_poster() {
+        let dir = std::env::temp_dir().join(format!("toobarr-sidecar-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        for name in ["Video.mp4", "Video.en.srt", "Video.en.vtt", "Video.de.srt", "Video.info.json", "Video-thumb.png"] {
+            tokio::fs::write(dir.join(name), b"").await.unwrap();
+        }
+        let media = dir.join("Video.mp4");
+
+        let tracks = subtitle_tracks(&media).await;
+        let files: Vec<_> = tracks.iter().map(|t| (t.lang.as_str(), t.file_name.as_str())).collect();
+        assert_eq!(files, vec![("de", "Video.de.srt"), ("en", "Video.en.vtt")]);
+        assert_eq!(poster_path(&media).await, Some(dir.join("Video-thumb.png")));
+        assert_eq!(poster_path(&dir.join("Other.mp4")).await, None);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_media_ranges_and_stays_in_download_root() {
+        use crate::models::{Settings, Video};
+
+        let dir = std::env::temp_dir().join(format!("toobarr-media-test-{}", uuid7::uuid7()));
+        let root = dir.join("downloads");
+        tokio::fs::create_dir_all(root.join("Chan")).await.unwrap();
+        let inside = root.join("Chan/Video [yt1].mp4");
+        let outside = dir.join("secret.mp4");
+        tokio::fs::write(&inside, b"0123456789abcdef").await.unwrap();
+        tokio::fs::write(&outside, b"secret").await.unwrap();
+
+        let pool = crate::db::test_pool().await;
+        Settings::set(&pool, "download_path", &root.to_string_lossy()).await.unwrap();
+        Channel::insert(&pool, "c1", "UC1", "Chan", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        for (video, download, file) in [("v1", "d1", &inside), ("v2", "d2", &outside)] {
+            Video::upsert(
+                &pool, video, "c1", video, "Video", None, None, None, None, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+            Download::insert(&pool, download, video).await.unwrap();
+            Download::update_completed(&pool, download, &file.to_string_lossy(), Some(16))
+                .await
+                .unwrap();
+        }
+        let state = AppState {
+            pool,
+            yt_dlp: std::sync::Arc::new(tokio::sync::RwLock::new(yt_dlp::YtDlp::new())),
+            download_tx: tokio::sync::mpsc::channel(1).0,
+            download_states: std::sync::Arc::default(),
+            version_cache: crate::version_cache::VersionCache::new(std::time::Duration::ZERO),
+            metrics: crate::metrics::Metrics::default(),
+            thumbnail_progress: std::sync::Arc::default()
+        };
+
+        let request = Request::builder()
+            .uri("/media/d1")
+            .header(header::RANGE, "bytes=4-9")
+            .body(Body::empty())
+            .unwrap();
+        let response = serve_media(State(state.clone()), Path("d1".to_string()), request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 4-9/16");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"456789");
+
+        let request = Request::builder().uri("/media/d2").body(Body::empty()).unwrap();
+        let error = serve_media(State(state), Path("d2".to_string()), request)
+            .await
+            .unwrap_err();
+        assert_eq!(error.into_response().status(), axum::http::StatusCode::NOT_FOUND);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -1,2 +1,4 @@
 pub mod api;
+pub mod media;
 pub mod pages;
+pub mod v1;
@@ -2,15 +2,18 @@ use std::collections::HashMap;
 
 use askama::Template;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::Html
 };
+use serde::Deserialize;
 use sqlx::Row;
+use yt_dlp::YtDlpVersion;
 
 use crate::error::AppError;
-use crate::handlers::api::check_binary_version;
-use crate::models::{Channel, Download, DownloadWithVideo, Settings, Video};
-use crate::state::AppState;
+use crate::handlers::api::validate_output_template;
+use crate::handlers::media::{completed_media, poster_path, subtitle_tracks, SubtitleTrack};
+use crate::models::{Channel, Download, DownloadWithVideo, Settings, Video, VideoWithChannel};
+use crate::state::{AppState, ThumbnailProgress, MIN_YTDLP_VERSION, YTDLP_STALE_DAYS};
 
 #[derive(Template)]
 #[template(path = "home.html")]
@@ -37,13 +40,52 @@ struct NewChannelTemplate;
 struct ChannelDetailTemplate {
     channel: Channel,
     videos: Vec<Video>,
-    download_statuses: HashMap<String, String>
+    download_statuses: HashMap<String, String>,
+    watched_filter: String,
+    thumbnail_progress: Option<ThumbnailProgress>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelDetailQuery {
+    watched: Option<String>
 }
 
 #[derive(Template)]
 #[template(path = "downloads.html")]
 struct DownloadsTemplate {
-    downloads: Vec<DownloadWithVideo>
+    downloads: Vec<DownloadWithVideo>,
+    labels: Vec<String>,
+    label_filter: String
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelQuery {
+    pub label: Option<String>
+}
+
+pub const SEARCH_PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    pub page: Option<i64>
+}
+
+#[derive(Template)]
+#[template(path = "player.html")]
+struct PlayerTemplate {
+    dl: DownloadWithVideo,
+    subtitles: Vec<SubtitleTrack>,
+    has_poster: bool
+}
+
+#[derive(Template)]
+#[template(path = "search.html")]
+struct SearchTemplate {
+    query: String,
+    page: i64,
+    results: Vec<VideoWithChannel>,
+    has_more: bool
 }
 
 pub struct BinaryStatus {
@@ -56,12 +98,55 @@ pub struct BinaryStatus {
 
 #[derive(Template)]
 #[template(path = "settings.html")]
+#[allow(clippy::struct_excessive_bools)]
 struct SettingsTemplate {
     download_path: String,
     max_concurrent_downloads: usize,
+    concurrent_fragments: u32,
+    rate_limit: String,
+    night_rate_limit: String,
+    night_start: String,
+    night_end: String,
+    throttled_rate: String,
+    max_filesize: String,
+    cache_dir: String,
+    ytdlp_config: String,
+    temp_dir: String,
     extractor_args: String,
+    impersonate: String,
+    env_vars: String,
+    output_template: String,
+    output_template_warning: Option<String>,
+    max_height: String,
+    remux_video: String,
+    recode_video_fallback: bool,
+    audio_format: String,
+    audio_only_format: String,
+    audio_quality: String,
+    download_subtitles: bool,
+    subtitle_langs: String,
+    subtitle_format: String,
+    nfo_episode_scheme: &'static str,
+    directory_layout: &'static str,
+    include_auto_subs: bool,
+    restrict_filenames: bool,
+    windows_filenames: bool,
+    trim_filenames: String,
+    integrity_tolerance: u32,
+    retry_max_attempts: u32,
+    retry_base_delay: u64,
+    keep_info_json: bool,
+    embed_thumbnail: bool,
+    thumbnail_format: &'static str,
+    record_live_streams: bool,
+    set_mtime: bool,
+    no_continue: bool,
+    estimate_size: bool,
     has_cookies: bool,
-    binaries: Vec<BinaryStatus>
+    cookies_browser: String,
+    cookies_browser_profile: String,
+    binaries: Vec<BinaryStatus>,
+    ytdlp_outdated: Option<String>
 }
 
 #[tracing::instrument(skip(state))]
@@ -88,7 +173,7 @@ pub async fn home_page(State(state): State<AppState>) -> Result<Html<String>, Ap
             .await?
             .get("count");
 
-    let all_downloads = Download::find_all_with_video(&state.pool).await?;
+    let all_downloads = Download::find_all_with_video(&state.pool, None).await?;
     let recent_downloads: Vec<_> = all_downloads.into_iter().take(5).collect();
 
     let template = HomeTemplate {
@@ -117,16 +202,25 @@ pub async fn new_channel_page() -> Result<Html<String>, AppError> {
 #[tracing::instrument(skip(state))]
 pub async fn channel_detail_page(
     State(state): State<AppState>,
-    Path(id): Path<String>
+    Path(id): Path<String>,
+    Query(params): Query<ChannelDetailQuery>
 ) -> Result<Html<String>, AppError> {
     let channel = Channel::find_by_id(&state.pool, &id)
         .await?
         .ok_or_else(|| AppError::not_found("Channel not found"))?;
 
-    let videos = Video::find_by_channel(&state.pool, &id).await?;
+    let watched_filter = params.watched.unwrap_or_default();
+    let watched = match watched_filter.as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None
+    };
+    let videos = Video::find_by_channel(&state.pool, &id, watched).await?;
 
     let rows = sqlx::query(
-        r"SELECT d.video_id, d.status FROM downloads d
+        r"SELECT d.video_id,
+                 CASE WHEN d.pruned_at IS NOT NULL THEN 'pruned' ELSE d.status END AS status
+          FROM downloads d
           WHERE d.video_id IN (SELECT v.id FROM videos v WHERE v.channel_id = ?)
           AND d.id = (SELECT d2.id FROM downloads d2 WHERE d2.video_id = d.video_id ORDER BY d2.created_at DESC LIMIT 1)"
     )
@@ -141,26 +235,188 @@ pub async fn channel_detail_page(
         download_statuses.insert(video_id, status);
     }
 
-    let template = ChannelDetailTemplate { channel, videos, download_statuses };
+    let thumbnail_progress = state.thumbnail_progress.read().await.get(&id).copied();
+
+    let template = ChannelDetailTemplate {
+        channel,
+        videos,
+        download_statuses,
+        watched_filter,
+        thumbnail_progress
+    };
+    Ok(Html(template.render()?))
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn downloads_page(
+    State(state): State<AppState>,
+    Query(params): Query<LabelQuery>
+) -> Result<Html<String>, AppError> {
+    let label_filter = params.label.unwrap_or_default();
+    let label = Some(label_filter.as_str()).filter(|l| !l.is_empty());
+    let downloads = Download::find_all_with_video(&state.pool, label).await?;
+    let labels = Download::all_labels(&state.pool).await?;
+    let template = DownloadsTemplate { downloads, labels, label_filter };
     Ok(Html(template.render()?))
 }
 
+/// Plays a completed download in the browser from the `/media` routes, with
+/// its subtitle sidecars as tracks and the saved thumbnail as poster.
 #[tracing::instrument(skip(state))]
-pub async fn downloads_page(State(state): State<AppState>) -> Result<Html<String>, AppError> {
-    let downloads = Download::find_all_with_video(&state.pool).await?;
-    let template = DownloadsTemplate { downloads };
+pub async fn player_page(
+    State(state): State<AppState>,
+    Path(id): Path<String>
+) -> Result<Html<String>, AppError> {
+    let dl = Download::find_with_video_by_id(&state.pool, &id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Download not found"))?;
+    let media = completed_media(&state, &id).await?;
+    let subtitles = subtitle_tracks(&media).await;
+    let has_poster = poster_path(&media).await.is_some();
+    let template = PlayerTemplate { dl, subtitles, has_poster };
     Ok(Html(template.render()?))
 }
 
 #[tracing::instrument(skip(state))]
+pub async fn search_page(
+    State(state): State<AppState>,
+    Query(params): Query<SearchQuery>
+) -> Result<Html<String>, AppError> {
+    let query = params.q.unwrap_or_default().trim().to_string();
+    let page = params.page.unwrap_or(1).max(1);
+
+    let mut results = if query.is_empty() {
+        Vec::new()
+    } else {
+        let offset = (page - 1) * SEARCH_PAGE_SIZE;
+        Video::fts_search(&state.pool, &query, SEARCH_PAGE_SIZE + 1, offset).await?
+    };
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let has_more = results.len() > SEARCH_PAGE_SIZE as usize;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    results.truncate(SEARCH_PAGE_SIZE as usize);
+
+    let template = SearchTemplate { query, page, results, has_more };
+    Ok(Html(template.render()?))
+}
+
+#[tracing::instrument(skip(state))]
+#[allow(clippy::too_many_lines)]
 pub async fn settings_page(State(state): State<AppState>) -> Result<Html<String>, AppError> {
     let download_path = Settings::get_download_path(&state.pool).await?;
     let max_concurrent_downloads = Settings::get_max_concurrent_downloads(&state.pool).await?;
+    let concurrent_fragments = Settings::get_concurrent_fragments(&state.pool).await?;
+    let rate_limit = Settings::get_rate_limit(&state.pool).await?.unwrap_or_default();
+    let night_rate_limit = Settings::get(&state.pool, "night_rate_limit").await?.unwrap_or_default();
+    let night_start = Settings::get(&state.pool, "night_start").await?.unwrap_or_default();
+    let night_end = Settings::get(&state.pool, "night_end").await?.unwrap_or_default();
+    let throttled_rate = Settings::get_throttled_rate(&state.pool).await?.unwrap_or_default();
+    let max_filesize = Settings::get_max_filesize(&state.pool).await?.unwrap_or_default();
+    let cache_dir = Settings::get(&state.pool, "cache_dir").await?.unwrap_or_default();
+    let ytdlp_config = Settings::get(&state.pool, "ytdlp_config").await?.unwrap_or_default();
+    let temp_dir = Settings::get_temp_dir(&state.pool).await?.unwrap_or_default();
     let extractor_args = Settings::get_extractor_args(&state.pool).await?;
+    let impersonate = Settings::get(&state.pool, "impersonate").await?.unwrap_or_default();
+    let env_vars = Settings::get_env_vars(&state.pool).await?;
+    let output_template = Settings::get_output_template(&state.pool).await?;
+    // Templates saved before the field check still download; flag them here
+    let output_template_warning = validate_output_template(&output_template).err();
+    let max_height = Settings::get_max_height(&state.pool)
+        .await?
+        .map(|n| n.to_string())
+        .unwrap_or_default();
+    let remux_video = Settings::get_remux_video(&state.pool).await?.unwrap_or_default();
+    let audio_format = Settings::get_audio_format(&state.pool).await?.unwrap_or_default();
+    let audio_only_format = Settings::get_audio_only_format(&state.pool).await?;
+    let audio_quality = Settings::get_audio_quality(&state.pool).await?.unwrap_or_default();
+    let download_subtitles = Settings::get_download_subtitles(&state.pool).await?;
+    let subtitle_langs = Settings::get_subtitle_langs(&state.pool).await?.join(",");
+    let subtitle_format = Settings::get_subtitle_format(&state.pool).await?.unwrap_or_default();
+    let nfo_episode_scheme = Settings::get_episode_scheme(&state.pool).await?.as_str();
+    let directory_layout = Settings::get_directory_layout(&state.pool).await?.as_str();
+    let include_auto_subs = Settings::get_include_auto_subs(&state.pool).await?;
+    let restrict_filenames = Settings::get_bool(&state.pool, "restrict_filenames").await?;
+    let windows_filenames = Settings::get_bool(&state.pool, "windows_filenames").await?;
+    let trim_filenames = Settings::get_trim_filenames(&state.pool)
+        .await?
+        .map(|n| n.to_string())
+        .unwrap_or_default();
+    let integrity_tolerance = Settings::get_integrity_tolerance(&state.pool).await?;
+    let retry_max_attempts = Settings::get_retry_max_attempts(&state.pool).await?;
+    let retry_base_delay = Settings::get_retry_base_delay(&state.pool).await?;
+    let recode_video_fallback = Settings::get_bool(&state.pool, "recode_video_fallback").await?;
+    let keep_info_json = Settings::get_bool(&state.pool, "keep_info_json").await?;
+    let embed_thumbnail = Settings::get_bool(&state.pool, "embed_thumbnail").await?;
+    let thumbnail_format = Settings::get_thumbnail_format(&state.pool).await?;
+    let record_live_streams = Settings::get_bool(&state.pool, "record_live_streams").await?;
+    let set_mtime = Settings::get_set_mtime(&state.pool).await?;
+    let no_continue = Settings::get_bool(&state.pool, "no_continue").await?;
+    let estimate_size = Settings::get_bool(&state.pool, "estimate_size").await?;
     let cookies_file = Settings::get_cookies_file(&state.pool).await?.unwrap_or_default();
     let has_cookies = !cookies_file.is_empty()
         && std::path::Path::new(&cookies_file).exists();
+    let cookies_spec = Settings::get_cookies_from_browser(&state.pool).await?.unwrap_or_default();
+    let (cookies_browser, cookies_browser_profile) = match cookies_spec.split_once(':') {
+        Some((browser, profile)) => (browser.to_string(), profile.to_string()),
+        None => (cookies_spec, String::new())
+    };
+    let binaries = binary_statuses(&state).await;
+    let ytdlp_outdated = ytdlp_outdated_warning(&binaries);
+
+    let template = SettingsTemplate {
+        download_path,
+        max_concurrent_downloads,
+        concurrent_fragments,
+        rate_limit,
+        night_rate_limit,
+        night_start,
+        night_end,
+        throttled_rate,
+        max_filesize,
+        cache_dir,
+        ytdlp_config,
+        temp_dir,
+        extractor_args,
+        impersonate,
+        env_vars,
+        output_template,
+        output_template_warning,
+        max_height,
+        remux_video,
+        recode_video_fallback,
+        audio_format,
+        audio_only_format,
+        audio_quality,
+        download_subtitles,
+        subtitle_langs,
+        subtitle_format,
+        nfo_episode_scheme,
+        directory_layout,
+        include_auto_subs,
+        restrict_filenames,
+        windows_filenames,
+        trim_filenames,
+        integrity_tolerance,
+        retry_max_attempts,
+        retry_base_delay,
+        keep_info_json,
+        embed_thumbnail,
+        thumbnail_format,
+        record_live_streams,
+        set_mtime,
+        no_continue,
+        estimate_size,
+        has_cookies,
+        cookies_browser,
+        cookies_browser_profile,
+        binaries,
+        ytdlp_outdated
+    };
+    Ok(Html(template.render()?))
+}
 
+async fn binary_statuses(state: &AppState) -> Vec<BinaryStatus> {
     let binary_configs = [
         ("yt-dlp", "ytdlp_path", "yt-dlp"),
         ("ffmpeg", "ffmpeg_path", "ffmpeg"),
@@ -176,7 +432,7 @@ pub async fn settings_page(State(state): State<AppState>) -> Result<Html<String>
             .flatten()
             .filter(|s| !s.is_empty());
         let bin_path = custom_path.unwrap_or_else(|| default_bin.to_string());
-        let version = check_binary_version(&bin_path).await;
+        let version = state.version_cache.get(&bin_path).await;
         let available = version.is_some();
         binaries.push(BinaryStatus {
             name: name.to_string(),
@@ -186,13 +442,25 @@ pub async fn settings_page(State(state): State<AppState>) -> Result<Html<String>
             available
         });
     }
+    binaries
+}
 
-    let template = SettingsTemplate {
-        download_path,
-        max_concurrent_downloads,
-        extractor_args,
-        has_cookies,
-        binaries
-    };
-    Ok(Html(template.render()?))
+fn ytdlp_outdated_warning(binaries: &[BinaryStatus]) -> Option<String> {
+    let (year, month, day) = MIN_YTDLP_VERSION;
+    let ytdlp_version = binaries
+        .iter()
+        .find(|b| b.setting_key == "ytdlp_path")
+        .and_then(|b| b.version.as_deref())
+        .and_then(YtDlpVersion::parse);
+    ytdlp_version.and_then(|v| {
+        if v.date() < MIN_YTDLP_VERSION {
+            Some(format!("yt-dlp {v} is older than {year}.{month:02}.{day:02}; downloads may fail until it is updated."))
+        } else if v.is_older_than(YTDLP_STALE_DAYS) {
+            Some(format!(
+                "yt-dlp {v} is more than {YTDLP_STALE_DAYS} days old; update it if extraction starts failing."
+            ))
+        } else {
+            None
+        }
+    })
 }
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json}
+};
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::handlers::api::{queue_download, QueueOutcome, WatchedQuery};
+use crate::handlers::pages::{LabelQuery, SearchQuery, SEARCH_PAGE_SIZE};
+use crate::models::{Channel, Download, DownloadWithVideo, Video, VideoWithChannel};
+use crate::state::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct QueuedDownload {
+    status: &'static str,
+    download: Option<Download>
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn list_channels(State(state): State<AppState>) -> Result<Json<Vec<Channel>>, AppError> {
+    Ok(Json(Channel::find_all(&state.pool).await?))
+}
+
+/// Accepts the same `?watched=` filter as the channel page.
+#[tracing::instrument(skip(state))]
+pub async fn list_channel_videos(
+    State(state): State<AppState>,
+    Path(channel_id): Path<String>,
+    Query(params): Query<WatchedQuery>
+) -> Result<Json<Vec<Video>>, AppError> {
+    if Channel::find_by_id(&state.pool, &channel_id).await?.is_none() {
+        return Err(AppError::not_found("Channel not found"));
+    }
+    Ok(Json(Video::find_by_channel(&state.pool, &channel_id, params.watched).await?))
+}
+
+/// Accepts the same `?label=` filter as the downloads page.
+#[tracing::instrument(skip(state))]
+pub async fn list_downloads(
+    State(state): State<AppState>,
+    Query(params): Query<LabelQuery>
+) -> Result<Json<Vec<DownloadWithVideo>>, AppError> {
+    let label = params.label.as_deref().filter(|l| !l.is_empty());
+    Ok(Json(Download::find_all_with_video(&state.pool, label).await?))
+}
+
+/// Full-text search ranked by relevance, paged like the search page with
+/// `?q=` and `?page=`.
+#[tracing::instrument(skip(state))]
+pub async fn search_videos(
+    State(state): State<AppState>,
+    Query(params): Query<SearchQuery>
+) -> Result<Json<Vec<VideoWithChannel>>, AppError> {
+    let query = params.q.unwrap_or_default();
+    let offset = (params.page.unwrap_or(1).max(1) - 1) * SEARCH_PAGE_SIZE;
+    Ok(Json(Video::fts_search(&state.pool, &query, SEARCH_PAGE_SIZE, offset).await?))
+}
+
+/// Responds with `202 Accepted` when a new download was queued and `200 OK`
+/// when the video is already downloading or downloaded.
+#[tracing::instrument(skip(state))]
+pub async fn start_download(
+    State(state): State<AppState>,
+    Path(video_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let (code, status, download) = match queue_download(&state, &video_id).await? {
+        QueueOutcome::Queued(download_id) => (
+            StatusCode::ACCEPTED,
+            "queued",
+            Download::find_by_id(&state.pool, &download_id).await?
+        ),
+        QueueOutcome::InProgress => (
+            StatusCode::OK,
+            "in_progress",
+            Download::find_by_video_id(&state.pool, &video_id).await?
+        ),
+        QueueOutcome::AlreadyDownloaded => (
+            StatusCode::OK,
+            "already_downloaded",
+            Download::find_by_video_id(&state.pool, &video_id).await?
+        )
+    };
+    Ok((code, Json(QueuedDownload { status, download })))
+}
@@ -1,10 +1,16 @@
 mod db;
 mod error;
+mod feed;
 mod handlers;
+mod metrics;
 mod models;
 mod nfo;
+mod opml;
+mod purge;
+mod self_test;
 mod state;
 mod thumbnail;
+mod version_cache;
 mod workers;
 
 use std::collections::HashMap;
@@ -16,14 +22,21 @@ use axum::{
     routing::{delete, get, post}
 };
 use tokio::sync::{RwLock, mpsc};
+use tower::limit::GlobalConcurrencyLimitLayer;
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use yt_dlp::YtDlp;
 
-use handlers::{api, pages};
+use db::DbPool;
+use handlers::{api, media, pages, v1};
+use metrics::Metrics;
 use models::Settings;
-use state::AppState;
+use state::{AppState, MIN_YTDLP_VERSION};
+use version_cache::{VERSION_CACHE_TTL, VersionCache};
 use workers::download::DownloadWorker;
+use workers::retention::RetentionWorker;
+
+const DEFAULT_MAX_CONCURRENT_YTDLP_REQUESTS: usize = 4;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -38,109 +51,264 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let database_path =
         std::env::var("DATABASE_PATH").unwrap_or_else(|_| "./toobarr.db".to_string());
 
-    let pool = db::init_pool(&database_path).await?;
+    let max_connections = db::max_connections_from_env();
+    let pool = db::init_pool(&database_path, max_connections).await?;
 
     sqlx::migrate!("./migrations")
         .run(&pool)
         .await?;
 
-    tracing::info!("Database initialized at {}", database_path);
-
-    let mut yt_dlp = YtDlp::new();
-
-    if let Ok(Some(ytdlp_path)) = Settings::get(&pool, "ytdlp_path").await {
-        if !ytdlp_path.is_empty() {
-            yt_dlp = YtDlp::with_binary(&ytdlp_path);
-            tracing::info!("Using custom yt-dlp path: {}", ytdlp_path);
-        }
-    }
-
-    if let Ok(args_str) = Settings::get_extractor_args(&pool).await {
-        let parsed = api::parse_extractor_args(&args_str);
-        if !parsed.is_empty() {
-            yt_dlp.set_extra_args(parsed);
-        }
-    }
+    tracing::info!(
+        "Database initialized at {} (max {} connections)",
+        database_path,
+        max_connections
+    );
 
-    if let Ok(Some(cookies_path)) = Settings::get_cookies_file(&pool).await {
-        if !cookies_path.is_empty() {
-            let path = PathBuf::from(&cookies_path);
-            if path.exists() {
-                yt_dlp.set_cookies_file(Some(path));
-                tracing::info!("Using cookies file: {}", cookies_path);
-            }
-        }
-    }
+    let yt_dlp = configure_yt_dlp(&pool).await;
 
-    if let Ok(Some(ffmpeg_path)) = Settings::get(&pool, "ffmpeg_path").await {
-        if !ffmpeg_path.is_empty() {
-            yt_dlp.set_ffmpeg_location(Some(PathBuf::from(&ffmpeg_path)));
-            tracing::info!("Using custom ffmpeg path: {}", ffmpeg_path);
-        }
-    }
-
-    if let Ok(Some(deno_path)) = Settings::get(&pool, "deno_path").await {
-        if !deno_path.is_empty() {
-            if let Some(parent) = std::path::Path::new(&deno_path).parent() {
-                yt_dlp.set_env("PATH_PREPEND".to_string(), parent.to_string_lossy().to_string());
-                tracing::info!("Using custom deno path: {}", deno_path);
-            }
-        }
-    }
-
-    if let Err(e) = yt_dlp.check_binary().await {
-        tracing::warn!("yt-dlp not found or not executable: {}", e);
-    } else {
-        let version = yt_dlp.check_binary().await.unwrap_or_default();
-        tracing::info!("yt-dlp version: {}", version);
-    }
+    log_ytdlp_version(&yt_dlp).await;
 
     let yt_dlp = Arc::new(RwLock::new(yt_dlp));
 
     let (download_tx, download_rx) = mpsc::channel(100);
     let download_states = Arc::new(RwLock::new(HashMap::new()));
 
-    let worker = DownloadWorker::new(pool.clone(), yt_dlp.clone(), download_rx, download_states.clone());
+    let metrics = Metrics::default();
+
+    let worker = DownloadWorker::new(
+        pool.clone(),
+        yt_dlp.clone(),
+        download_rx,
+        download_states.clone(),
+        metrics.clone()
+    );
 
     tokio::spawn(async move {
         worker.run().await;
     });
 
+    tokio::spawn(RetentionWorker::new(pool.clone()).run());
+
     let state = AppState {
         pool,
         yt_dlp,
         download_tx,
-        download_states
+        download_states,
+        version_cache: VersionCache::new(VERSION_CACHE_TTL),
+        metrics,
+        thumbnail_progress: Arc::new(RwLock::new(HashMap::new()))
     };
 
-    let app = Router::new()
+    tokio::spawn(resume_downloads(state.clone()));
+
+    let app = app_routes(state);
+
+    let port = std::env::var("PORT").unwrap_or_else(|_| "8000".to_string());
+    let addr = format!("0.0.0.0:{port}");
+    tracing::info!("listening on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Pages and API routes; those that run yt-dlp come from [`ytdlp_routes`].
+fn app_routes(state: AppState) -> Router {
+    Router::new()
         .route("/", get(pages::home_page))
         .route("/channels", get(pages::channels_page))
         .route("/channels/new", get(pages::new_channel_page))
         .route("/channels/{id}", get(pages::channel_detail_page))
+        .route("/channels/{id}/rss", get(media::channel_feed))
+        .route("/channels/{id}/feed.xml", get(media::channel_feed))
         .route("/downloads", get(pages::downloads_page))
+        .route("/downloads/{id}/play", get(pages::player_page))
+        .route("/media/{id}", get(media::serve_media))
+        .route("/media/{id}/poster", get(media::serve_poster))
+        .route("/media/{id}/subtitles/{file}", get(media::serve_subtitle))
+        .route("/search", get(pages::search_page))
         .route("/settings", get(pages::settings_page))
-        .route("/api/channels", post(api::create_channel))
+        .route("/api/channels/export.opml", get(api::export_channels))
+        .route("/api/channels/export", get(api::export_channels_json))
         .route("/api/channels/{id}", delete(api::delete_channel))
-        .route("/api/channels/{id}/sync", post(api::sync_channel))
+        .route("/api/channels/{id}/retention", post(api::update_retention))
+        .route("/api/channels/{id}/date-range", post(api::update_date_range))
+        .route("/api/channels/{id}/match-filter", post(api::update_match_filter))
+        .route("/api/channels/{id}/audio-only", post(api::update_audio_only))
+        .route("/api/channels/{id}/thumbnails", get(api::thumbnail_progress))
         .route("/api/videos/{id}/download", post(api::start_download))
+        .route("/api/videos/{id}/watched", post(api::toggle_watched))
         .route("/api/downloads/{id}/cancel", post(api::cancel_download))
         .route("/api/downloads/{id}/retry", post(api::retry_download))
+        .route("/api/downloads/{id}/pause", post(api::pause_download))
+        .route("/api/downloads/{id}/resume", post(api::resume_download))
+        .route("/api/downloads/{id}/labels", post(api::add_download_label))
+        .route("/api/downloads/{id}/labels/{label}", delete(api::remove_download_label))
+        .route("/api/downloads/retry-failed", post(api::retry_failed_downloads))
+        .route("/api/downloads/cancel-active", post(api::cancel_active_downloads))
         .route("/api/downloads/active", get(api::active_downloads))
+        .route("/api/downloads/summary", get(api::download_summary))
+        .route("/api/downloads/queue", get(api::download_queue))
+        .route("/api/downloads/{id}/priority", post(api::set_download_priority))
         .route("/api/downloads/count", get(api::download_count))
         .route("/api/settings", post(api::update_settings))
+        .route("/api/health", get(api::health))
+        .route("/metrics", get(api::metrics))
         .route("/api/settings/cookies", post(api::upload_cookies))
         .route("/api/settings/cookies", delete(api::delete_cookies))
+        .route("/api/settings/cookies-browser", post(api::update_cookies_browser))
+        .route("/api/v1/channels", get(v1::list_channels))
+        .route("/api/v1/channels/{id}/videos", get(v1::list_channel_videos))
+        .route("/api/v1/downloads", get(v1::list_downloads))
+        .route("/api/v1/search", get(v1::search_videos))
+        .route("/api/v1/videos/{id}/download", post(v1::start_download))
+        .merge(ytdlp_routes())
         .nest_service("/static", ServeDir::new("static"))
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state)
+}
 
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8000".to_string());
-    let addr = format!("0.0.0.0:{port}");
-    tracing::info!("listening on {}", addr);
+/// Runs in the background so a long backlog does not hold up the listener
+/// while the worker channel fills. Requests may queue the same downloads
+/// meanwhile; the worker ignores a `Start` for one it already holds.
+async fn resume_downloads(state: AppState) {
+    if let Err(e) = api::resume_unfinished_downloads(&state).await {
+        tracing::error!("Failed to resume unfinished downloads: {}", e.message);
+    }
+}
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+/// Routes that run yt-dlp inside the request. They share one concurrency
+/// limit, set with `MAX_CONCURRENT_YTDLP_REQUESTS`, so a burst of channel
+/// adds or syncs queues up instead of spawning a process per request.
+fn ytdlp_routes() -> Router<AppState> {
+    let limit = std::env::var("MAX_CONCURRENT_YTDLP_REQUESTS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&limit| limit > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_YTDLP_REQUESTS);
+    tracing::info!("Limiting yt-dlp requests to {} at a time", limit);
 
-    Ok(())
+    Router::new()
+        .route("/api/channels", post(api::create_channel))
+        .route("/api/channels/import", post(api::import_channels))
+        .route("/api/channels/{id}/sync", post(api::sync_channel))
+        .route("/api/videos/{id}/refresh", post(api::refresh_video))
+        .route("/api/videos/{id}/subtitles", post(api::download_video_subtitles))
+        .route("/api/system/self-test", post(api::self_test))
+        .route("/api/settings/update-ytdlp", post(api::update_ytdlp))
+        .route_layer(GlobalConcurrencyLimitLayer::new(limit))
+}
+
+/// Builds the shared client from the binary, argument and environment settings.
+async fn configure_yt_dlp(pool: &DbPool) -> YtDlp {
+    let mut yt_dlp = YtDlp::new();
+
+    if let Ok(Some(ytdlp_path)) = Settings::get(pool, "ytdlp_path").await {
+        if !ytdlp_path.is_empty() {
+            yt_dlp = YtDlp::with_binary(&ytdlp_path);
+            tracing::info!("Using custom yt-dlp path: {}", ytdlp_path);
+        }
+    }
+
+    if let Ok(Some(cache_dir)) = Settings::get(pool, "cache_dir").await {
+        if !cache_dir.is_empty() {
+            match api::ensure_cache_dir(&cache_dir).await {
+                Ok(path) => {
+                    yt_dlp.set_cache_dir(Some(path));
+                    tracing::info!("Using yt-dlp cache directory: {}", cache_dir);
+                }
+                Err(e) => tracing::warn!("{}", e)
+            }
+        }
+    }
+
+    if let Ok(Some(config)) = Settings::get(pool, "ytdlp_config").await {
+        if !config.is_empty() {
+            match api::check_ytdlp_config(&config).await {
+                Ok(path) => {
+                    yt_dlp.set_config_location(Some(path));
+                    tracing::info!("Using yt-dlp config: {}", config);
+                }
+                Err(e) => tracing::warn!("{}", e)
+            }
+        }
+    }
+
+    if let Ok(args_str) = Settings::get_extractor_args(pool).await {
+        let parsed = api::parse_extractor_args(&args_str);
+        if !parsed.is_empty() {
+            yt_dlp.set_extra_args(parsed);
+        }
+    }
+
+    if let Ok(env_str) = Settings::get_env_vars(pool).await {
+        match api::parse_env_vars(&env_str) {
+            Ok(vars) => {
+                for (key, value) in vars {
+                    yt_dlp.set_env(key, value);
+                }
+            }
+            Err(e) => tracing::warn!("Ignoring invalid environment variables setting: {}", e)
+        }
+    }
+
+    if let Ok(Some(target)) = Settings::get(pool, "impersonate").await {
+        if !target.is_empty() {
+            tracing::info!("Impersonating {}", target);
+            yt_dlp.set_impersonate(Some(target));
+        }
+    }
+
+    if let Ok(Some(cookies_path)) = Settings::get_cookies_file(pool).await {
+        if !cookies_path.is_empty() {
+            let path = PathBuf::from(&cookies_path);
+            if path.exists() {
+                yt_dlp.set_cookies_file(Some(path));
+                tracing::info!("Using cookies file: {}", cookies_path);
+            }
+        }
+    }
+
+    if let Ok(Some(browser)) = Settings::get_cookies_from_browser(pool).await {
+        tracing::info!("Using cookies from browser: {}", browser);
+        yt_dlp.set_cookies_from_browser(Some(browser));
+    }
+
+    if let Ok(Some(ffmpeg_path)) = Settings::get(pool, "ffmpeg_path").await {
+        if !ffmpeg_path.is_empty() {
+            yt_dlp.set_ffmpeg_location(Some(PathBuf::from(&ffmpeg_path)));
+            tracing::info!("Using custom ffmpeg path: {}", ffmpeg_path);
+        }
+    }
+
+    if let Ok(Some(deno_path)) = Settings::get(pool, "deno_path").await {
+        if !deno_path.is_empty() {
+            if let Some(parent) = std::path::Path::new(&deno_path).parent() {
+                yt_dlp.set_env("PATH_PREPEND".to_string(), parent.to_string_lossy().to_string());
+                tracing::info!("Using custom deno path: {}", deno_path);
+            }
+        }
+    }
+
+    yt_dlp
+}
+
+async fn log_ytdlp_version(yt_dlp: &YtDlp) {
+    match yt_dlp.check_binary().await {
+        Ok(version) => {
+            tracing::info!("yt-dlp version: {}", version);
+            let (year, month, day) = MIN_YTDLP_VERSION;
+            if yt_dlp::parse_version(&version).is_some_and(|installed| installed < MIN_YTDLP_VERSION) {
+                tracing::warn!(
+                    "yt-dlp {} is older than {}.{:02}.{:02}; downloads may fail until yt-dlp is updated",
+                    version,
+                    year,
+                    month,
+                    day
+                );
+            }
+        }
+        Err(e) => tracing::warn!("yt-dlp not found or not executable: {}", e)
+    }
 }
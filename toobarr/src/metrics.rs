@@ -0,0 +1,124 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Process-lifetime counters updated by the download worker. They reset on
+/// restart, which Prometheus `rate()` and `increase()` handle.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    downloads_completed: Arc<AtomicU64>,
+    downloaded_bytes: Arc<AtomicU64>,
+    ytdlp_failures: Arc<AtomicU64>
+}
+
+impl Metrics {
+    pub fn record_completed(&self, bytes: Option<u64>) {
+        self.downloads_completed.fetch_add(1, Ordering::Relaxed);
+        if let Some(bytes) = bytes {
+            self.downloaded_bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Downloads that yt-dlp failed; user cancellations are not counted.
+    pub fn record_failure(&self) {
+        self.ytdlp_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time values read from the database and worker state.
+pub struct Gauges {
+    pub channels: i64,
+    pub videos: i64,
+    pub downloads_by_status: Vec<(String, i64)>,
+    pub active_downloads: usize
+}
+
+/// Renders the Prometheus text exposition format.
+pub fn render(metrics: &Metrics, gauges: &Gauges) -> String {
+    let mut out = String::new();
+
+    write_metric(&mut out, "toobarr_channels", "gauge", "Number of channels", gauges.channels);
+    write_metric(&mut out, "toobarr_videos", "gauge", "Number of known videos", gauges.videos);
+
+    let _ = writeln!(out, "# HELP toobarr_downloads Download rows by status");
+    let _ = writeln!(out, "# TYPE toobarr_downloads gauge");
+    for (status, count) in &gauges.downloads_by_status {
+        let _ = writeln!(out, "toobarr_downloads{{status=\"{}\"}} {count}", escape_label(status));
+    }
+
+    write_metric(
+        &mut out,
+        "toobarr_active_downloads",
+        "gauge",
+        "Downloads currently running or post-processing",
+        gauges.active_downloads
+    );
+    write_metric(
+        &mut out,
+        "toobarr_downloads_completed_total",
+        "counter",
+        "Downloads completed since start",
+        metrics.downloads_completed.load(Ordering::Relaxed)
+    );
+    write_metric(
+        &mut out,
+        "toobarr_downloaded_bytes_total",
+        "counter",
+        "Bytes of media downloaded since start",
+        metrics.downloaded_bytes.load(Ordering::Relaxed)
+    );
+    write_metric(
+        &mut out,
+        "toobarr_ytdlp_failures_total",
+        "counter",
+        "Downloads that failed in yt-dlp since start",
+        metrics.ytdlp_failures.load(Ordering::Relaxed)
+    );
+
+    out
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.record_completed(Some(1024));
+        metrics.record_completed(None);
+        metrics.record_failure();
+
+        let gauges = Gauges {
+            channels: 2,
+            videos: 40,
+            downloads_by_status: vec![("completed".to_string(), 2), ("failed".to_string(), 1)],
+            active_downloads: 0
+        };
+        let text = render(&metrics, &gauges);
+
+        assert!(text.contains("# TYPE toobarr_channels gauge\ntoobarr_channels 2\n"));
+        assert!(text.contains("toobarr_videos 40\n"));
+        assert!(text.contains("toobarr_downloads{status=\"completed\"} 2\n"));
+        assert!(text.contains("toobarr_downloads{status=\"failed\"} 1\n"));
+        assert!(text.contains("toobarr_active_downloads 0\n"));
+        assert!(text.contains("# TYPE toobarr_downloads_completed_total counter\ntoobarr_downloads_completed_total 2\n"));
+        assert!(text.contains("toobarr_downloaded_bytes_total 1024\n"));
+        assert!(text.contains("toobarr_ytdlp_failures_total 1\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
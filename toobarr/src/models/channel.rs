@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, SqlitePool};
+use yt_dlp::DateRange;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Channel {
@@ -11,6 +12,15 @@ pub struct Channel {
     pub description: Option<String>,
     pub video_count: Option<i64>,
     pub last_synced_at: Option<String>,
+    pub retention_keep_latest: Option<i64>,
+    pub retention_max_age_days: Option<i64>,
+    pub retention_delete_videos: bool,
+    pub date_after: Option<String>,
+    pub date_before: Option<String>,
+    pub match_filter: Option<String>,
+    pub no_shorts: bool,
+    /// Extract the audio track instead of keeping the video.
+    pub audio_only: bool,
     pub created_at: String,
     pub updated_at: String
 }
@@ -20,11 +30,69 @@ pub struct CreateChannel {
     pub url: String
 }
 
+/// Which completed downloads of a channel to keep on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub keep_latest: Option<i64>,
+    pub max_age_days: Option<i64>,
+    pub delete_videos: bool
+}
+
+/// The per-channel options carried by the JSON channel export.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub retention_keep_latest: Option<i64>,
+    pub retention_max_age_days: Option<i64>,
+    pub retention_delete_videos: bool,
+    pub date_after: Option<String>,
+    pub date_before: Option<String>,
+    pub match_filter: Option<String>,
+    pub no_shorts: bool,
+    pub audio_only: bool
+}
+
 impl Channel {
+    pub fn settings(&self) -> ChannelSettings {
+        ChannelSettings {
+            retention_keep_latest: self.retention_keep_latest,
+            retention_max_age_days: self.retention_max_age_days,
+            retention_delete_videos: self.retention_delete_videos,
+            date_after: self.date_after.clone(),
+            date_before: self.date_before.clone(),
+            match_filter: self.match_filter.clone(),
+            no_shorts: self.no_shorts,
+            audio_only: self.audio_only
+        }
+    }
+
+    /// `None` when neither retention rule is set for this channel.
+    pub fn retention_policy(&self) -> Option<RetentionPolicy> {
+        if self.retention_keep_latest.is_none() && self.retention_max_age_days.is_none() {
+            return None;
+        }
+        Some(RetentionPolicy {
+            keep_latest: self.retention_keep_latest,
+            max_age_days: self.retention_max_age_days,
+            delete_videos: self.retention_delete_videos
+        })
+    }
+
+    /// The upload date bounds for syncs, resolved against today. Values are
+    /// validated when saved, so an unparseable one is only logged and ignored.
+    pub fn date_range(&self) -> DateRange {
+        DateRange::parse(self.date_after.as_deref(), self.date_before.as_deref()).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring date range of channel {}: {}", self.name, e);
+            DateRange::default()
+        })
+    }
+
     pub async fn find_all(pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, youtube_id, name, url, thumbnail_url, description,
-                      video_count, last_synced_at, created_at, updated_at
+                      video_count, last_synced_at, retention_keep_latest, retention_max_age_days,
+                      retention_delete_videos, date_after, date_before, match_filter, no_shorts,
+                      audio_only, created_at, updated_at
                FROM channels ORDER BY created_at DESC"
         )
         .fetch_all(pool)
@@ -34,7 +102,9 @@ impl Channel {
     pub async fn find_by_id(pool: &SqlitePool, id: &str) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, youtube_id, name, url, thumbnail_url, description,
-                      video_count, last_synced_at, created_at, updated_at
+                      video_count, last_synced_at, retention_keep_latest, retention_max_age_days,
+                      retention_delete_videos, date_after, date_before, match_filter, no_shorts,
+                      audio_only, created_at, updated_at
                FROM channels WHERE id = ?"
         )
         .bind(id)
@@ -48,7 +118,9 @@ impl Channel {
     ) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, youtube_id, name, url, thumbnail_url, description,
-                      video_count, last_synced_at, created_at, updated_at
+                      video_count, last_synced_at, retention_keep_latest, retention_max_age_days,
+                      retention_delete_videos, date_after, date_before, match_filter, no_shorts,
+                      audio_only, created_at, updated_at
                FROM channels WHERE youtube_id = ?"
         )
         .bind(youtube_id)
@@ -106,6 +178,99 @@ impl Channel {
         Ok(result.rows_affected() > 0)
     }
 
+    pub async fn update_retention(
+        pool: &SqlitePool,
+        id: &str,
+        policy: &RetentionPolicy
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE channels SET retention_keep_latest = ?, retention_max_age_days = ?,
+               retention_delete_videos = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(policy.keep_latest)
+        .bind(policy.max_age_days)
+        .bind(policy.delete_videos)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_date_range(
+        pool: &SqlitePool,
+        id: &str,
+        date_after: Option<&str>,
+        date_before: Option<&str>
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE channels SET date_after = ?, date_before = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(date_after)
+        .bind(date_before)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_match_filter(
+        pool: &SqlitePool,
+        id: &str,
+        match_filter: Option<&str>,
+        no_shorts: bool
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE channels SET match_filter = ?, no_shorts = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(match_filter)
+        .bind(no_shorts)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_audio_only(pool: &SqlitePool, id: &str, audio_only: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE channels SET audio_only = ?, updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(audio_only)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces retention, date range, download filter and mode in one go, as
+    /// when importing a channel export.
+    pub async fn update_settings(
+        pool: &SqlitePool,
+        id: &str,
+        settings: &ChannelSettings
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE channels SET retention_keep_latest = ?, retention_max_age_days = ?,
+               retention_delete_videos = ?, date_after = ?, date_before = ?, match_filter = ?,
+               no_shorts = ?, audio_only = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(settings.retention_keep_latest)
+        .bind(settings.retention_max_age_days)
+        .bind(settings.retention_delete_videos)
+        .bind(&settings.date_after)
+        .bind(&settings.date_before)
+        .bind(&settings.match_filter)
+        .bind(settings.no_shorts)
+        .bind(settings.audio_only)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn update_thumbnail(
         pool: &SqlitePool,
         id: &str,
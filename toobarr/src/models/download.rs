@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
 use sqlx::{FromRow, Row, SqlitePool};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -6,8 +7,13 @@ use sqlx::{FromRow, Row, SqlitePool};
 pub enum DownloadStatus {
     Pending,
     Downloading,
+    /// Stopped by the user with its partial files kept for a resume.
+    Paused,
     Completed,
-    Failed
+    Failed,
+    /// yt-dlp declined the video, e.g. it is over the size limit; neither
+    /// retried nor counted as a failure.
+    Skipped
 }
 
 impl DownloadStatus {
@@ -15,8 +21,10 @@ impl DownloadStatus {
         match self {
             Self::Pending => "pending",
             Self::Downloading => "downloading",
+            Self::Paused => "paused",
             Self::Completed => "completed",
-            Self::Failed => "failed"
+            Self::Failed => "failed",
+            Self::Skipped => "skipped"
         }
     }
 }
@@ -38,6 +46,14 @@ pub struct Download {
     pub error_message: Option<String>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
+    pub format_id: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    /// The format selector that was not available when yt-dlp fell back to
+    /// the best format.
+    pub format_fallback: Option<String>,
+    /// Failed attempts so far; reset by a manual retry.
+    pub attempt_count: i64,
     pub created_at: String,
     pub updated_at: String
 }
@@ -47,68 +63,100 @@ pub struct DownloadWithVideo {
     pub download: Download,
     pub video_title: String,
     pub video_thumbnail: Option<String>,
-    pub channel_name: String
+    pub channel_name: String,
+    pub labels: Vec<String>
+}
+
+/// A pending download and its place in the queue, 1 being the next to start.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct QueueEntry {
+    pub position: i64,
+    pub download_id: String,
+    pub video_id: String,
+    pub title: String,
+    pub priority: i64,
+    pub created_at: String
+}
+
+/// A completed, not yet pruned download considered by the retention worker.
+#[derive(Debug, Clone, FromRow)]
+pub struct RetentionCandidate {
+    pub download_id: String,
+    pub video_id: String,
+    pub youtube_id: String,
+    pub file_path: Option<String>,
+    pub age_days: i64
+}
+
+/// A completed download with the video fields a channel feed item needs.
+#[derive(Debug, Clone, FromRow)]
+pub struct FeedEntry {
+    pub download_id: String,
+    pub file_path: String,
+    pub file_size_bytes: Option<i64>,
+    pub completed_at: Option<String>,
+    pub youtube_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub duration_seconds: Option<i64>,
+    pub upload_date: Option<String>
 }
 
 impl Download {
     pub fn status_enum(&self) -> DownloadStatus {
         match self.status.as_str() {
             "downloading" => DownloadStatus::Downloading,
+            "paused" => DownloadStatus::Paused,
             "completed" => DownloadStatus::Completed,
             "failed" => DownloadStatus::Failed,
+            "skipped" => DownloadStatus::Skipped,
             _ => DownloadStatus::Pending
         }
     }
 
+    pub fn resolution(&self) -> Option<String> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Some(format!("{width}x{height}")),
+            _ => None
+        }
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     pub fn progress_int(&self) -> i64 {
         self.progress_percent.unwrap_or(0.0) as i64
     }
 
+    /// With a `label`, only downloads carrying that label are returned.
     pub async fn find_all_with_video(
-        pool: &SqlitePool
+        pool: &SqlitePool,
+        label: Option<&str>
     ) -> Result<Vec<DownloadWithVideo>, sqlx::Error> {
-        let rows = sqlx::query(
-            r"SELECT d.id, d.video_id, d.status, d.file_path, d.file_size_bytes,
-                      d.progress_percent, d.error_message, d.started_at, d.completed_at,
-                      d.created_at, d.updated_at,
-                      v.title as video_title, v.thumbnail_url as video_thumbnail,
-                      c.name as channel_name
-               FROM downloads d
-               JOIN videos v ON d.video_id = v.id
-               JOIN channels c ON v.channel_id = c.id
+        let query = format!(
+            "{DOWNLOAD_WITH_VIDEO_SELECT}
+               WHERE ?1 IS NULL OR EXISTS (
+                   SELECT 1 FROM download_labels l WHERE l.download_id = d.id AND l.label = ?1
+               )
                ORDER BY d.created_at DESC"
-        )
-        .fetch_all(pool)
-        .await?;
+        );
+        let rows = sqlx::query(&query).bind(label).fetch_all(pool).await?;
+        Ok(rows.iter().map(download_with_video).collect())
+    }
 
-        Ok(rows
-            .into_iter()
-            .map(|r| DownloadWithVideo {
-                download: Download {
-                    id: r.get("id"),
-                    video_id: r.get("video_id"),
-                    status: r.get("status"),
-                    file_path: r.get("file_path"),
-                    file_size_bytes: r.get("file_size_bytes"),
-                    progress_percent: r.get("progress_percent"),
-                    error_message: r.get("error_message"),
-                    started_at: r.get("started_at"),
-                    completed_at: r.get("completed_at"),
-                    created_at: r.get("created_at"),
-                    updated_at: r.get("updated_at")
-                },
-                video_title: r.get("video_title"),
-                video_thumbnail: r.get("video_thumbnail"),
-                channel_name: r.get("channel_name")
-            })
-            .collect())
+    pub async fn find_with_video_by_id(
+        pool: &SqlitePool,
+        id: &str
+    ) -> Result<Option<DownloadWithVideo>, sqlx::Error> {
+        let query = format!("{DOWNLOAD_WITH_VIDEO_SELECT} WHERE d.id = ?");
+        let row = sqlx::query(&query).bind(id).fetch_optional(pool).await?;
+        Ok(row.as_ref().map(download_with_video))
     }
 
     pub async fn find_by_id(pool: &SqlitePool, id: &str) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, video_id, status, file_path, file_size_bytes, progress_percent,
-                      error_message, started_at, completed_at, created_at, updated_at
+                      error_message, started_at, completed_at, format_id, width, height,
+                      format_fallback, attempt_count, created_at, updated_at
                FROM downloads WHERE id = ?"
         )
         .bind(id)
@@ -116,11 +164,26 @@ impl Download {
         .await
     }
 
-    #[allow(dead_code)]
+    pub async fn find_by_status(
+        pool: &SqlitePool,
+        status: DownloadStatus
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r"SELECT id, video_id, status, file_path, file_size_bytes, progress_percent,
+                      error_message, started_at, completed_at, format_id, width, height,
+                      format_fallback, attempt_count, created_at, updated_at
+               FROM downloads WHERE status = ? ORDER BY created_at ASC"
+        )
+        .bind(status.as_str())
+        .fetch_all(pool)
+        .await
+    }
+
     pub async fn find_pending(pool: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, video_id, status, file_path, file_size_bytes, progress_percent,
-                      error_message, started_at, completed_at, created_at, updated_at
+                      error_message, started_at, completed_at, format_id, width, height,
+                      format_fallback, attempt_count, created_at, updated_at
                FROM downloads WHERE status = 'pending' ORDER BY created_at ASC"
         )
         .fetch_all(pool)
@@ -133,7 +196,8 @@ impl Download {
     ) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, video_id, status, file_path, file_size_bytes, progress_percent,
-                      error_message, started_at, completed_at, created_at, updated_at
+                      error_message, started_at, completed_at, format_id, width, height,
+                      format_fallback, attempt_count, created_at, updated_at
                FROM downloads WHERE video_id = ? ORDER BY created_at DESC LIMIT 1"
         )
         .bind(video_id)
@@ -141,13 +205,79 @@ impl Download {
         .await
     }
 
-    pub async fn insert(pool: &SqlitePool, id: &str, video_id: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("INSERT INTO downloads (id, video_id) VALUES (?, ?)")
+    pub async fn file_paths_for_channel(
+        pool: &SqlitePool,
+        channel_id: &str
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r"SELECT d.file_path FROM downloads d
+               JOIN videos v ON v.id = d.video_id
+               WHERE v.channel_id = ? AND d.file_path IS NOT NULL"
+        )
+        .bind(channel_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Newest video first, skipping pruned downloads whose file is gone.
+    pub async fn find_feed_entries(
+        pool: &SqlitePool,
+        channel_id: &str
+    ) -> Result<Vec<FeedEntry>, sqlx::Error> {
+        sqlx::query_as::<_, FeedEntry>(
+            r"SELECT d.id AS download_id, d.file_path, d.file_size_bytes, d.completed_at,
+                      v.youtube_id, v.title, v.description, v.thumbnail_url,
+                      v.duration_seconds, v.upload_date
+               FROM downloads d
+               JOIN videos v ON v.id = d.video_id
+               WHERE v.channel_id = ? AND d.status = 'completed' AND d.file_path IS NOT NULL
+               ORDER BY v.upload_date DESC, d.completed_at DESC"
+        )
+        .bind(channel_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Newest video first, so the head of the list is what `keep_latest` keeps.
+    pub async fn find_retention_candidates(
+        pool: &SqlitePool,
+        channel_id: &str
+    ) -> Result<Vec<RetentionCandidate>, sqlx::Error> {
+        sqlx::query_as::<_, RetentionCandidate>(
+            r"SELECT d.id AS download_id, d.video_id, v.youtube_id, d.file_path,
+                      CAST(julianday('now') - julianday(d.completed_at) AS INTEGER) AS age_days
+               FROM downloads d
+               JOIN videos v ON v.id = d.video_id
+               WHERE v.channel_id = ? AND d.status = 'completed' AND d.pruned_at IS NULL
+               ORDER BY v.upload_date DESC, d.completed_at DESC"
+        )
+        .bind(channel_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Keeps the row as completed so the video is not downloaded again.
+    pub async fn mark_pruned(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET file_path = NULL, pruned_at = datetime('now'),
+               updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Inserts a pending download. Returns `false` without inserting when the
+    /// video already has a pending or downloading one.
+    pub async fn insert(pool: &SqlitePool, id: &str, video_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("INSERT INTO downloads (id, video_id) VALUES (?, ?) ON CONFLICT DO NOTHING")
             .bind(id)
             .bind(video_id)
             .execute(pool)
             .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     pub async fn update_status(
@@ -161,8 +291,8 @@ impl Download {
         match status {
             DownloadStatus::Downloading => {
                 sqlx::query(
-                    r"UPDATE downloads SET status = ?, started_at = ?, updated_at = datetime('now')
-                       WHERE id = ?"
+                    r"UPDATE downloads SET status = ?, started_at = ?, format_fallback = NULL,
+                       updated_at = datetime('now') WHERE id = ?"
                 )
                 .bind(status_str)
                 .bind(&now)
@@ -194,6 +324,51 @@ impl Download {
         Ok(())
     }
 
+    /// Puts the downloads a previous run left `downloading` back to
+    /// `pending`. Returns how many were reset.
+    pub async fn reset_interrupted(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE downloads SET status = 'pending', updated_at = datetime('now')
+               WHERE status = 'downloading'"
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Counts a failed attempt and returns the new total.
+    pub async fn record_failed_attempt(pool: &SqlitePool, id: &str) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r"UPDATE downloads SET attempt_count = attempt_count + 1, updated_at = datetime('now')
+               WHERE id = ? RETURNING attempt_count"
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Puts a failed download back to `pending` for an automatic retry,
+    /// keeping `note` as its message.
+    pub async fn update_retrying(pool: &SqlitePool, id: &str, note: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET status = 'pending', error_message = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(note)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn reset_attempts(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE downloads SET attempt_count = 0, updated_at = datetime('now') WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
     pub async fn update_progress(
         pool: &SqlitePool,
         id: &str,
@@ -230,14 +405,49 @@ impl Download {
         Ok(())
     }
 
+    pub async fn update_format(
+        pool: &SqlitePool,
+        id: &str,
+        format_id: Option<&str>,
+        width: Option<i64>,
+        height: Option<i64>
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET format_id = ?, width = ?, height = ?,
+               updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(format_id)
+        .bind(width)
+        .bind(height)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn update_format_fallback(
+        pool: &SqlitePool,
+        id: &str,
+        requested: Option<&str>
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET format_fallback = ?, updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(requested)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn update_failed(
         pool: &SqlitePool,
         id: &str,
         error_message: &str
     ) -> Result<(), sqlx::Error> {
         sqlx::query(
-            r"UPDATE downloads SET status = 'failed', error_message = ?,
-               updated_at = datetime('now') WHERE id = ?"
+            r"UPDATE downloads SET status = 'failed', error_message = ?, file_path = NULL,
+               file_size_bytes = NULL, updated_at = datetime('now') WHERE id = ?"
         )
         .bind(error_message)
         .bind(id)
@@ -246,6 +456,113 @@ impl Download {
         Ok(())
     }
 
+    /// `destination` is the file yt-dlp was writing, kept in `file_path` so
+    /// its partial files can be found again if the download is cancelled.
+    pub async fn update_paused(
+        pool: &SqlitePool,
+        id: &str,
+        destination: Option<&str>
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET status = 'paused', file_path = COALESCE(?, file_path),
+               updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(destination)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// `reason` is kept in `error_message` to show why nothing was downloaded.
+    pub async fn update_skipped(pool: &SqlitePool, id: &str, reason: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE downloads SET status = 'skipped', error_message = ?,
+               updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(reason)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Row counts per status, for the metrics endpoint.
+    pub async fn count_by_status(pool: &SqlitePool) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        sqlx::query_as::<_, (String, i64)>(
+            r"SELECT status, COUNT(*) FROM downloads GROUP BY status ORDER BY status"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Returns `false` if the download already had the label.
+    pub async fn add_label(pool: &SqlitePool, id: &str, label: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO download_labels (download_id, label) VALUES (?, ?)"
+        )
+        .bind(id)
+        .bind(label)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_label(pool: &SqlitePool, id: &str, label: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM download_labels WHERE download_id = ? AND label = ?")
+            .bind(id)
+            .bind(label)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every label in use, for the downloads page filter.
+    pub async fn all_labels(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT DISTINCT label FROM download_labels ORDER BY label")
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Pending downloads in the order the worker starts them: highest
+    /// priority first, then oldest.
+    pub async fn queue(pool: &SqlitePool) -> Result<Vec<QueueEntry>, sqlx::Error> {
+        sqlx::query_as::<_, QueueEntry>(
+            r"SELECT ROW_NUMBER() OVER (ORDER BY d.priority DESC, d.created_at ASC, d.rowid ASC) AS position,
+                      d.id AS download_id, d.video_id, v.title, d.priority, d.created_at
+               FROM downloads d
+               JOIN videos v ON v.id = d.video_id
+               WHERE d.status = 'pending'
+               ORDER BY position"
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Highest and lowest priority among pending downloads, `(0, 0)` when
+    /// none are pending.
+    pub async fn queue_priority_bounds(pool: &SqlitePool) -> Result<(i64, i64), sqlx::Error> {
+        sqlx::query_as(
+            r"SELECT COALESCE(MAX(priority), 0), COALESCE(MIN(priority), 0)
+               FROM downloads WHERE status = 'pending'"
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Only pending downloads are reprioritized; returns whether one was.
+    pub async fn set_priority(pool: &SqlitePool, id: &str, priority: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE downloads SET priority = ?, updated_at = datetime('now')
+               WHERE id = ? AND status = 'pending'"
+        )
+        .bind(priority)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     #[allow(dead_code)]
     pub async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("DELETE FROM downloads WHERE id = ?")
@@ -255,3 +572,182 @@ impl Download {
         Ok(result.rows_affected() > 0)
     }
 }
+
+const MAX_LABEL_LEN: usize = 50;
+
+const DOWNLOAD_WITH_VIDEO_SELECT: &str = r"SELECT d.id, d.video_id, d.status, d.file_path, d.file_size_bytes,
+                      d.progress_percent, d.error_message, d.started_at, d.completed_at,
+                      d.format_id, d.width, d.height, d.format_fallback, d.attempt_count, d.created_at, d.updated_at,
+                      v.title as video_title, v.thumbnail_url as video_thumbnail,
+                      c.name as channel_name,
+                      (SELECT json_group_array(label) FROM
+                          (SELECT label FROM download_labels WHERE download_id = d.id ORDER BY label)
+                      ) as labels
+               FROM downloads d
+               JOIN videos v ON d.video_id = v.id
+               JOIN channels c ON v.channel_id = c.id";
+
+fn download_with_video(r: &SqliteRow) -> DownloadWithVideo {
+    let labels: Option<String> = r.get("labels");
+    DownloadWithVideo {
+        download: Download {
+            id: r.get("id"),
+            video_id: r.get("video_id"),
+            status: r.get("status"),
+            file_path: r.get("file_path"),
+            file_size_bytes: r.get("file_size_bytes"),
+            progress_percent: r.get("progress_percent"),
+            error_message: r.get("error_message"),
+            started_at: r.get("started_at"),
+            completed_at: r.get("completed_at"),
+            format_id: r.get("format_id"),
+            width: r.get("width"),
+            height: r.get("height"),
+            format_fallback: r.get("format_fallback"),
+            attempt_count: r.get("attempt_count"),
+            created_at: r.get("created_at"),
+            updated_at: r.get("updated_at")
+        },
+        video_title: r.get("video_title"),
+        video_thumbnail: r.get("video_thumbnail"),
+        channel_name: r.get("channel_name"),
+        labels: labels
+            .and_then(|l| serde_json::from_str(&l).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Trims and lowercases a label so `Favorite` and `favorite ` are the same.
+pub fn normalize_label(label: &str) -> Result<String, String> {
+    let label = label.trim().to_lowercase();
+    if label.is_empty() {
+        return Err("Label must not be empty".to_string());
+    }
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(format!("Label must be at most {MAX_LABEL_LEN} characters"));
+    }
+    Ok(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Channel, Video};
+
+    async fn pool_with_download() -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+
+        Channel::insert(&pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        Video::upsert(
+            &pool, "v1", "c1", "yt1", "Video", None, None, None, None, None,
+            "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+        Download::insert(&pool, "d1", "v1").await.unwrap();
+        pool
+    }
+
+    async fn labels(pool: &SqlitePool) -> Vec<String> {
+        Download::find_with_video_by_id(pool, "d1").await.unwrap().unwrap().labels
+    }
+
+    #[tokio::test]
+    async fn test_queue_orders_by_priority_then_age() {
+        let pool = pool_with_download().await;
+        for (video, download) in [("v2", "d2"), ("v3", "d3")] {
+            Video::upsert(
+                &pool, video, "c1", video, video, None, None, None, None, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+            Download::insert(&pool, download, video).await.unwrap();
+        }
+        let order = |queue: Vec<QueueEntry>| -> Vec<(i64, String)> {
+            queue.into_iter().map(|e| (e.position, e.download_id)).collect()
+        };
+        let ids = |ids: [&str; 3]| -> Vec<(i64, String)> { (1..).zip(ids.map(String::from)).collect() };
+
+        assert_eq!(order(Download::queue(&pool).await.unwrap()), ids(["d1", "d2", "d3"]));
+
+        assert!(Download::set_priority(&pool, "d3", 5).await.unwrap());
+        assert!(Download::set_priority(&pool, "d1", -1).await.unwrap());
+        assert_eq!(order(Download::queue(&pool).await.unwrap()), ids(["d3", "d2", "d1"]));
+        assert_eq!(Download::queue_priority_bounds(&pool).await.unwrap(), (5, -1));
+
+        Download::update_status(&pool, "d3", DownloadStatus::Downloading).await.unwrap();
+        assert!(!Download::set_priority(&pool, "d3", 0).await.unwrap());
+        assert_eq!(Download::queue(&pool).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_attempts() {
+        let pool = pool_with_download().await;
+
+        assert_eq!(Download::record_failed_attempt(&pool, "d1").await.unwrap(), 1);
+        assert_eq!(Download::record_failed_attempt(&pool, "d1").await.unwrap(), 2);
+        Download::update_retrying(&pool, "d1", "Retrying in 60s").await.unwrap();
+        let download = Download::find_by_id(&pool, "d1").await.unwrap().unwrap();
+        assert_eq!(download.status_enum(), DownloadStatus::Pending);
+        assert_eq!(download.attempt_count, 2);
+        assert_eq!(download.error_message.as_deref(), Some("Retrying in 60s"));
+
+        Download::reset_attempts(&pool, "d1").await.unwrap();
+        assert_eq!(Download::find_by_id(&pool, "d1").await.unwrap().unwrap().attempt_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_skipped_is_not_failed() {
+        let pool = pool_with_download().await;
+
+        Download::update_skipped(&pool, "d1", "Skipped, file is larger than max-filesize").await.unwrap();
+        let download = Download::find_by_id(&pool, "d1").await.unwrap().unwrap();
+        assert_eq!(download.status_enum(), DownloadStatus::Skipped);
+        assert_eq!(download.error_message.as_deref(), Some("Skipped, file is larger than max-filesize"));
+        assert!(Download::find_by_status(&pool, DownloadStatus::Failed).await.unwrap().is_empty());
+        assert!(Download::find_pending(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_label_crud() {
+        let pool = pool_with_download().await;
+
+        assert!(Download::add_label(&pool, "d1", "to-watch").await.unwrap());
+        assert!(Download::add_label(&pool, "d1", "favorite").await.unwrap());
+        assert!(!Download::add_label(&pool, "d1", "favorite").await.unwrap());
+        assert_eq!(labels(&pool).await, vec!["favorite", "to-watch"]);
+        assert_eq!(Download::all_labels(&pool).await.unwrap(), vec!["favorite", "to-watch"]);
+
+        assert!(Download::remove_label(&pool, "d1", "favorite").await.unwrap());
+        assert!(!Download::remove_label(&pool, "d1", "favorite").await.unwrap());
+        assert_eq!(labels(&pool).await, vec!["to-watch"]);
+
+        assert!(Download::delete(&pool, "d1").await.unwrap());
+        assert!(Download::all_labels(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_all_with_video_filters_by_label() {
+        let pool = pool_with_download().await;
+
+        let all = Download::find_all_with_video(&pool, None).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all[0].labels.is_empty());
+        assert!(Download::find_all_with_video(&pool, Some("favorite")).await.unwrap().is_empty());
+
+        Download::add_label(&pool, "d1", "favorite").await.unwrap();
+        let favorites = Download::find_all_with_video(&pool, Some("favorite")).await.unwrap();
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].download.id, "d1");
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label("  Favorite "), Ok("favorite".to_string()));
+        assert!(normalize_label("   ").is_err());
+        assert!(normalize_label(&"x".repeat(51)).is_err());
+    }
+}
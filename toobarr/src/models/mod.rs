@@ -3,7 +3,13 @@ mod download;
 mod settings;
 mod video;
 
-pub use channel::{Channel, CreateChannel};
-pub use download::{Download, DownloadStatus, DownloadWithVideo};
-pub use settings::Settings;
-pub use video::Video;
+pub use channel::{Channel, ChannelSettings, CreateChannel, RetentionPolicy};
+pub use download::{
+    normalize_label, Download, DownloadStatus, DownloadWithVideo, FeedEntry, QueueEntry, RetentionCandidate
+};
+pub use settings::{
+    parse_clock_time, parse_subtitle_langs, DirectoryLayout, AUDIO_FORMATS, EpisodeScheme, Settings, DEFAULT_CONCURRENT_FRAGMENTS, DEFAULT_INTEGRITY_TOLERANCE,
+    DEFAULT_OUTPUT_TEMPLATE, DEFAULT_RETRY_BASE_DELAY, DEFAULT_RETRY_MAX_ATTEMPTS,
+    REMUX_FORMATS, SUBTITLE_FORMATS, THUMBNAIL_FORMATS
+};
+pub use video::{Video, VideoWithChannel};
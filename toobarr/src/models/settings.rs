@@ -1,7 +1,24 @@
+use chrono::NaiveTime;
 use sqlx::{Row, SqlitePool};
 
 pub struct Settings;
 
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "%(upload_date)s - %(title)s [%(id)s].%(ext)s";
+const DEFAULT_SUBTITLE_LANGS: &str = "en";
+const DEFAULT_AUDIO_ONLY_FORMAT: &str = "mp3";
+pub const DEFAULT_CONCURRENT_FRAGMENTS: u32 = 4;
+pub const DEFAULT_INTEGRITY_TOLERANCE: u32 = 10;
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+pub const DEFAULT_RETRY_BASE_DELAY: u64 = 30;
+/// Thumbnail formats Kodi and Jellyfin read; yt-dlp often fetches WebP.
+pub const THUMBNAIL_FORMATS: [&str; 2] = ["jpg", "png"];
+/// Formats offered for `--audio-format`, as in the settings form.
+pub const AUDIO_FORMATS: [&str; 4] = ["mp3", "m4a", "opus", "flac"];
+/// Containers offered for `--remux-video`.
+pub const REMUX_FORMATS: [&str; 3] = ["mp4", "mkv", "webm"];
+/// Formats offered for `--convert-subs`.
+pub const SUBTITLE_FORMATS: [&str; 3] = ["srt", "vtt", "ass"];
+
 impl Settings {
     pub async fn get(pool: &SqlitePool, key: &str) -> Result<Option<String>, sqlx::Error> {
         let row = sqlx::query("SELECT value FROM settings WHERE key = ?")
@@ -35,16 +52,243 @@ impl Settings {
         Ok(value.parse().unwrap_or(2))
     }
 
+    /// Fragments of DASH/HLS formats fetched in parallel; defaults to 4.
+    pub async fn get_concurrent_fragments(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+        let value = Self::get(pool, "concurrent_fragments").await?;
+        Ok(value
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_CONCURRENT_FRAGMENTS))
+    }
+
+    /// How many percent shorter than the reported duration a finished file may
+    /// be before it is failed as truncated; 0 turns the check off.
+    pub async fn get_integrity_tolerance(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+        let value = Self::get(pool, "integrity_tolerance").await?;
+        Ok(value
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n <= 100)
+            .unwrap_or(DEFAULT_INTEGRITY_TOLERANCE))
+    }
+
+    /// Attempts a download gets before a transient error fails it, 1 to 10;
+    /// 1 turns automatic retries off.
+    pub async fn get_retry_max_attempts(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+        let value = Self::get(pool, "retry_max_attempts").await?;
+        Ok(value
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|n| (1..=10).contains(n))
+            .unwrap_or(DEFAULT_RETRY_MAX_ATTEMPTS))
+    }
+
+    /// Seconds before the first automatic retry, doubled for each further one.
+    pub async fn get_retry_base_delay(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+        let value = Self::get(pool, "retry_base_delay").await?;
+        Ok(value
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|n| (1..=3600).contains(n))
+            .unwrap_or(DEFAULT_RETRY_BASE_DELAY))
+    }
+
+    /// Maximum filename length yt-dlp trims to, or `None` for no limit.
+    pub async fn get_trim_filenames(pool: &SqlitePool) -> Result<Option<u32>, sqlx::Error> {
+        Ok(Self::get(pool, "trim_filenames")
+            .await?
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n > 0))
+    }
+
+    /// Tallest video resolution to download, e.g. 1080, or `None` for the best
+    /// available.
+    pub async fn get_max_height(pool: &SqlitePool) -> Result<Option<u32>, sqlx::Error> {
+        Ok(Self::get(pool, "max_height")
+            .await?
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n > 0))
+    }
+
+    pub async fn get_directory_layout(pool: &SqlitePool) -> Result<DirectoryLayout, sqlx::Error> {
+        Ok(Self::get(pool, "directory_layout")
+            .await?
+            .and_then(|v| DirectoryLayout::parse(&v))
+            .unwrap_or_default())
+    }
+
+    pub async fn get_episode_scheme(pool: &SqlitePool) -> Result<EpisodeScheme, sqlx::Error> {
+        Ok(Self::get(pool, "nfo_episode_scheme")
+            .await?
+            .and_then(|v| EpisodeScheme::parse(&v))
+            .unwrap_or_default())
+    }
+
+    /// The format thumbnails are converted to before being embedded or saved
+    /// alongside the video.
+    pub async fn get_thumbnail_format(pool: &SqlitePool) -> Result<&'static str, sqlx::Error> {
+        let format = Self::get(pool, "thumbnail_format").await?;
+        Ok(THUMBNAIL_FORMATS
+            .into_iter()
+            .find(|&f| format.as_deref().map(str::trim) == Some(f))
+            .unwrap_or(THUMBNAIL_FORMATS[0]))
+    }
+
+    /// Maximum download speed per download, e.g. `2M`.
+    pub async fn get_rate_limit(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "rate_limit")
+            .await?
+            .filter(|v| !v.trim().is_empty()))
+    }
+
+    /// The daytime rate limit plus the optional night window and its limit.
+    /// The window only applies when both `night_start` and `night_end` are
+    /// set; a blank night limit means unlimited inside it.
+    pub async fn get_rate_schedule(pool: &SqlitePool) -> Result<RateSchedule, sqlx::Error> {
+        let time = |value: Option<String>| value.as_deref().and_then(parse_clock_time);
+        let start = time(Self::get(pool, "night_start").await?);
+        let end = time(Self::get(pool, "night_end").await?);
+        Ok(RateSchedule {
+            day: Self::get_rate_limit(pool).await?,
+            night: Self::get(pool, "night_rate_limit")
+                .await?
+                .filter(|v| !v.trim().is_empty()),
+            window: start.zip(end)
+        })
+    }
+
+    pub async fn get_throttled_rate(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "throttled_rate")
+            .await?
+            .filter(|v| !v.trim().is_empty()))
+    }
+
+    /// Largest file a download may produce, e.g. `2G`; larger ones are skipped.
+    pub async fn get_max_filesize(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "max_filesize")
+            .await?
+            .filter(|v| !v.trim().is_empty()))
+    }
+
+    /// Where in-progress downloads are written before yt-dlp moves the
+    /// finished file into the download path.
+    pub async fn get_temp_dir(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "temp_dir")
+            .await?
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()))
+    }
+
     pub async fn get_extractor_args(pool: &SqlitePool) -> Result<String, sqlx::Error> {
         Ok(Self::get(pool, "extractor_args")
             .await?
             .unwrap_or_default())
     }
 
+    pub async fn get_env_vars(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "env_vars")
+            .await?
+            .unwrap_or_default())
+    }
+
     pub async fn get_cookies_file(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
         Self::get(pool, "cookies_file").await
     }
 
+    /// The `--cookies-from-browser` spec, e.g. `firefox` or `chrome:Profile 1`.
+    pub async fn get_cookies_from_browser(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "cookies_from_browser")
+            .await?
+            .filter(|s| !s.is_empty()))
+    }
+
+    pub async fn get_output_template(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "output_template")
+            .await?
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_OUTPUT_TEMPLATE.to_string()))
+    }
+
+    pub async fn get_remux_video(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "remux_video")
+            .await?
+            .filter(|s| !s.is_empty()))
+    }
+
+    /// The format to extract audio to, or `None` to keep the video.
+    pub async fn get_audio_format(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "audio_format")
+            .await?
+            .filter(|s| !s.is_empty()))
+    }
+
+    /// The format for channels in audio-only mode when `audio_format` does
+    /// not already turn every download into audio.
+    pub async fn get_audio_only_format(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "audio_only_format")
+            .await?
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| DEFAULT_AUDIO_ONLY_FORMAT.to_string()))
+    }
+
+    pub async fn get_audio_quality(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "audio_quality")
+            .await?
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()))
+    }
+
+    pub async fn get_ytdlp_path(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "ytdlp_path")
+            .await?
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "yt-dlp".to_string()))
+    }
+
+    pub async fn get_ffmpeg_path(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "ffmpeg_path")
+            .await?
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "ffmpeg".to_string()))
+    }
+
+    pub async fn get_ffprobe_path(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+        Ok(Self::get(pool, "ffprobe_path")
+            .await?
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "ffprobe".to_string()))
+    }
+
+    pub async fn get_bool(pool: &SqlitePool, key: &str) -> Result<bool, sqlx::Error> {
+        Ok(Self::get(pool, key).await?.is_some_and(|v| v == "true"))
+    }
+
+    /// Whether finished files take the upload date as their modification
+    /// time; on unless turned off, which passes `--no-mtime` instead.
+    pub async fn get_set_mtime(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+        Ok(Self::get(pool, "set_mtime").await?.is_none_or(|v| v == "true"))
+    }
+
+    pub async fn get_download_subtitles(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+        Self::get_bool(pool, "download_subtitles").await
+    }
+
+    pub async fn get_include_auto_subs(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+        Self::get_bool(pool, "include_auto_subs").await
+    }
+
+    pub async fn get_subtitle_langs(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+        let value = Self::get(pool, "subtitle_langs")
+            .await?
+            .unwrap_or_else(|| DEFAULT_SUBTITLE_LANGS.to_string());
+        Ok(parse_subtitle_langs(&value))
+    }
+
+    /// The format subtitles are converted to, or `None` to keep what the site
+    /// serves (usually `vtt` on `YouTube`).
+    pub async fn get_subtitle_format(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+        Ok(Self::get(pool, "subtitle_format")
+            .await?
+            .filter(|s| !s.is_empty()))
+    }
+
     #[allow(dead_code)]
     pub async fn get_all(pool: &SqlitePool) -> Result<Vec<(String, String)>, sqlx::Error> {
         let rows = sqlx::query("SELECT key, value FROM settings ORDER BY key")
@@ -53,3 +297,191 @@ impl Settings {
         Ok(rows.into_iter().map(|r| (r.get("key"), r.get("value"))).collect())
     }
 }
+
+/// Rate limits chosen by time of day.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateSchedule {
+    pub day: Option<String>,
+    pub night: Option<String>,
+    pub window: Option<(NaiveTime, NaiveTime)>
+}
+
+impl RateSchedule {
+    /// The limit to apply at `now`. A window whose start is after its end,
+    /// e.g. 23:00 to 07:00, wraps past midnight.
+    pub fn limit_at(&self, now: NaiveTime) -> Option<&str> {
+        let at_night = self.window.is_some_and(|(start, end)| {
+            if start <= end {
+                start <= now && now < end
+            } else {
+                now >= start || now < end
+            }
+        });
+        if at_night {
+            self.night.as_deref()
+        } else {
+            self.day.as_deref()
+        }
+    }
+}
+
+/// How NFO files number episodes so media servers can order a channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EpisodeScheme {
+    /// No season or episode; media servers sort by title.
+    #[default]
+    None,
+    /// Everything in season 1, numbered by upload order within the channel.
+    Index,
+    /// One season per upload year, numbered by upload order within the year.
+    Year
+}
+
+impl EpisodeScheme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Index => "index",
+            Self::Year => "year"
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "" | "none" => Some(Self::None),
+            "index" => Some(Self::Index),
+            "year" => Some(Self::Year),
+            _ => None
+        }
+    }
+
+    /// The season of a video, or `None` when the scheme is off or the video
+    /// lacks the `YYYYMMDD` upload date it needs.
+    pub fn season(self, upload_date: Option<&str>) -> Option<i64> {
+        match self {
+            Self::None => None,
+            Self::Index => Some(1),
+            Self::Year => upload_date?.get(..4)?.parse().ok()
+        }
+    }
+}
+
+/// Folders a download is placed in below the download path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirectoryLayout {
+    /// `{base}/{channel}/`
+    #[default]
+    ChannelOnly,
+    /// `{base}/{channel}/{year}/`, by upload year, for season-per-year libraries.
+    ChannelYear,
+    /// Everything directly in `{base}/`.
+    Flat
+}
+
+impl DirectoryLayout {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ChannelOnly => "channel",
+            Self::ChannelYear => "channel_year",
+            Self::Flat => "flat"
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "" | "channel" => Some(Self::ChannelOnly),
+            "channel_year" => Some(Self::ChannelYear),
+            "flat" => Some(Self::Flat),
+            _ => None
+        }
+    }
+
+    /// Unsanitized folder names below the download path. A video without a
+    /// `YYYYMMDD` upload date stays in the channel folder.
+    pub fn components<'a>(self, channel_name: &'a str, upload_date: Option<&'a str>) -> Vec<&'a str> {
+        match self {
+            Self::ChannelOnly => vec![channel_name],
+            Self::ChannelYear => {
+                let year = upload_date
+                    .and_then(|date| date.get(..4))
+                    .filter(|year| year.bytes().all(|b| b.is_ascii_digit()));
+                std::iter::once(channel_name).chain(year).collect()
+            }
+            Self::Flat => Vec::new()
+        }
+    }
+}
+
+/// Parses a `HH:MM` time as submitted by a time input.
+pub fn parse_clock_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// Splits a comma or whitespace separated language list such as `en, de.*`.
+pub fn parse_subtitle_langs(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|lang| !lang.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subtitle_langs() {
+        assert_eq!(parse_subtitle_langs("en, de.*  fr"), vec!["en", "de.*", "fr"]);
+        assert!(parse_subtitle_langs(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_rate_schedule_limit_at() {
+        let time = |value| parse_clock_time(value).unwrap();
+        let mut schedule = RateSchedule {
+            day: Some("2M".to_string()),
+            night: None,
+            window: Some((time("23:00"), time("07:00")))
+        };
+        assert_eq!(schedule.limit_at(time("12:00")), Some("2M"));
+        assert_eq!(schedule.limit_at(time("23:30")), None);
+        assert_eq!(schedule.limit_at(time("03:00")), None);
+        assert_eq!(schedule.limit_at(time("07:00")), Some("2M"));
+
+        schedule.night = Some("10M".to_string());
+        schedule.window = Some((time("01:00"), time("05:00")));
+        assert_eq!(schedule.limit_at(time("02:00")), Some("10M"));
+        assert_eq!(schedule.limit_at(time("23:00")), Some("2M"));
+
+        schedule.window = None;
+        assert_eq!(schedule.limit_at(time("02:00")), Some("2M"));
+        assert_eq!(parse_clock_time("25:00"), None);
+    }
+
+    #[test]
+    fn test_episode_scheme_season() {
+        assert_eq!(EpisodeScheme::None.season(Some("20240501")), None);
+        assert_eq!(EpisodeScheme::Index.season(None), Some(1));
+        assert_eq!(EpisodeScheme::Year.season(Some("20240501")), Some(2024));
+        assert_eq!(EpisodeScheme::Year.season(None), None);
+
+        assert_eq!(EpisodeScheme::parse(""), Some(EpisodeScheme::None));
+        assert_eq!(EpisodeScheme::parse(" year "), Some(EpisodeScheme::Year));
+        assert_eq!(EpisodeScheme::parse("season"), None);
+    }
+
+    #[test]
+    fn test_directory_layout_components() {
+        assert_eq!(DirectoryLayout::ChannelOnly.components("Chan", Some("20240501")), vec!["Chan"]);
+        assert_eq!(DirectoryLayout::ChannelYear.components("Chan", Some("20240501")), vec!["Chan", "2024"]);
+        assert_eq!(DirectoryLayout::ChannelYear.components("Chan", None), vec!["Chan"]);
+        assert_eq!(DirectoryLayout::ChannelYear.components("Chan", Some("NA")), vec!["Chan"]);
+        assert!(DirectoryLayout::Flat.components("Chan", Some("20240501")).is_empty());
+
+        assert_eq!(DirectoryLayout::parse(""), Some(DirectoryLayout::ChannelOnly));
+        assert_eq!(DirectoryLayout::parse(" channel_year "), Some(DirectoryLayout::ChannelYear));
+        assert_eq!(DirectoryLayout::parse("flat"), Some(DirectoryLayout::Flat));
+        assert_eq!(DirectoryLayout::parse("year"), None);
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, SqlitePool};
+use sqlx::{FromRow, Row, SqlitePool};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Video {
@@ -13,22 +13,38 @@ pub struct Video {
     pub upload_date: Option<String>,
     pub view_count: Option<i64>,
     pub webpage_url: String,
+    pub watched: bool,
+    pub live_status: Option<String>,
+    pub playlist_index: Option<i64>,
+    pub tags: Option<String>,
+    pub categories: Option<String>,
     pub created_at: String,
     pub updated_at: String
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoWithChannel {
+    pub video: Video,
+    pub channel_name: String
+}
+
 impl Video {
+    /// Lists a channel's videos, optionally keeping only watched or unwatched ones.
     pub async fn find_by_channel(
         pool: &SqlitePool,
-        channel_id: &str
+        channel_id: &str,
+        watched: Option<bool>
     ) -> Result<Vec<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, channel_id, youtube_id, title, description, thumbnail_url,
-                      duration_seconds, upload_date, view_count, webpage_url,
+                      duration_seconds, upload_date, view_count, webpage_url, watched, live_status, playlist_index,
+                      tags, categories,
                       created_at, updated_at
-               FROM videos WHERE channel_id = ? ORDER BY upload_date DESC"
+               FROM videos WHERE channel_id = ?1 AND (?2 IS NULL OR watched = ?2)
+               ORDER BY upload_date DESC"
         )
         .bind(channel_id)
+        .bind(watched)
         .fetch_all(pool)
         .await
     }
@@ -36,7 +52,8 @@ impl Video {
     pub async fn find_by_id(pool: &SqlitePool, id: &str) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, channel_id, youtube_id, title, description, thumbnail_url,
-                      duration_seconds, upload_date, view_count, webpage_url,
+                      duration_seconds, upload_date, view_count, webpage_url, watched, live_status, playlist_index,
+                      tags, categories,
                       created_at, updated_at
                FROM videos WHERE id = ?"
         )
@@ -52,7 +69,8 @@ impl Video {
     ) -> Result<Option<Self>, sqlx::Error> {
         sqlx::query_as::<_, Self>(
             r"SELECT id, channel_id, youtube_id, title, description, thumbnail_url,
-                      duration_seconds, upload_date, view_count, webpage_url,
+                      duration_seconds, upload_date, view_count, webpage_url, watched, live_status, playlist_index,
+                      tags, categories,
                       created_at, updated_at
                FROM videos WHERE youtube_id = ?"
         )
@@ -61,6 +79,47 @@ impl Video {
         .await
     }
 
+    /// Full-text search over titles and descriptions, best matches first.
+    /// Title hits weigh more than description hits; see [`fts_query`] for how
+    /// the input is interpreted.
+    pub async fn fts_search(
+        pool: &SqlitePool,
+        query: &str,
+        limit: i64,
+        offset: i64
+    ) -> Result<Vec<VideoWithChannel>, sqlx::Error> {
+        let Some(fts_query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let rows = sqlx::query(
+            r"SELECT v.id, v.channel_id, v.youtube_id, v.title, v.description, v.thumbnail_url,
+                      v.duration_seconds, v.upload_date, v.view_count, v.webpage_url, v.watched, v.live_status, v.playlist_index,
+                      v.tags, v.categories,
+                      v.created_at, v.updated_at,
+                      c.name as channel_name
+               FROM videos_fts
+               JOIN videos v ON v.id = videos_fts.video_id
+               JOIN channels c ON v.channel_id = c.id
+               WHERE videos_fts MATCH ?1
+               ORDER BY bm25(videos_fts, 0.0, 10.0, 1.0), v.upload_date DESC
+               LIMIT ?2 OFFSET ?3"
+        )
+        .bind(&fts_query)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|r| {
+                Ok(VideoWithChannel {
+                    video: Video::from_row(&r)?,
+                    channel_name: r.get("channel_name")
+                })
+            })
+            .collect()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn upsert(
         pool: &SqlitePool,
@@ -73,18 +132,29 @@ impl Video {
         duration_seconds: Option<i64>,
         upload_date: Option<&str>,
         view_count: Option<i64>,
-        webpage_url: &str
+        webpage_url: &str,
+        live_status: Option<&str>,
+        playlist_index: Option<i64>,
+        tags: &[String],
+        categories: &[String]
     ) -> Result<(), sqlx::Error> {
         sqlx::query(
             r"INSERT INTO videos (id, channel_id, youtube_id, title, description,
                                    thumbnail_url, duration_seconds, upload_date,
-                                   view_count, webpage_url)
-               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                                   view_count, webpage_url, live_status, playlist_index,
+                                   tags, categories)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                ON CONFLICT(youtube_id) DO UPDATE SET
                    title = excluded.title,
-                   description = excluded.description,
-                   thumbnail_url = excluded.thumbnail_url,
-                   view_count = excluded.view_count,
+                   description = COALESCE(excluded.description, videos.description),
+                   thumbnail_url = COALESCE(excluded.thumbnail_url, videos.thumbnail_url),
+                   duration_seconds = COALESCE(excluded.duration_seconds, videos.duration_seconds),
+                   upload_date = COALESCE(excluded.upload_date, videos.upload_date),
+                   view_count = COALESCE(excluded.view_count, videos.view_count),
+                   live_status = excluded.live_status,
+                   playlist_index = COALESCE(excluded.playlist_index, videos.playlist_index),
+                   tags = COALESCE(excluded.tags, videos.tags),
+                   categories = COALESCE(excluded.categories, videos.categories),
                    updated_at = datetime('now')"
         )
         .bind(id)
@@ -97,11 +167,56 @@ impl Video {
         .bind(upload_date)
         .bind(view_count)
         .bind(webpage_url)
+        .bind(live_status)
+        .bind(playlist_index)
+        .bind(encode_list(tags))
+        .bind(encode_list(categories))
         .execute(pool)
         .await?;
         Ok(())
     }
 
+    /// 1-based position of the video among its channel's dated videos,
+    /// oldest first with ties broken by row id, or among those uploaded in
+    /// the same year with `same_year`. `None` without an upload date.
+    pub async fn chronological_position(
+        pool: &SqlitePool,
+        id: &str,
+        same_year: bool
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let position: i64 = sqlx::query_scalar(
+            r"SELECT COUNT(*) FROM videos o
+               JOIN videos v ON v.id = ?
+               WHERE o.channel_id = v.channel_id
+                 AND o.upload_date IS NOT NULL AND v.upload_date IS NOT NULL
+                 AND (o.upload_date < v.upload_date OR (o.upload_date = v.upload_date AND o.id <= v.id))
+                 AND (? = 0 OR substr(o.upload_date, 1, 4) = substr(v.upload_date, 1, 4))"
+        )
+        .bind(id)
+        .bind(same_year)
+        .fetch_one(pool)
+        .await?;
+        Ok((position > 0).then_some(position))
+    }
+
+    /// Currently streaming.
+    pub fn is_live(&self) -> bool {
+        self.live_status.as_deref() == Some("is_live")
+    }
+
+    /// A scheduled stream or premiere that has not started yet.
+    pub fn is_upcoming(&self) -> bool {
+        self.live_status.as_deref() == Some("is_upcoming")
+    }
+
+    pub fn tag_list(&self) -> Vec<String> {
+        decode_list(self.tags.as_deref())
+    }
+
+    pub fn category_list(&self) -> Vec<String> {
+        decode_list(self.categories.as_deref())
+    }
+
     pub fn format_duration(&self) -> String {
         match self.duration_seconds {
             Some(secs) => {
@@ -118,6 +233,72 @@ impl Video {
         }
     }
 
+    /// Flips the watched flag, returning the new value or `None` if the video does not exist.
+    pub async fn toggle_watched(pool: &SqlitePool, id: &str) -> Result<Option<bool>, sqlx::Error> {
+        let row = sqlx::query(
+            r"UPDATE videos SET watched = NOT watched, updated_at = datetime('now')
+               WHERE id = ? RETURNING watched"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.map(|r| r.get("watched")))
+    }
+
+    /// Deletes the video and remembers its id so a later sync does not add it back.
+    pub async fn prune(pool: &SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r"INSERT OR IGNORE INTO pruned_videos (youtube_id, channel_id)
+               SELECT youtube_id, channel_id FROM videos WHERE id = ?"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM videos WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub async fn is_pruned(pool: &SqlitePool, youtube_id: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT 1 FROM pruned_videos WHERE youtube_id = ?")
+            .bind(youtube_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Returns `false` if no video has the given id.
+    pub async fn set_watched(pool: &SqlitePool, id: &str, watched: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r"UPDATE videos SET watched = ?, updated_at = datetime('now')
+               WHERE id = ?"
+        )
+        .bind(watched)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_thumbnail_by_youtube_id(
+        pool: &SqlitePool,
+        youtube_id: &str,
+        thumbnail_url: &str
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r"UPDATE videos SET thumbnail_url = ?, updated_at = datetime('now')
+               WHERE youtube_id = ?"
+        )
+        .bind(thumbnail_url)
+        .bind(youtube_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn update_thumbnail(
         pool: &SqlitePool,
@@ -135,3 +316,133 @@ impl Video {
         Ok(())
     }
 }
+
+/// Empty lists are stored as NULL so a flat sync does not erase values
+/// captured earlier.
+fn encode_list(values: &[String]) -> Option<String> {
+    if values.is_empty() {
+        None
+    } else {
+        serde_json::to_string(values).ok()
+    }
+}
+
+fn decode_list(value: Option<&str>) -> Vec<String> {
+    value
+        .and_then(|v| serde_json::from_str(v).ok())
+        .unwrap_or_default()
+}
+
+/// Turns free text into an FTS5 query. Every word has to match, as a prefix
+/// so `rust` finds `rustacean`, and `"quoted phrases"` match as written. FTS5
+/// operators and column filters are quoted rather than interpreted, so no
+/// input is a syntax error. `None` when nothing searchable is left.
+fn fts_query(input: &str) -> Option<String> {
+    let searchable = |term: &&str| term.chars().any(char::is_alphanumeric);
+    let mut terms = Vec::new();
+    for (index, part) in input.split('"').enumerate() {
+        if index % 2 == 1 {
+            let phrase = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            if searchable(&phrase.as_str()) {
+                terms.push(format!("\"{phrase}\""));
+            }
+        } else {
+            terms.extend(part.split_whitespace().filter(searchable).map(|word| format!("\"{word}\"*")));
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Channel;
+
+    async fn pool_with_channel() -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        Channel::insert(&pool, "c1", "UC1", "Channel", "https://example.com/c", None, None)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn insert(pool: &SqlitePool, youtube_id: &str, title: &str, description: Option<&str>) {
+        Video::upsert(
+            pool, &format!("id-{youtube_id}"), "c1", youtube_id, title, description, None, None,
+            None, None, "https://example.com/v", None, None, &[], &[]
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn search(pool: &SqlitePool, query: &str) -> Vec<String> {
+        Video::fts_search(pool, query, 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.video.youtube_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fts_search_ranks_title_matches_first() {
+        let pool = pool_with_channel().await;
+        insert(&pool, "a", "Cooking pasta", Some("We talk about sourdough bread today")).await;
+        insert(&pool, "b", "Sourdough bread from scratch", None).await;
+        insert(&pool, "c", "Unrelated", Some("Nothing to see")).await;
+
+        assert_eq!(search(&pool, "sourdough").await, vec!["b", "a"]);
+        assert_eq!(search(&pool, "sourd").await, vec!["b", "a"]);
+        assert_eq!(search(&pool, "\"about sourdough\"").await, vec!["a"]);
+        assert_eq!(search(&pool, "bread pasta").await, vec!["a"]);
+        assert!(search(&pool, "OR AND NOT (").await.is_empty());
+        assert!(search(&pool, "-- *").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fts_index_follows_updates_and_deletes() {
+        let pool = pool_with_channel().await;
+        insert(&pool, "a", "Old title", None).await;
+        assert_eq!(search(&pool, "old").await, vec!["a"]);
+
+        insert(&pool, "a", "New title", Some("Fresh description")).await;
+        assert!(search(&pool, "old").await.is_empty());
+        assert_eq!(search(&pool, "fresh").await, vec!["a"]);
+
+        Video::prune(&pool, "id-a").await.unwrap();
+        assert!(search(&pool, "fresh").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chronological_position() {
+        let pool = pool_with_channel().await;
+        for (id, date) in [("c", Some("20240301")), ("a", Some("20231231")), ("b", Some("20240301")), ("d", None)] {
+            Video::upsert(
+                &pool, id, "c1", id, "Video", None, None, None, date, None,
+                "https://example.com/v", None, None, &[], &[]
+            )
+            .await
+            .unwrap();
+        }
+        let position = |id: &'static str, same_year: bool| {
+            let pool = pool.clone();
+            async move { Video::chronological_position(&pool, id, same_year).await.unwrap() }
+        };
+
+        assert_eq!(position("a", false).await, Some(1));
+        assert_eq!(position("b", false).await, Some(2));
+        assert_eq!(position("c", false).await, Some(3));
+        assert_eq!(position("b", true).await, Some(1));
+        assert_eq!(position("c", true).await, Some(2));
+        assert_eq!(position("d", false).await, None);
+        assert_eq!(position("missing", false).await, None);
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("rust async").as_deref(), Some("\"rust\"* \"async\"*"));
+        assert_eq!(fts_query("say \"hello  world\" now").as_deref(), Some("\"say\"* \"hello world\" \"now\"*"));
+        assert_eq!(fts_query("title:foo NEAR(").as_deref(), Some("\"title:foo\"* \"NEAR(\"*"));
+        assert_eq!(fts_query("  - * \"\" "), None);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 use tokio::fs;
@@ -12,6 +12,8 @@ struct EpisodeDetails {
     dateadded: String,
     title: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    director: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     year: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     runtime: Option<i64>,
@@ -19,7 +21,15 @@ struct EpisodeDetails {
     art: Option<Art>,
     showtitle: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    season: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    episode: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     aired: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    genre: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tag: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     fileinfo: Option<FileInfo>,
     uniqueid: UniqueId,
@@ -84,14 +94,21 @@ pub struct VideoNfo {
     pub youtube_id: String,
     pub channel_name: String,
     pub upload_date: Option<String>,
+    pub upload_timestamp: Option<i64>,
+    pub director: Option<String>,
     pub duration_seconds: Option<i64>,
+    pub tags: Vec<String>,
+    pub categories: Vec<String>,
+    /// `(season, episode)` from the configured [`crate::models::EpisodeScheme`].
+    pub episode: Option<(i64, i64)>,
     pub thumb_filename: Option<String>,
     pub media_info: Option<MediaInfo>
 }
 
 pub struct MediaInfo {
     pub video: Option<VideoStream>,
-    pub audio: Option<AudioStream>
+    pub audio: Option<AudioStream>,
+    pub duration_seconds: Option<i64>
 }
 
 impl VideoNfo {
@@ -99,37 +116,49 @@ impl VideoNfo {
         let plot = self.description.as_deref().unwrap_or("").to_string();
         let dateadded = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-        let year = self
-            .upload_date
-            .as_deref()
-            .and_then(|d| d.get(..4))
-            .map(String::from);
+        let aired = self
+            .upload_timestamp
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .or_else(|| self.upload_date.as_deref().map(format_upload_date));
 
-        let runtime = self.duration_seconds.map(|d| (d + 59) / 60);
+        let year = aired.as_deref().and_then(|d| d.get(..4)).map(String::from);
+
+        let duration_seconds = self
+            .duration_seconds
+            .or_else(|| self.media_info.as_ref().and_then(|mi| mi.duration_seconds));
+        let runtime = duration_seconds.map(|d| (d + 59) / 60);
 
         let art = self.thumb_filename.as_ref().map(|t| Art {
             poster: t.clone()
         });
 
-        let aired = self.upload_date.as_deref().map(format_upload_date);
-
-        let fileinfo = self.media_info.as_ref().map(|mi| FileInfo {
-            streamdetails: StreamDetails {
-                video: mi.video.clone(),
-                audio: mi.audio.clone()
-            }
-        });
+        let fileinfo = self
+            .media_info
+            .as_ref()
+            .filter(|mi| mi.video.is_some() || mi.audio.is_some())
+            .map(|mi| FileInfo {
+                streamdetails: StreamDetails {
+                    video: mi.video.clone(),
+                    audio: mi.audio.clone()
+                }
+            });
 
         let details = EpisodeDetails {
             plot,
             lockdata: false,
             dateadded,
             title: self.title.clone(),
+            director: self.director.clone(),
             year,
             runtime,
             art,
             showtitle: self.channel_name.clone(),
+            season: self.episode.map(|(season, _)| season),
+            episode: self.episode.map(|(_, episode)| episode),
             aired,
+            genre: self.categories.clone(),
+            tag: self.tags.clone(),
             fileinfo,
             uniqueid: UniqueId {
                 id_type: "youtube".to_string(),
@@ -151,6 +180,72 @@ impl VideoNfo {
     }
 }
 
+impl VideoNfo {
+    /// Prefers metadata from yt-dlp's `.info.json` over the database row,
+    /// which may be stale or come from a flat playlist listing.
+    pub fn apply_info_json(&mut self, info: &yt_dlp::VideoInfo) {
+        if let Some(description) = info.description.as_ref().filter(|d| !d.is_empty()) {
+            self.description = Some(description.clone());
+        }
+        if info.upload_date.is_some() {
+            self.upload_date.clone_from(&info.upload_date);
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        if let Some(timestamp) = info.timestamp {
+            self.upload_timestamp = Some(timestamp.floor() as i64);
+        }
+        if let Some(uploader) = info.uploader.as_ref().or(info.channel.as_ref()) {
+            self.director = Some(uploader.clone());
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        if let Some(duration) = info.duration {
+            self.duration_seconds = Some(duration.round() as i64);
+        }
+        if !info.tags.is_empty() {
+            self.tags.clone_from(&info.tags);
+        }
+        if !info.categories.is_empty() {
+            self.categories.clone_from(&info.categories);
+        }
+    }
+}
+
+/// Locates the `.info.json` written for a video. yt-dlp names it after the
+/// pre-merge output, so when `<video>.info.json` is missing the directory is
+/// scanned for a sidecar whose `id` matches.
+pub async fn find_info_json(video_file_path: &str, youtube_id: &str) -> Option<PathBuf> {
+    let video_path = Path::new(video_file_path);
+    let expected = video_path.with_extension("info.json");
+    if fs::try_exists(&expected).await.unwrap_or(false) {
+        return Some(expected);
+    }
+
+    let mut entries = fs::read_dir(video_path.parent()?).await.ok()?;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if !path.to_string_lossy().ends_with(".info.json") {
+            continue;
+        }
+        if let Some(info) = read_info_json(&path).await {
+            if info.id == youtube_id {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+pub async fn read_info_json(path: &Path) -> Option<yt_dlp::VideoInfo> {
+    let contents = fs::read(path).await.ok()?;
+    match serde_json::from_slice(&contents) {
+        Ok(info) => Some(info),
+        Err(e) => {
+            tracing::warn!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
 pub async fn write_nfo(
     video_file_path: &str,
     nfo: &VideoNfo
@@ -199,10 +294,30 @@ struct FfprobeStream {
     bit_rate: Option<String>,
     duration: Option<String>,
     channels: Option<i64>,
-    sample_rate: Option<String>
+    sample_rate: Option<String>,
+    #[serde(default)]
+    disposition: FfprobeDisposition
+}
+
+#[derive(Default, serde::Deserialize)]
+struct FfprobeDisposition {
+    #[serde(default)]
+    attached_pic: i64
 }
 
 pub async fn probe_media(path: &str, ffprobe_bin: &str) -> Option<MediaInfo> {
+    match try_probe_media(path, ffprobe_bin).await {
+        Ok(info) => info,
+        Err(e) => {
+            tracing::warn!("ffprobe ({}) could not run for {}: {}", ffprobe_bin, path, e);
+            None
+        }
+    }
+}
+
+/// Like [`probe_media`], but tells a missing or unrunnable ffprobe (`Err`)
+/// apart from a file ffprobe could not read (`Ok(None)`).
+pub async fn try_probe_media(path: &str, ffprobe_bin: &str) -> std::io::Result<Option<MediaInfo>> {
     let output = tokio::process::Command::new(ffprobe_bin)
         .args([
             "-v",
@@ -214,15 +329,21 @@ pub async fn probe_media(path: &str, ffprobe_bin: &str) -> Option<MediaInfo> {
         ])
         .arg(path)
         .output()
-        .await
-        .ok()?;
+        .await?;
 
     if !output.status.success() {
         tracing::warn!("ffprobe ({}) failed for {}", ffprobe_bin, path);
-        return None;
+        return Ok(None);
     }
 
-    let parsed: FfprobeOutput = serde_json::from_slice(&output.stdout).ok()?;
+    Ok(parse_probe_output(&output.stdout))
+}
+
+/// Audio-only files have no video stream; cover art embedded in them shows up
+/// as an attached picture and is not treated as one. Their duration comes from
+/// the audio stream or, like video, falls back to the format-level duration.
+fn parse_probe_output(stdout: &[u8]) -> Option<MediaInfo> {
+    let parsed: FfprobeOutput = serde_json::from_slice(stdout).ok()?;
     let format_duration = parsed
         .format
         .as_ref()
@@ -231,7 +352,20 @@ pub async fn probe_media(path: &str, ffprobe_bin: &str) -> Option<MediaInfo> {
     let video = parse_video_stream(&parsed.streams, format_duration);
     let audio = parse_audio_stream(&parsed.streams);
 
-    Some(MediaInfo { video, audio })
+    #[allow(clippy::cast_possible_truncation)]
+    let duration_seconds = match &video {
+        Some(video) => Some(video.durationinseconds),
+        None => parsed
+            .streams
+            .iter()
+            .find(|s| s.codec_type.as_deref() == Some("audio"))
+            .and_then(|s| s.duration.as_deref())
+            .and_then(|d| d.parse::<f64>().ok())
+            .or(format_duration)
+            .map(|d| d.round() as i64)
+    };
+
+    Some(MediaInfo { video, audio, duration_seconds })
 }
 
 fn parse_video_stream(
@@ -240,7 +374,7 @@ fn parse_video_stream(
 ) -> Option<VideoStream> {
     let s = streams
         .iter()
-        .find(|s| s.codec_type.as_deref() == Some("video"))?;
+        .find(|s| s.codec_type.as_deref() == Some("video") && s.disposition.attached_pic == 0)?;
 
     let width = s.width?;
     let height = s.height?;
@@ -321,7 +455,12 @@ mod tests {
             youtube_id: "abc123".to_string(),
             channel_name: "Test Channel".to_string(),
             upload_date: Some("20230415".to_string()),
+            upload_timestamp: None,
+            director: Some("Test Uploader".to_string()),
             duration_seconds: Some(300),
+            tags: vec!["testing".to_string()],
+            categories: vec!["Education".to_string()],
+            episode: None,
             thumb_filename: Some("thumb.jpg".to_string()),
             media_info: Some(MediaInfo {
                 video: Some(VideoStream {
@@ -338,7 +477,8 @@ mod tests {
                     codec: "aac".to_string(),
                     channels: Some(2),
                     samplingrate: Some(48000)
-                })
+                }),
+                duration_seconds: Some(300)
             })
         };
 
@@ -349,11 +489,14 @@ mod tests {
         assert!(xml.contains("<lockdata>false</lockdata>"));
         assert!(xml.contains("<dateadded>"));
         assert!(xml.contains("<title>Test Video</title>"));
+        assert!(xml.contains("<director>Test Uploader</director>"));
         assert!(xml.contains("<year>2023</year>"));
         assert!(xml.contains("<runtime>5</runtime>"));
         assert!(xml.contains("<poster>thumb.jpg</poster>"));
         assert!(xml.contains("<showtitle>Test Channel</showtitle>"));
         assert!(xml.contains("<aired>2023-04-15</aired>"));
+        assert!(xml.contains("<genre>Education</genre>"));
+        assert!(xml.contains("<tag>testing</tag>"));
         assert!(xml.contains("<codec>h264</codec>"));
         assert!(xml.contains("<width>1920</width>"));
         assert!(xml.contains("<height>1080</height>"));
@@ -368,6 +511,28 @@ mod tests {
         assert!(xml.contains("</episodedetails>"));
     }
 
+    #[test]
+    fn test_to_xml_season_and_episode() {
+        let nfo = VideoNfo {
+            title: "Episode".to_string(),
+            description: None,
+            youtube_id: "ep1".to_string(),
+            channel_name: "Chan".to_string(),
+            upload_date: Some("20240501".to_string()),
+            upload_timestamp: None,
+            director: None,
+            duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: Some((2024, 12)),
+            thumb_filename: None,
+            media_info: None
+        };
+
+        let xml = nfo.to_xml();
+        assert!(xml.contains("<showtitle>Chan</showtitle><season>2024</season><episode>12</episode>"));
+    }
+
     #[test]
     fn test_to_xml_minimal() {
         let nfo = VideoNfo {
@@ -376,7 +541,12 @@ mod tests {
             youtube_id: "xyz789".to_string(),
             channel_name: "Chan".to_string(),
             upload_date: None,
+            upload_timestamp: None,
+            director: None,
             duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: None,
             thumb_filename: None,
             media_info: None
         };
@@ -391,6 +561,32 @@ mod tests {
         assert!(!xml.contains("<aired>"));
         assert!(!xml.contains("<fileinfo>"));
         assert!(!xml.contains("<thumb"));
+        assert!(!xml.contains("<genre>"));
+        assert!(!xml.contains("<director>"));
+        assert!(!xml.contains("<tag>"));
+    }
+
+    #[test]
+    fn test_to_xml_genres_and_tags() {
+        let nfo = VideoNfo {
+            title: "Tagged".to_string(),
+            description: None,
+            youtube_id: "tag1".to_string(),
+            channel_name: "Chan".to_string(),
+            upload_date: None,
+            upload_timestamp: None,
+            director: None,
+            duration_seconds: None,
+            tags: vec!["rust".to_string(), "async & await".to_string()],
+            categories: vec!["Science & Technology".to_string()],
+            episode: None,
+            thumb_filename: None,
+            media_info: None
+        };
+
+        let xml = nfo.to_xml();
+        assert!(xml.contains("<genre>Science &amp; Technology</genre>"));
+        assert!(xml.contains("<tag>rust</tag><tag>async &amp; await</tag>"));
     }
 
     #[test]
@@ -401,7 +597,12 @@ mod tests {
             youtube_id: "id&1".to_string(),
             channel_name: "Chan <&>".to_string(),
             upload_date: None,
+            upload_timestamp: None,
+            director: None,
             duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: None,
             thumb_filename: None,
             media_info: None
         };
@@ -421,7 +622,12 @@ mod tests {
             youtube_id: "dt1".to_string(),
             channel_name: "Chan".to_string(),
             upload_date: Some("20180102".to_string()),
+            upload_timestamp: None,
+            director: None,
             duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: None,
             thumb_filename: None,
             media_info: None
         };
@@ -431,6 +637,66 @@ mod tests {
         assert!(xml.contains("<year>2018</year>"));
     }
 
+    #[test]
+    fn test_apply_info_json() {
+        let mut nfo = VideoNfo {
+            title: "Info".to_string(),
+            description: Some("Stale".to_string()),
+            youtube_id: "info1".to_string(),
+            channel_name: "Chan".to_string(),
+            upload_date: Some("20200101".to_string()),
+            upload_timestamp: None,
+            director: None,
+            duration_seconds: Some(60),
+            tags: vec!["old".to_string()],
+            categories: Vec::new(),
+            episode: None,
+            thumb_filename: None,
+            media_info: None
+        };
+        let info: yt_dlp::VideoInfo = serde_json::from_str(
+            r#"{
+                "id": "info1",
+                "title": "Info",
+                "description": "Fresh",
+                "uploader": "Uploader Name",
+                "duration": 61.6,
+                "upload_date": "20200102",
+                "timestamp": 1577923200.5,
+                "tags": ["new"],
+                "categories": ["Music"]
+            }"#
+        )
+        .unwrap();
+
+        nfo.apply_info_json(&info);
+        assert_eq!(nfo.description.as_deref(), Some("Fresh"));
+        assert_eq!(nfo.director.as_deref(), Some("Uploader Name"));
+        assert_eq!(nfo.duration_seconds, Some(62));
+        assert_eq!(nfo.upload_timestamp, Some(1_577_923_200));
+        assert_eq!(nfo.tags, vec!["new"]);
+        assert_eq!(nfo.categories, vec!["Music"]);
+
+        let xml = nfo.to_xml();
+        assert!(xml.contains("<aired>2020-01-02</aired>"));
+        assert!(xml.contains("<director>Uploader Name</director>"));
+    }
+
+    #[tokio::test]
+    async fn test_find_info_json_with_different_name() {
+        let dir = std::env::temp_dir().join(format!("toobarr-nfo-test-{}", uuid7::uuid7()));
+        fs::create_dir_all(&dir).await.unwrap();
+        fs::write(dir.join("other.info.json"), r#"{"id": "zzz", "title": "Other"}"#).await.unwrap();
+        fs::write(dir.join("Video.f137.info.json"), r#"{"id": "abc", "title": "Video"}"#).await.unwrap();
+        let video = dir.join("Video.mkv").to_string_lossy().to_string();
+
+        let found = find_info_json(&video, "abc").await;
+        assert_eq!(found, Some(dir.join("Video.f137.info.json")));
+        assert_eq!(find_info_json(&video, "missing").await, None);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
     #[test]
     fn test_parse_ffprobe_output() {
         let json = r#"{
@@ -505,4 +771,94 @@ mod tests {
         assert_eq!(video.durationinseconds, 1320);
         assert_eq!(video.duration, "22:00");
     }
+
+    #[test]
+    fn test_parse_probe_output_audio_only() {
+        let json = br#"{
+            "streams": [
+                {
+                    "codec_type": "audio",
+                    "codec_name": "mp3",
+                    "channels": 2,
+                    "sample_rate": "44100"
+                },
+                {
+                    "codec_type": "video",
+                    "codec_name": "mjpeg",
+                    "width": 1280,
+                    "height": 720,
+                    "disposition": { "default": 0, "attached_pic": 1 }
+                }
+            ],
+            "format": { "duration": "184.2" }
+        }"#;
+
+        let info = parse_probe_output(json).unwrap();
+        assert!(info.video.is_none());
+        assert_eq!(info.duration_seconds, Some(184));
+        let audio = info.audio.as_ref().unwrap();
+        assert_eq!(audio.codec, "mp3");
+        assert_eq!(audio.samplingrate, Some(44_100));
+    }
+
+    #[test]
+    fn test_to_xml_audio_only() {
+        let json = br#"{
+            "streams": [
+                {
+                    "codec_type": "audio",
+                    "codec_name": "opus",
+                    "channels": 2,
+                    "sample_rate": "48000"
+                }
+            ],
+            "format": { "duration": "3599.6" }
+        }"#;
+        let nfo = VideoNfo {
+            title: "Podcast".to_string(),
+            description: None,
+            youtube_id: "pod1".to_string(),
+            channel_name: "Chan".to_string(),
+            upload_date: None,
+            upload_timestamp: None,
+            director: None,
+            duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: None,
+            thumb_filename: None,
+            media_info: parse_probe_output(json)
+        };
+
+        let xml = nfo.to_xml();
+        assert!(xml.contains(
+            "<fileinfo><streamdetails><audio><codec>opus</codec><channels>2</channels>\
+             <samplingrate>48000</samplingrate></audio></streamdetails></fileinfo>"
+        ));
+        assert!(!xml.contains("<video"));
+        assert!(xml.contains("<runtime>60</runtime>"));
+    }
+
+    #[test]
+    fn test_to_xml_omits_empty_fileinfo() {
+        let nfo = VideoNfo {
+            title: "Empty".to_string(),
+            description: None,
+            youtube_id: "empty1".to_string(),
+            channel_name: "Chan".to_string(),
+            upload_date: None,
+            upload_timestamp: None,
+            director: None,
+            duration_seconds: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            episode: None,
+            thumb_filename: None,
+            media_info: parse_probe_output(br#"{"streams": []}"#)
+        };
+
+        let xml = nfo.to_xml();
+        assert!(!xml.contains("<fileinfo"));
+        assert!(!xml.contains("<streamdetails"));
+    }
 }
@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Channel, ChannelSettings};
+
+const YOUTUBE_FEED_PREFIX: &str = "https://www.youtube.com/feeds/videos.xml?channel_id=";
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "opml")]
+struct Opml {
+    #[serde(rename = "@version")]
+    version: &'static str,
+    head: Head,
+    body: Body
+}
+
+#[derive(Debug, Serialize)]
+struct Head {
+    title: &'static str
+}
+
+#[derive(Debug, Serialize)]
+struct Body {
+    #[serde(rename = "outline")]
+    outlines: Vec<ExportOutline>
+}
+
+#[derive(Debug, Serialize)]
+struct ExportOutline {
+    #[serde(rename = "@text")]
+    text: String,
+    #[serde(rename = "@title")]
+    title: String,
+    #[serde(rename = "@type")]
+    outline_type: &'static str,
+    #[serde(rename = "@xmlUrl", skip_serializing_if = "Option::is_none")]
+    xml_url: Option<String>,
+    #[serde(rename = "@htmlUrl")]
+    html_url: String
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportDocument {
+    body: ImportBody
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportBody {
+    #[serde(default, rename = "outline")]
+    outlines: Vec<ImportOutline>
+}
+
+/// The JSON channel export: every channel with its URL and settings.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonExport {
+    version: u32,
+    channels: Vec<JsonChannel>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonChannel {
+    #[serde(default)]
+    youtube_id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    url: String,
+    #[serde(flatten)]
+    settings: ChannelSettings
+}
+
+/// A channel to import. Only JSON exports carry an id and settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEntry {
+    pub url: String,
+    pub youtube_id: Option<String>,
+    pub settings: Option<ChannelSettings>
+}
+
+impl ImportEntry {
+    fn from_url(url: String) -> Self {
+        Self { url, youtube_id: None, settings: None }
+    }
+}
+
+/// Outlines may nest, e.g. when a feed reader groups subscriptions into folders.
+#[derive(Debug, Deserialize)]
+struct ImportOutline {
+    #[serde(rename = "@xmlUrl")]
+    xml_url: Option<String>,
+    #[serde(rename = "@htmlUrl")]
+    html_url: Option<String>,
+    #[serde(default, rename = "outline")]
+    children: Vec<ImportOutline>
+}
+
+/// Every channel as an OPML 2.0 outline. `YouTube` channels also get their
+/// uploads feed as `xmlUrl` so feed readers can subscribe to the export.
+pub fn export_channels(channels: &[Channel]) -> String {
+    let outlines = channels
+        .iter()
+        .map(|channel| ExportOutline {
+            text: channel.name.clone(),
+            title: channel.name.clone(),
+            outline_type: "rss",
+            xml_url: is_youtube_channel_id(&channel.youtube_id)
+                .then(|| format!("{YOUTUBE_FEED_PREFIX}{}", channel.youtube_id)),
+            html_url: channel.url.clone()
+        })
+        .collect();
+
+    let opml = Opml {
+        version: "2.0",
+        head: Head { title: "toobarr subscriptions" },
+        body: Body { outlines }
+    };
+
+    let body = quick_xml::se::to_string(&opml).unwrap_or_else(|e| {
+        tracing::error!("Failed to serialize OPML: {}", e);
+        String::new()
+    });
+
+    format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n{body}\n")
+}
+
+/// Every channel with its URL and settings as a JSON document that
+/// [`parse_import`] reads back, for moving a channel list between servers.
+pub fn export_channels_json(channels: &[Channel]) -> String {
+    let export = JsonExport {
+        version: 1,
+        channels: channels
+            .iter()
+            .map(|channel| JsonChannel {
+                youtube_id: Some(channel.youtube_id.clone()),
+                name: Some(channel.name.clone()),
+                url: channel.url.clone(),
+                settings: channel.settings()
+            })
+            .collect()
+    };
+    serde_json::to_string_pretty(&export).unwrap_or_else(|e| {
+        tracing::error!("Failed to serialize channel export: {}", e);
+        String::new()
+    })
+}
+
+/// Channels from a JSON export, an OPML document, or a plain list with one
+/// URL per line where blank lines and `#` comments are ignored. Entries
+/// repeating an earlier URL or channel id are dropped while keeping the
+/// original order.
+pub fn parse_import(input: &str) -> Result<Vec<ImportEntry>, String> {
+    let input = input.trim_start_matches('\u{feff}').trim();
+    let entries: Vec<ImportEntry> = if input.starts_with('{') {
+        let export: JsonExport =
+            serde_json::from_str(input).map_err(|e| format!("Invalid channel export: {e}"))?;
+        export
+            .channels
+            .into_iter()
+            .map(|channel| ImportEntry {
+                url: channel.url.trim().to_string(),
+                youtube_id: channel.youtube_id.filter(|id| !id.trim().is_empty()),
+                settings: Some(channel.settings)
+            })
+            .filter(|entry| !entry.url.is_empty())
+            .collect()
+    } else if input.starts_with('<') {
+        let document: ImportDocument =
+            quick_xml::de::from_str(input).map_err(|e| format!("Invalid OPML: {e}"))?;
+        let mut urls = Vec::new();
+        collect_urls(&document.body.outlines, &mut urls);
+        urls.into_iter().map(ImportEntry::from_url).collect()
+    } else {
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| ImportEntry::from_url(line.to_string()))
+            .collect()
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    Ok(entries
+        .into_iter()
+        .filter(|entry| {
+            let new_id = entry.youtube_id.as_ref().is_none_or(|id| seen.insert(format!("id:{id}")));
+            seen.insert(format!("url:{}", entry.url)) && new_id
+        })
+        .collect())
+}
+
+fn collect_urls(outlines: &[ImportOutline], urls: &mut Vec<String>) {
+    for outline in outlines {
+        if let Some(url) = outline_url(outline) {
+            urls.push(url);
+        }
+        collect_urls(&outline.children, urls);
+    }
+}
+
+/// Prefers the page URL; a `YouTube` uploads feed is turned back into its
+/// channel page since yt-dlp cannot list a feed.
+fn outline_url(outline: &ImportOutline) -> Option<String> {
+    if let Some(html_url) = outline.html_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        return Some(html_url.trim().to_string());
+    }
+    let xml_url = outline.xml_url.as_deref()?.trim();
+    match xml_url.strip_prefix(YOUTUBE_FEED_PREFIX) {
+        Some(channel_id) => Some(format!("https://www.youtube.com/channel/{channel_id}")),
+        None if !xml_url.is_empty() => Some(xml_url.to_string()),
+        None => None
+    }
+}
+
+fn is_youtube_channel_id(id: &str) -> bool {
+    id.len() == 24 && id.starts_with("UC")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(youtube_id: &str, name: &str, url: &str) -> Channel {
+        Channel {
+            id: format!("id-{youtube_id}"),
+            youtube_id: youtube_id.to_string(),
+            name: name.to_string(),
+            url: url.to_string(),
+            thumbnail_url: None,
+            description: None,
+            video_count: None,
+            last_synced_at: None,
+            retention_keep_latest: None,
+            retention_max_age_days: None,
+            retention_delete_videos: false,
+            date_after: None,
+            date_before: None,
+            match_filter: None,
+            no_shorts: false,
+            audio_only: false,
+            created_at: String::new(),
+            updated_at: String::new()
+        }
+    }
+
+    fn urls(entries: Vec<ImportEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.url).collect()
+    }
+
+    #[test]
+    fn test_export_channels() {
+        let channels = vec![
+            channel("UCuAXFkgsw1L7xaCfnd5JJOw", "Rick & Co", "https://www.youtube.com/@rick"),
+            channel("vimeo:12345", "Vimeo", "https://vimeo.com/user12345")
+        ];
+        let xml = export_channels(&channels);
+
+        assert!(xml.contains("<opml version=\"2.0\"><head><title>toobarr subscriptions</title></head>"));
+        assert!(xml.contains(
+            "<outline text=\"Rick &amp; Co\" title=\"Rick &amp; Co\" type=\"rss\" \
+             xmlUrl=\"https://www.youtube.com/feeds/videos.xml?channel_id=UCuAXFkgsw1L7xaCfnd5JJOw\" \
+             htmlUrl=\"https://www.youtube.com/@rick\"/>"
+        ));
+        assert!(xml.contains(
+            "<outline text=\"Vimeo\" title=\"Vimeo\" type=\"rss\" htmlUrl=\"https://vimeo.com/user12345\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_export_round_trips() {
+        let channels = vec![channel("UCuAXFkgsw1L7xaCfnd5JJOw", "Rick", "https://www.youtube.com/@rick")];
+        assert_eq!(
+            urls(parse_import(&export_channels(&channels)).unwrap()),
+            vec!["https://www.youtube.com/@rick"]
+        );
+    }
+
+    #[test]
+    fn test_parse_import_opml() {
+        let opml = r#"<?xml version="1.0"?>
+            <opml version="1.1">
+              <head><title>Subscriptions</title></head>
+              <body>
+                <outline text="YouTube Subscriptions" title="YouTube Subscriptions">
+                  <outline text="A" type="rss" xmlUrl="https://www.youtube.com/feeds/videos.xml?channel_id=UC111"/>
+                  <outline text="B" type="rss" xmlUrl="https://example.com/feed" htmlUrl="https://www.youtube.com/@b"/>
+                </outline>
+                <outline text="A again" xmlUrl="https://www.youtube.com/feeds/videos.xml?channel_id=UC111"/>
+              </body>
+            </opml>"#;
+        assert_eq!(
+            urls(parse_import(opml).unwrap()),
+            vec!["https://www.youtube.com/channel/UC111", "https://www.youtube.com/@b"]
+        );
+    }
+
+    #[test]
+    fn test_parse_import_url_list() {
+        let list = "\u{feff}# my channels\nhttps://www.youtube.com/@a\n\n  https://www.youtube.com/@b  \nhttps://www.youtube.com/@a\n";
+        assert_eq!(
+            urls(parse_import(list).unwrap()),
+            vec!["https://www.youtube.com/@a", "https://www.youtube.com/@b"]
+        );
+    }
+
+    #[test]
+    fn test_json_export_round_trips_settings() {
+        let mut filtered = channel("UCuAXFkgsw1L7xaCfnd5JJOw", "Rick", "https://www.youtube.com/@rick");
+        filtered.retention_keep_latest = Some(10);
+        filtered.date_after = Some("now-1year".to_string());
+        filtered.match_filter = Some("duration > 60".to_string());
+        filtered.no_shorts = true;
+        let plain = channel("vimeo:12345", "Vimeo", "https://vimeo.com/user12345");
+
+        let entries = parse_import(&export_channels_json(&[filtered.clone(), plain])).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "https://www.youtube.com/@rick");
+        assert_eq!(entries[0].youtube_id.as_deref(), Some("UCuAXFkgsw1L7xaCfnd5JJOw"));
+        assert_eq!(entries[0].settings, Some(filtered.settings()));
+        assert_eq!(entries[1].settings, Some(ChannelSettings::default()));
+    }
+
+    #[test]
+    fn test_parse_import_json_dedups_on_id_and_url() {
+        let json = r#"{"version": 1, "channels": [
+            {"youtube_id": "UC1", "url": "https://www.youtube.com/@a"},
+            {"youtube_id": "UC1", "url": "https://www.youtube.com/channel/UC1"},
+            {"url": "https://www.youtube.com/@a", "no_shorts": true},
+            {"url": "https://www.youtube.com/@b", "no_shorts": true}
+        ]}"#;
+        let entries = parse_import(json).unwrap();
+        assert_eq!(urls(entries.clone()), vec!["https://www.youtube.com/@a", "https://www.youtube.com/@b"]);
+        assert!(entries[1].settings.as_ref().unwrap().no_shorts);
+        assert!(parse_import("{\"channels\": 3}").is_err());
+    }
+
+    #[test]
+    fn test_parse_import_rejects_malformed_opml() {
+        assert!(parse_import("<opml><body>").is_err());
+    }
+}
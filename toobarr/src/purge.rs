@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use crate::db::DbPool;
+use crate::models::{Channel, Download, Settings};
+use crate::workers::download::channel_download_dir;
+
+/// Removes everything toobarr wrote to disk for a channel: downloaded media
+/// with their NFO, thumbnail, subtitle and info.json sidecars, the channel
+/// folder and the cached channel thumbnail. Paths outside the download root
+/// are never touched. Returns the number of entries removed.
+pub async fn purge_channel_files(pool: &DbPool, channel: &Channel) -> Result<usize, sqlx::Error> {
+    let Some(download_root) = download_root(pool).await? else {
+        return Ok(0);
+    };
+
+    let mut removed = 0;
+
+    for file_path in Download::file_paths_for_channel(pool, &channel.id).await? {
+        removed += remove_media(&download_root, &file_path).await;
+    }
+
+    let channel_dir = channel_download_dir(pool, &channel.name).await?;
+    let channel_dir = match channel_dir {
+        Some(dir) => resolve_within(&download_root, Path::new(&dir)).await,
+        None => None
+    };
+    if let Some(dir) = channel_dir {
+        if dir == download_root {
+            tracing::warn!("Refusing to remove the download root {}", dir.display());
+        } else {
+            match tokio::fs::remove_dir_all(&dir).await {
+                Ok(()) => {
+                    tracing::info!("Removed channel folder {}", dir.display());
+                    removed += 1;
+                }
+                Err(e) => tracing::warn!("Failed to remove channel folder {}: {}", dir.display(), e)
+            }
+        }
+    }
+
+    if let Some(ref web_path) = channel.thumbnail_url {
+        let thumbnails = Path::new("static/thumbnails/channels");
+        if let Ok(thumbnails) = tokio::fs::canonicalize(thumbnails).await {
+            let local = Path::new(web_path.strip_prefix('/').unwrap_or(web_path));
+            if let Some(thumb) = resolve_within(&thumbnails, local).await {
+                if remove_file(&thumb).await {
+                    removed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+/// The canonical download path, or `None` if it does not exist yet.
+pub async fn download_root(pool: &DbPool) -> Result<Option<PathBuf>, sqlx::Error> {
+    let download_path = Settings::get_download_path(pool).await?;
+    let root = tokio::fs::canonicalize(&download_path).await.ok();
+    if root.is_none() {
+        tracing::info!("Download path {} does not exist, nothing to remove", download_path);
+    }
+    Ok(root)
+}
+
+/// Removes a downloaded media file and its sidecars if it lies inside
+/// `download_root`. Returns the number of files removed.
+pub async fn remove_media(download_root: &Path, file_path: &str) -> usize {
+    let Some(media) = resolve_within(download_root, Path::new(file_path)).await else {
+        return 0;
+    };
+    let mut removed = 0;
+    for path in with_sidecars(&media).await {
+        if remove_file(&path).await {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Canonicalizes `path` and returns it only if it exists inside `root`.
+pub async fn resolve_within(root: &Path, path: &Path) -> Option<PathBuf> {
+    let resolved = tokio::fs::canonicalize(path).await.ok()?;
+    if is_within(root, &resolved) {
+        Some(resolved)
+    } else {
+        tracing::warn!("Skipping {} outside of {}", resolved.display(), root.display());
+        None
+    }
+}
+
+fn is_within(root: &Path, path: &Path) -> bool {
+    path.starts_with(root)
+}
+
+/// The media file plus siblings sharing its stem, e.g. `name.nfo`,
+/// `name-thumb.jpg` and `name.en.srt`.
+async fn with_sidecars(media: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![media.to_path_buf()];
+    let (Some(dir), Some(stem)) = (media.parent(), media.file_stem()) else {
+        return paths;
+    };
+    let stem = stem.to_string_lossy();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return paths;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path == media {
+            continue;
+        }
+        let name = entry.file_name();
+        if is_sidecar(&stem, &name.to_string_lossy()) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn is_sidecar(stem: &str, name: &str) -> bool {
+    // Hidden `.<name>.http.json` files hold the thumbnail's cache validators
+    let name = name.strip_prefix('.').unwrap_or(name);
+    name.strip_prefix(stem)
+        .is_some_and(|rest| rest.starts_with('.') || rest.starts_with("-thumb."))
+}
+
+async fn remove_file(path: &Path) -> bool {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {
+            tracing::info!("Removed {}", path.display());
+            true
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => {
+            tracing::warn!("Failed to remove {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within() {
+        let root = Path::new("/data/downloads");
+        assert!(is_within(root, Path::new("/data/downloads/Channel/video.mkv")));
+        assert!(!is_within(root, Path::new("/data/downloads-old/video.mkv")));
+        assert!(!is_within(root, Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn test_is_sidecar() {
+        let stem = "Video [abc123]";
+        assert!(is_sidecar(stem, "Video [abc123].nfo"));
+        assert!(is_sidecar(stem, "Video [abc123].en.srt"));
+        assert!(is_sidecar(stem, "Video [abc123].info.json"));
+        assert!(is_sidecar(stem, "Video [abc123]-thumb.jpg"));
+        assert!(is_sidecar(stem, ".Video [abc123]-thumb.jpg.http.json"));
+        assert!(!is_sidecar(stem, "Video [abc123] part 2.mkv"));
+        assert!(!is_sidecar(stem, "Other.nfo"));
+    }
+}
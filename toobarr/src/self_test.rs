@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use serde::Serialize;
+use yt_dlp::{Container, DownloadOptions, OutputFormat, YtDlp};
+
+use crate::nfo::{self, VideoNfo};
+
+/// The Blender Foundation's Big Buck Bunny, published on `YouTube` under
+/// CC BY 3.0; at the worst formats it is a few megabytes. Override with
+/// `SELF_TEST_URL`.
+const DEFAULT_SELF_TEST_URL: &str = "https://www.youtube.com/watch?v=aqz-KE-bpKQ";
+
+/// Lowest quality video and audio so the merge step still runs.
+const SELF_TEST_FORMAT: &str = "worstvideo+worstaudio/worst";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StageStatus {
+    Pass,
+    Fail,
+    Skip
+}
+
+#[derive(Debug, Serialize)]
+pub struct StageResult {
+    pub stage: &'static str,
+    pub status: StageStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub url: String,
+    pub stages: Vec<StageResult>
+}
+
+struct Stages(Vec<StageResult>);
+
+impl Stages {
+    fn pass(&mut self, stage: &'static str, detail: impl Into<String>) {
+        self.0.push(StageResult { stage, status: StageStatus::Pass, detail: Some(detail.into()) });
+    }
+
+    fn fail(&mut self, stage: &'static str, detail: impl Into<String>) {
+        self.0.push(StageResult { stage, status: StageStatus::Fail, detail: Some(detail.into()) });
+    }
+
+    fn skip(&mut self, stages: &[&'static str], reason: &str) {
+        for stage in stages {
+            self.0.push(StageResult {
+                stage,
+                status: StageStatus::Skip,
+                detail: Some(reason.to_string())
+            });
+        }
+    }
+}
+
+/// Downloads a short video into a scratch directory and checks each step of
+/// the download pipeline: yt-dlp, the ffmpeg merge, ffprobe and NFO writing.
+/// The scratch directory is removed afterwards.
+pub async fn run(yt_dlp: &YtDlp, ffprobe_bin: &str) -> SelfTestReport {
+    let url = std::env::var("SELF_TEST_URL").unwrap_or_else(|_| DEFAULT_SELF_TEST_URL.to_string());
+    let work_dir = std::env::temp_dir().join(format!("toobarr-self-test-{}", uuid7::uuid7()));
+
+    let stages = run_stages(yt_dlp, ffprobe_bin, &url, &work_dir).await;
+
+    if let Err(e) = tokio::fs::remove_dir_all(&work_dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Failed to clean up self-test directory {}: {}", work_dir.display(), e);
+        }
+    }
+
+    let passed = stages.iter().all(|s| s.status == StageStatus::Pass);
+    SelfTestReport { passed, url, stages }
+}
+
+async fn run_stages(yt_dlp: &YtDlp, ffprobe_bin: &str, url: &str, work_dir: &Path) -> Vec<StageResult> {
+    let mut stages = Stages(Vec::new());
+
+    match yt_dlp.check_binary().await {
+        Ok(version) => stages.pass("yt-dlp", version),
+        Err(e) => {
+            stages.fail("yt-dlp", e.to_string());
+            stages.skip(&["download", "ffprobe", "merge", "nfo"], "yt-dlp unavailable");
+            return stages.0;
+        }
+    }
+
+    if let Err(e) = tokio::fs::create_dir_all(work_dir).await {
+        stages.fail("download", format!("Failed to create {}: {e}", work_dir.display()));
+        stages.skip(&["ffprobe", "merge", "nfo"], "download failed");
+        return stages.0;
+    }
+
+    let options = DownloadOptions::new()
+        .format(OutputFormat::Custom(SELF_TEST_FORMAT.to_string()))
+        .container(Container::Mkv)
+        .output_template(work_dir.join("self-test.%(ext)s").to_string_lossy().to_string());
+
+    let downloaded = match yt_dlp.download_with_options(url, work_dir, &options).await {
+        Ok(path) if path.is_file() => Ok(path),
+        Ok(path) => Err(format!("yt-dlp succeeded but {} was not written", path.display())),
+        Err(e) => Err(e.to_string())
+    };
+    let video_path = match downloaded {
+        Ok(path) => {
+            stages.pass("download", path.display().to_string());
+            path
+        }
+        Err(detail) => {
+            stages.fail("download", detail);
+            stages.skip(&["ffprobe", "merge", "nfo"], "download failed");
+            return stages.0;
+        }
+    };
+    let video_path = video_path.to_string_lossy().to_string();
+
+    let media_info = nfo::probe_media(&video_path, ffprobe_bin).await;
+    if let Some(ref info) = media_info {
+        stages.pass("ffprobe", ffprobe_bin);
+        match (&info.video, &info.audio) {
+            (Some(video), Some(audio)) => stages.pass(
+                "merge",
+                format!("{} {}x{} + {}", video.codec, video.width, video.height, audio.codec)
+            ),
+            _ => stages.fail("merge", "merged file is missing a video or audio stream")
+        }
+    } else {
+        stages.fail("ffprobe", format!("{ffprobe_bin} could not read {video_path}"));
+        stages.skip(&["merge"], "ffprobe failed");
+    }
+
+    let nfo_data = VideoNfo {
+        title: "Self-test".to_string(),
+        description: None,
+        youtube_id: "self-test".to_string(),
+        channel_name: "Self-test".to_string(),
+        upload_date: None,
+        upload_timestamp: None,
+        director: None,
+        duration_seconds: None,
+        tags: Vec::new(),
+        categories: Vec::new(),
+        episode: None,
+        thumb_filename: None,
+        media_info
+    };
+    match nfo::write_nfo(&video_path, &nfo_data).await {
+        Ok(path) => stages.pass("nfo", path),
+        Err(e) => stages.fail("nfo", e.to_string())
+    }
+
+    stages.0
+}
@@ -4,24 +4,148 @@ use tokio::sync::{RwLock, mpsc};
 use yt_dlp::YtDlp;
 
 use crate::db::DbPool;
+use crate::metrics::Metrics;
+use crate::version_cache::VersionCache;
 use crate::workers::download::DownloadCommand;
+use crate::workers::thumbnails::ThumbnailProgressMap;
+
+/// Oldest yt-dlp release known to work with current `YouTube` extraction.
+pub const MIN_YTDLP_VERSION: (u32, u32, u32) = (2025, 1, 26);
+
+/// Releases older than this are flagged on the settings page; `YouTube`
+/// changes often enough that stale builds are the usual cause of failures.
+pub const YTDLP_STALE_DAYS: i64 = 30;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: DbPool,
     pub yt_dlp: Arc<RwLock<YtDlp>>,
     pub download_tx: mpsc::Sender<DownloadCommand>,
-    pub download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>
+    pub download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>,
+    pub version_cache: VersionCache,
+    pub metrics: Metrics,
+    pub thumbnail_progress: ThumbnailProgressMap
+}
+
+/// Background thumbnail fetches for one channel.
+#[derive(Clone, Copy, Debug, serde::Serialize)]
+pub struct ThumbnailProgress {
+    pub done: usize,
+    pub total: usize
 }
 
-#[derive(Clone, Debug, serde::Serialize)]
+/// Live state of one download. Fields other than `status` default to empty,
+/// so updates only spell out what they know.
+#[derive(Clone, Debug, Default, serde::Serialize)]
 pub struct DownloadStateInfo {
     pub status: String,
     pub percent: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speed: Option<String>,
+    /// Current speed in bytes per second, summed by [`DownloadSummary`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_bytes: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eta: Option<String>,
+    /// Seconds left as reported by yt-dlp, for sorting without parsing `eta`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<u64>,
+    /// `fragment 12/345` for DASH/HLS downloads, where percent is jumpy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fragment: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>
 }
+
+impl DownloadStateInfo {
+    pub fn is_active(&self) -> bool {
+        matches!(self.status.as_str(), "started" | "resolving" | "progress" | "processing")
+    }
+}
+
+/// Combined progress of the in-flight downloads.
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+pub struct DownloadSummary {
+    pub active: usize,
+    /// Sum of the current speeds in bytes per second.
+    pub speed_bytes: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<String>,
+    /// Percent across all active downloads, weighted by size. Downloads of
+    /// unknown size count as the average known size.
+    pub percent: f64,
+    /// Sum of the known download sizes.
+    pub total_bytes: u64
+}
+
+impl DownloadSummary {
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn from_states<'a>(states: impl IntoIterator<Item = &'a DownloadStateInfo>) -> Self {
+        let active: Vec<&DownloadStateInfo> = states.into_iter().filter(|s| s.is_active()).collect();
+        if active.is_empty() {
+            return Self::default();
+        }
+
+        let speed_bytes: f64 = active.iter().filter_map(|s| s.speed_bytes).sum();
+        let known: Vec<u64> = active.iter().filter_map(|s| s.total_bytes).collect();
+        let total_bytes: u64 = known.iter().sum();
+        let unknown_weight = if known.is_empty() { 1.0 } else { total_bytes as f64 / known.len() as f64 };
+        let (weighted, weights) = active.iter().fold((0.0, 0.0), |(weighted, weights), s| {
+            let weight = s.total_bytes.map_or(unknown_weight, |bytes| bytes as f64);
+            (weighted + s.percent * weight, weights + weight)
+        });
+
+        Self {
+            active: active.len(),
+            speed_bytes,
+            speed: (speed_bytes > 0.0).then(|| format!("{}/s", yt_dlp::format_bytes(speed_bytes as u64))),
+            percent: if weights > 0.0 { weighted / weights } else { 0.0 },
+            total_bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(status: &str, percent: f64, speed_bytes: Option<f64>, total_bytes: Option<u64>) -> DownloadStateInfo {
+        DownloadStateInfo {
+            status: status.to_string(),
+            percent,
+            speed_bytes,
+            total_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_download_summary_weights_by_size() {
+        let states = [
+            state("progress", 50.0, Some(1_000_000.0), Some(300)),
+            state("progress", 100.0, Some(500_000.0), Some(100)),
+            state("completed", 100.0, None, Some(1000)),
+            state("failed", 0.0, None, None)
+        ];
+        let summary = DownloadSummary::from_states(&states);
+        assert_eq!(summary.active, 2);
+        assert!((summary.speed_bytes - 1_500_000.0).abs() < f64::EPSILON);
+        assert_eq!(summary.speed.as_deref(), Some("1.50 MB/s"));
+        assert!((summary.percent - 62.5).abs() < f64::EPSILON);
+        assert_eq!(summary.total_bytes, 400);
+    }
+
+    #[test]
+    fn test_download_summary_unknown_sizes() {
+        let states = [state("started", 0.0, None, None), state("progress", 40.0, None, Some(200))];
+        let summary = DownloadSummary::from_states(&states);
+        assert!((summary.percent - 20.0).abs() < f64::EPSILON);
+        assert_eq!(summary.speed, None);
+
+        assert_eq!(DownloadSummary::from_states(&[]), DownloadSummary::default());
+    }
+}
@@ -1,8 +1,57 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 const THUMBNAIL_DIR: &str = "static/thumbnails";
+const DEFAULT_VALIDATOR_DIR: &str = "cache/thumbnails";
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+/// How long an image whose server sent no `ETag`/`Last-Modified` is reused
+/// before it is fetched again.
+const UNVALIDATED_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Shared client for thumbnail fetches. Timeouts can be overridden with
+/// `THUMBNAIL_CONNECT_TIMEOUT_SECS` and `THUMBNAIL_TIMEOUT_SECS`.
+fn http_client() -> &'static reqwest::Client {
+    HTTP_CLIENT.get_or_init(|| {
+        let connect_timeout =
+            env_secs("THUMBNAIL_CONNECT_TIMEOUT_SECS").unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+        let timeout = env_secs("THUMBNAIL_TIMEOUT_SECS").unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+        build_client(Duration::from_secs(connect_timeout), Duration::from_secs(timeout))
+    })
+}
+
+/// Where cache validators are kept, `THUMBNAIL_CACHE_DIR` or
+/// `./cache/thumbnails`, so media folders only hold the images themselves.
+fn validator_dir() -> PathBuf {
+    std::env::var("THUMBNAIL_CACHE_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_VALIDATOR_DIR), PathBuf::from)
+}
+
+fn env_secs(key: &str) -> Option<u64> {
+    std::env::var(key).ok()?.trim().parse().ok()
+}
+
+fn build_client(connect_timeout: Duration, timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(timeout)
+        .build()
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to build thumbnail HTTP client, using defaults: {}", e);
+            reqwest::Client::new()
+        })
+}
 
 pub async fn download_channel_thumbnail(
     channel_id: &str,
@@ -22,8 +71,7 @@ pub async fn download_video_thumbnail(
     video_id: &str,
     url: &str
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let extension = get_extension_from_url(url);
-    let filename = format!("{video_id}.{extension}");
+    let filename = video_thumbnail_name(video_id, url);
     let local_path = format!("{THUMBNAIL_DIR}/videos/{filename}");
     let web_path = format!("/static/thumbnails/videos/{filename}");
 
@@ -32,28 +80,166 @@ pub async fn download_video_thumbnail(
     Ok(web_path)
 }
 
+/// Whether the video's thumbnail is on disk and was fetched from `url`, so
+/// there is nothing new to download.
+pub async fn has_video_thumbnail(video_id: &str, url: &str) -> bool {
+    let local_path = format!("{THUMBNAIL_DIR}/videos/{}", video_thumbnail_name(video_id, url));
+    has_image_from(&validator_dir(), Path::new(&local_path), url).await
+}
+
+fn video_thumbnail_name(video_id: &str, url: &str) -> String {
+    format!("{video_id}.{}", get_extension_from_url(url))
+}
+
+async fn has_image_from(validator_dir: &Path, local_path: &Path, url: &str) -> bool {
+    read_validators(validator_dir, local_path)
+        .await
+        .is_some_and(|cached| cached.url.as_deref() == Some(url))
+}
+
+/// The image URL answered with a non-success status, e.g. a `YouTube` size
+/// that was never generated for the video.
+#[derive(Debug)]
+pub struct HttpStatusError(pub reqwest::StatusCode);
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to download image: HTTP {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// `ETag` and `Last-Modified` of a downloaded image, the URL they belong to
+/// and when it was fetched, kept in the validator directory under a hash of
+/// the image's path.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Validators {
+    /// Validators only apply to the URL that returned them; files written
+    /// before this was stored have none and are fetched again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    #[serde(default)]
+    fetched_at: i64
+}
+
+impl Validators {
+    fn from_headers(url: &str, headers: &HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+        Self {
+            url: Some(url.to_string()),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            fetched_at: chrono::Utc::now().timestamp()
+        }
+    }
+
+    /// Without validators the server cannot answer 304, so the image is
+    /// simply reused until it is `UNVALIDATED_MAX_AGE_SECS` old.
+    fn is_fresh(&self, now: i64) -> bool {
+        self.etag.is_none()
+            && self.last_modified.is_none()
+            && now - self.fetched_at < UNVALIDATED_MAX_AGE_SECS
+    }
+}
+
+/// FNV-1a of the image path, stable across builds unlike `DefaultHasher`.
+fn validators_path(dir: &Path, local_path: &Path) -> PathBuf {
+    let path = std::path::absolute(local_path).unwrap_or_else(|_| local_path.to_path_buf());
+    let hash = path.to_string_lossy().bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    dir.join(format!("{hash:016x}.http.json"))
+}
+
+async fn read_validators(dir: &Path, local_path: &Path) -> Option<Validators> {
+    if !fs::try_exists(local_path).await.unwrap_or(false) {
+        return None;
+    }
+    let contents = fs::read(validators_path(dir, local_path)).await.ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+async fn write_validators(dir: &Path, local_path: &Path, validators: &Validators) {
+    // Older versions kept a hidden `.<name>.http.json` next to the image
+    if let Some(name) = local_path.file_name() {
+        let legacy = local_path.with_file_name(format!(".{}.http.json", name.to_string_lossy()));
+        let _ = fs::remove_file(legacy).await;
+    }
+    let path = validators_path(dir, local_path);
+    let result = match fs::create_dir_all(dir).await {
+        Ok(()) => fs::write(&path, serde_json::to_vec(validators).unwrap_or_default()).await,
+        Err(e) => Err(e)
+    };
+    if let Err(e) = result {
+        tracing::debug!("Failed to store cache validators {}: {}", path.display(), e);
+    }
+}
+
+/// Fetches `url` into `local_path`. An image downloaded before is revalidated
+/// with its stored `ETag`/`Last-Modified`: a 304 keeps the file, anything new
+/// replaces it once it has fully arrived, so a failed fetch never loses the
+/// old image.
 pub async fn download_image(
     url: &str,
     local_path: &str
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if Path::new(local_path).exists() {
+    download_image_with(http_client(), &validator_dir(), url, local_path).await
+}
+
+async fn download_image_with(
+    client: &reqwest::Client,
+    validator_dir: &Path,
+    url: &str,
+    local_path: &str
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = Path::new(local_path);
+    // Another URL for the same file, e.g. a fallback candidate, is a
+    // different resource; its validators and age say nothing about it
+    let cached = read_validators(validator_dir, path)
+        .await
+        .filter(|cached| cached.url.as_deref() == Some(url));
+    if cached.as_ref().is_some_and(|cached| cached.is_fresh(chrono::Utc::now().timestamp())) {
+        tracing::debug!("Thumbnail fetched recently: {}", local_path);
         return Ok(());
     }
 
-    if let Some(parent) = Path::new(local_path).parent() {
+    if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).await?;
     }
 
-    let response = reqwest::get(url).await?;
+    let mut request = client.get(url);
+    if let Some(ref cached) = cached {
+        if let Some(ref etag) = cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await?;
 
+    if cached.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+        tracing::debug!("Thumbnail unchanged: {}", local_path);
+        return Ok(());
+    }
     if !response.status().is_success() {
-        return Err(format!("Failed to download image: HTTP {}", response.status()).into());
+        return Err(HttpStatusError(response.status()).into());
     }
 
+    let validators = Validators::from_headers(url, response.headers());
     let bytes = response.bytes().await?;
 
-    let mut file = fs::File::create(local_path).await?;
+    let partial = format!("{local_path}.part");
+    let mut file = fs::File::create(&partial).await?;
     file.write_all(&bytes).await?;
+    file.flush().await?;
+    fs::rename(&partial, local_path).await?;
+    write_validators(validator_dir, path, &validators).await;
 
     tracing::debug!("Downloaded thumbnail to {}", local_path);
 
@@ -69,3 +255,183 @@ pub fn get_extension_from_url(url: &str) -> &str {
         "jpg"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Minimal HTTP server: `/missing*` returns 404, `/plain*` a body without
+    /// validators, a request revalidating the `"v1"` `ETag` gets 304, anything
+    /// else returns a tiny body.
+    async fn spawn_image_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let response: &[u8] = if request.starts_with("GET /missing") {
+                    b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                } else if request.starts_with("GET /plain") {
+                    b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nplain"
+                } else if request.to_ascii_lowercase().contains("if-none-match: \"v1\"") {
+                    b"HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 4\r\nconnection: close\r\n\r\njpeg"
+                };
+                let _ = socket.write_all(response).await;
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_download_image_revalidates_with_etag() {
+        let base = spawn_image_server().await;
+        let client = build_client(Duration::from_secs(2), Duration::from_secs(5));
+        let dir = std::env::temp_dir().join(format!("toobarr-etag-{}", uuid7::uuid7()));
+        let cache = dir.join("cache");
+        let local = dir.join("media").join("thumb.jpg");
+        let local_path = local.to_string_lossy().to_string();
+        let validators = validators_path(&cache, &local);
+
+        download_image_with(&client, &cache, &format!("{base}/a.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"jpeg");
+        let stored = read_validators(&cache, &local).await.unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(stored.last_modified, None);
+        // Validators stay out of the media folder
+        assert_eq!(std::fs::read_dir(dir.join("media")).unwrap().count(), 1);
+
+        // Unchanged upstream: the 304 leaves the cached file alone
+        fs::write(&local, b"cached").await.unwrap();
+        download_image_with(&client, &cache, &format!("{base}/a.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"cached");
+
+        // A failed fetch keeps the old image
+        assert!(download_image_with(&client, &cache, &format!("{base}/missing.jpg"), &local_path)
+            .await
+            .is_err());
+        assert_eq!(fs::read(&local).await.unwrap(), b"cached");
+
+        // Without validators the image is fetched again
+        fs::remove_file(&validators).await.unwrap();
+        download_image_with(&client, &cache, &format!("{base}/a.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"jpeg");
+        assert!(!Path::new(&format!("{local_path}.part")).exists());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_image_without_validators_uses_max_age() {
+        let base = spawn_image_server().await;
+        let client = build_client(Duration::from_secs(2), Duration::from_secs(5));
+        let dir = std::env::temp_dir().join(format!("toobarr-max-age-{}", uuid7::uuid7()));
+        let cache = dir.join("cache");
+        let local = dir.join("thumb.jpg");
+        let local_path = local.to_string_lossy().to_string();
+        let url = format!("{base}/plain.jpg");
+
+        download_image_with(&client, &cache, &url, &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"plain");
+
+        // Fetched recently, so the image is kept without a request
+        fs::write(&local, b"cached").await.unwrap();
+        download_image_with(&client, &cache, &url, &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"cached");
+
+        // Once older than the max age it is fetched again
+        let fetched_at = chrono::Utc::now().timestamp() - UNVALIDATED_MAX_AGE_SECS;
+        let stale = Validators { url: Some(url.clone()), fetched_at, ..Validators::default() };
+        write_validators(&cache, &local, &stale).await;
+        download_image_with(&client, &cache, &url, &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"plain");
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_image_ignores_validators_of_another_url() {
+        let base = spawn_image_server().await;
+        let client = build_client(Duration::from_secs(2), Duration::from_secs(5));
+        let dir = std::env::temp_dir().join(format!("toobarr-url-change-{}", uuid7::uuid7()));
+        let cache = dir.join("cache");
+        let local = dir.join("thumb.jpg");
+        let local_path = local.to_string_lossy().to_string();
+
+        // Fresh without validators, yet a new URL is still fetched
+        download_image_with(&client, &cache, &format!("{base}/plain.jpg"), &local_path).await.unwrap();
+        download_image_with(&client, &cache, &format!("{base}/a.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"jpeg");
+
+        // The "v1" ETag of a.jpg is not sent for b.jpg, so no 304 keeps the old file
+        fs::write(&local, b"cached").await.unwrap();
+        download_image_with(&client, &cache, &format!("{base}/b.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"jpeg");
+        let stored = read_validators(&cache, &local).await.unwrap();
+        assert_eq!(stored.url, Some(format!("{base}/b.jpg")));
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_has_image_from() {
+        let base = spawn_image_server().await;
+        let client = build_client(Duration::from_secs(2), Duration::from_secs(5));
+        let dir = std::env::temp_dir().join(format!("toobarr-has-image-{}", uuid7::uuid7()));
+        let cache = dir.join("cache");
+        let local = dir.join("thumb.jpg");
+        let url = format!("{base}/a.jpg");
+
+        assert!(!has_image_from(&cache, &local, &url).await);
+        download_image_with(&client, &cache, &url, &local.to_string_lossy()).await.unwrap();
+        assert!(has_image_from(&cache, &local, &url).await);
+        assert!(!has_image_from(&cache, &local, &format!("{base}/b.jpg")).await);
+
+        fs::remove_file(&local).await.unwrap();
+        assert!(!has_image_from(&cache, &local, &url).await);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_image_rejects_404() {
+        let base = spawn_image_server().await;
+        let client = build_client(Duration::from_secs(2), Duration::from_secs(5));
+        let dir = std::env::temp_dir().join(format!("toobarr-404-{}", uuid7::uuid7()));
+        let cache = dir.join("cache");
+        let local = dir.join("thumb.jpg");
+        let local_path = local.to_string_lossy().to_string();
+
+        let err = download_image_with(&client, &cache, &format!("{base}/missing.jpg"), &local_path)
+            .await
+            .unwrap_err();
+        let status = err.downcast_ref::<HttpStatusError>().map(|e| e.0);
+        assert_eq!(status, Some(reqwest::StatusCode::NOT_FOUND));
+        download_image_with(&client, &cache, &format!("{base}/found.jpg"), &local_path).await.unwrap();
+        assert_eq!(fs::read(&local).await.unwrap(), b"jpeg");
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_download_image_times_out_on_unroutable_host() {
+        let client = build_client(Duration::from_millis(200), Duration::from_millis(500));
+        let dir = std::env::temp_dir().join(format!("toobarr-timeout-{}", uuid7::uuid7()));
+        let local_path = dir.join("thumb.jpg").to_string_lossy().to_string();
+
+        let started = std::time::Instant::now();
+        // TEST-NET-1 (RFC 5737) is reserved and never routed.
+        let result = download_image_with(&client, &dir.join("cache"), "http://192.0.2.1/thumb.jpg", &local_path).await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!Path::new(&local_path).exists());
+
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+}
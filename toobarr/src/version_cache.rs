@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// How long a probed `--version` result stays valid.
+pub const VERSION_CACHE_TTL: Duration = Duration::from_mins(10);
+
+struct CachedVersion {
+    version: Option<String>,
+    checked_at: Instant
+}
+
+/// Caches `--version` output per binary path so pages don't spawn a process
+/// for every binary on each load. Lookups hold the lock while probing, so
+/// concurrent requests for a cold entry only spawn the binary once.
+#[derive(Clone)]
+pub struct VersionCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, CachedVersion>>>
+}
+
+impl VersionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new()))
+        }
+    }
+
+    pub async fn get(&self, binary: &str) -> Option<String> {
+        self.get_or_probe(binary, check_binary_version).await
+    }
+
+    pub async fn invalidate(&self) {
+        self.entries.lock().await.clear();
+    }
+
+    async fn get_or_probe<'a, F, Fut>(&self, binary: &'a str, probe: F) -> Option<String>
+    where
+        F: FnOnce(&'a str) -> Fut,
+        Fut: Future<Output = Option<String>>
+    {
+        let mut entries = self.entries.lock().await;
+        if let Some(cached) = entries.get(binary) {
+            if cached.checked_at.elapsed() < self.ttl {
+                return cached.version.clone();
+            }
+        }
+
+        let version = probe(binary).await;
+        entries.insert(
+            binary.to_string(),
+            CachedVersion { version: version.clone(), checked_at: Instant::now() }
+        );
+        version
+    }
+}
+
+pub async fn check_binary_version(binary: &str) -> Option<String> {
+    let output = tokio::process::Command::new(binary)
+        .arg("--version")
+        .output()
+        .await
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    async fn probe_counted(cache: &VersionCache, calls: &AtomicUsize) -> Option<String> {
+        cache
+            .get_or_probe("yt-dlp", |_| async {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                Some(format!("2025.0{n}.01"))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_version_cache_hits_within_ttl() {
+        let cache = VersionCache::new(Duration::from_mins(1));
+        let calls = AtomicUsize::new(0);
+
+        assert_eq!(probe_counted(&cache, &calls).await.as_deref(), Some("2025.01.01"));
+        assert_eq!(probe_counted(&cache, &calls).await.as_deref(), Some("2025.01.01"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_version_cache_expires_and_refreshes() {
+        let cache = VersionCache::new(Duration::from_millis(20));
+        let calls = AtomicUsize::new(0);
+
+        assert_eq!(probe_counted(&cache, &calls).await.as_deref(), Some("2025.01.01"));
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(probe_counted(&cache, &calls).await.as_deref(), Some("2025.02.01"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_version_cache_invalidate() {
+        let cache = VersionCache::new(Duration::from_mins(1));
+        let calls = AtomicUsize::new(0);
+
+        probe_counted(&cache, &calls).await;
+        cache.invalidate().await;
+        assert_eq!(probe_counted(&cache, &calls).await.as_deref(), Some("2025.02.01"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -4,43 +4,267 @@ use std::sync::Arc;
 
 use tokio::sync::{mpsc, RwLock};
 use tokio_stream::StreamExt;
-use yt_dlp::{DownloadEvent, DownloadOptions, YtDlp};
+use yt_dlp::{DateRange, DownloadEvent, DownloadOptions, OutputFormat, PathType, YtDlp};
 
-use crate::db::DbPool;
-use crate::models::{Download, DownloadStatus, Settings};
+use crate::db::{self, DbPool};
+use crate::metrics::Metrics;
+use crate::models::{
+    Channel, DirectoryLayout, Download, DownloadStatus, EpisodeScheme, Settings, Video,
+    DEFAULT_INTEGRITY_TOLERANCE, DEFAULT_OUTPUT_TEMPLATE, DEFAULT_RETRY_BASE_DELAY, DEFAULT_RETRY_MAX_ATTEMPTS,
+    THUMBNAIL_FORMATS
+};
 use crate::nfo::{self, VideoNfo};
 use crate::state::DownloadStateInfo;
 use crate::thumbnail;
 
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
+/// `--wait-for-video` retry interval in seconds for upcoming streams.
+const LIVE_WAIT_INTERVAL: &str = "60";
+
+/// How often the queue is checked without a command, so a raised
+/// `max_concurrent_downloads` takes effect.
+const QUEUE_RECHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+const ACCENT_CHARS: &[(char, &str)] = &[
+    ('Â', "A"), ('Ã', "A"), ('Ä', "A"), ('À', "A"), ('Á', "A"), ('Å', "A"), ('Æ', "AE"),
+    ('Ç', "C"), ('È', "E"), ('É', "E"), ('Ê', "E"), ('Ë', "E"), ('Ì', "I"), ('Í', "I"),
+    ('Î', "I"), ('Ï', "I"), ('Ð', "D"), ('Ñ', "N"), ('Ò', "O"), ('Ó', "O"), ('Ô', "O"),
+    ('Õ', "O"), ('Ö', "O"), ('Ő', "O"), ('Ø', "O"), ('Œ', "OE"), ('Ù', "U"), ('Ú', "U"),
+    ('Û', "U"), ('Ü', "U"), ('Ű', "U"), ('Ý', "Y"), ('Þ', "TH"), ('ß', "ss"),
+    ('à', "a"), ('á', "a"), ('â', "a"), ('ã', "a"), ('ä', "a"), ('å', "a"), ('æ', "ae"),
+    ('ç', "c"), ('è', "e"), ('é', "e"), ('ê', "e"), ('ë', "e"), ('ì', "i"), ('í', "i"),
+    ('î', "i"), ('ï', "i"), ('ð', "o"), ('ñ', "n"), ('ò', "o"), ('ó', "o"), ('ô', "o"),
+    ('õ', "o"), ('ö', "o"), ('ő', "o"), ('ø', "o"), ('œ', "oe"), ('ù', "u"), ('ú', "u"),
+    ('û', "u"), ('ü', "u"), ('ű', "u"), ('ý', "y"), ('þ', "th"), ('ÿ', "y")
+];
+
+/// Mirrors the yt-dlp flags used for the media file so the channel folder
+/// is named with the same scheme.
+#[derive(Debug, Clone, Copy, Default)]
+struct FilenameMode {
+    restrict: bool,
+    windows: bool
+}
+
+fn sanitize_filename(name: &str, mode: FilenameMode) -> String {
+    let sanitized = if mode.restrict {
+        restrict_filename(name)
+    } else {
+        name.chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                _ => c
+            })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    };
+
+    if mode.windows {
+        windows_filename(&sanitized)
+    } else {
+        sanitized
+    }
+}
+
+/// Port of yt-dlp's `sanitize_filename(restricted=True)`.
+fn restrict_filename(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::with_capacity(name.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        // Timestamps like 12:34 become 12_34 rather than 12_-34
+        let in_timestamp = c == ':'
+            && i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+
+        if in_timestamp {
+            result.push('_');
+        } else if let Some((_, ascii)) = ACCENT_CHARS.iter().find(|(accent, _)| *accent == c) {
+            result.push_str(ascii);
+        } else if c == '?' || c == '"' || c.is_ascii_control() {
+            // Dropped entirely
+        } else if c == ':' {
+            result.push_str("_-");
+        } else if matches!(c, '\\' | '/' | '|' | '*' | '<' | '>') {
+            result.push('_');
+        } else if "!&'()[]{}$;`^,#".contains(c) || c.is_whitespace() || !c.is_ascii() {
+            if !c.is_control() && !is_mark_or_format(c) {
+                result.push('_');
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    while result.contains("__") {
+        result = result.replace("__", "_");
+    }
+    let mut result = result.trim_matches('_').to_string();
+    if let Some(rest) = result.strip_prefix("-_") {
+        result = rest.to_string();
+    }
+    if let Some(rest) = result.strip_prefix('-') {
+        result = format!("_{rest}");
+    }
+    let result = result.trim_start_matches('.');
+
+    if result.is_empty() {
+        "_".to_string()
+    } else {
+        result.to_string()
+    }
+}
+
+/// Combining marks and invisible format characters (ZWJ, variation selectors)
+/// are dropped instead of becoming underscores.
+fn is_mark_or_format(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{FEFF}'
+            | '\u{E0000}'..='\u{E007F}'
+            | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+/// Port of yt-dlp's `--windows-filenames` path rule: reserved characters and
+/// a trailing dot or space become `#`.
+fn windows_filename(name: &str) -> String {
+    let mut result: String = name
+        .chars()
         .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            '<' | '>' | ':' | '"' | '|' | '\\' | '/' | '?' | '*' => '#',
             _ => c
         })
-        .collect::<String>()
-        .trim()
-        .to_string()
+        .collect();
+    if result.ends_with(|c: char| c == '.' || c.is_whitespace()) {
+        result.pop();
+        result.push('#');
+    }
+    result
 }
 
 #[derive(Debug, Clone)]
 pub struct VideoMeta {
     pub youtube_id: String,
+    /// The site a non-`YouTube` key is prefixed with, `None` for `YouTube`.
+    pub site: Option<String>,
     pub title: String,
     pub description: Option<String>,
     pub duration_seconds: Option<i64>,
-    pub upload_date: Option<String>
+    pub upload_date: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub tags: Vec<String>,
+    pub categories: Vec<String>,
+    pub live_status: Option<String>,
+    /// The `videos` row, which episode numbering is derived from.
+    pub video_id: String
 }
 
-#[derive(Debug, Clone)]
+/// A channel's conditions for which videos yt-dlp should download.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadFilter {
+    pub match_filter: Option<String>,
+    pub no_shorts: bool,
+    /// Checked again at download time, since syncs keep entries whose flat
+    /// listing had no upload date.
+    pub date_range: DateRange,
+    /// The channel keeps only the audio track.
+    pub audio_only: bool
+}
+
+impl DownloadFilter {
+    pub fn for_channel(channel: &Channel) -> Self {
+        Self {
+            match_filter: channel.match_filter.clone(),
+            no_shorts: channel.no_shorts,
+            // The range was validated when it was saved
+            date_range: DateRange::parse(channel.date_after.as_deref(), channel.date_before.as_deref())
+                .unwrap_or_default(),
+            audio_only: channel.audio_only
+        }
+    }
+
+    fn apply(self, options: DownloadOptions) -> DownloadOptions {
+        let options = options.no_shorts(self.no_shorts);
+        let filters: Vec<String> = self
+            .match_filter
+            .map(|filter| filter.trim().to_string())
+            .filter(|filter| !filter.is_empty())
+            .into_iter()
+            .chain(self.date_range.match_filter())
+            .collect();
+        if filters.is_empty() {
+            options
+        } else {
+            options.match_filter(filters.join(" & "))
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum DownloadCommand {
     Start {
         download_id: String,
         video_url: String,
         channel_name: String,
-        video_meta: VideoMeta
+        video_meta: Box<VideoMeta>,
+        filter: DownloadFilter
     },
-    Cancel { download_id: String }
+    /// `ack` receives whether the download was running. A running download
+    /// is marked failed by the worker once yt-dlp has exited.
+    Cancel {
+        download_id: String,
+        ack: tokio::sync::oneshot::Sender<bool>
+    },
+    /// `ack` receives whether the worker held the download. A running one is
+    /// marked paused once yt-dlp has exited, with its partial files kept.
+    Pause {
+        download_id: String,
+        ack: tokio::sync::oneshot::Sender<bool>
+    },
+    /// Puts a paused download back in the queue. `ack` is `false` when the
+    /// worker no longer holds it, e.g. after a restart; it then needs a
+    /// fresh `Start`.
+    Resume {
+        download_id: String,
+        ack: tokio::sync::oneshot::Sender<bool>
+    }
+}
+
+/// Why a running download is stopped before it finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    Cancel,
+    Pause
+}
+
+/// How a download task ended, for the worker's bookkeeping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Finished,
+    /// Stopped with its partial files kept for a resume.
+    Paused,
+    /// Failed with a transient error; queued again after the delay.
+    Retry(std::time::Duration)
+}
+
+/// A `Start` waiting for a free download slot, or kept while paused.
+#[derive(Clone)]
+struct QueuedStart {
+    video_url: String,
+    channel_name: String,
+    video_meta: Box<VideoMeta>,
+    filter: DownloadFilter
 }
 
 pub struct DownloadWorker {
@@ -48,7 +272,8 @@ pub struct DownloadWorker {
     yt_dlp: Arc<RwLock<YtDlp>>,
     rx: mpsc::Receiver<DownloadCommand>,
     download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>,
-    active_downloads: Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<()>>>>
+    active_downloads: Arc<RwLock<HashMap<String, tokio::sync::oneshot::Sender<Stop>>>>,
+    metrics: Metrics
 }
 
 impl DownloadWorker {
@@ -56,68 +281,217 @@ impl DownloadWorker {
         pool: DbPool,
         yt_dlp: Arc<RwLock<YtDlp>>,
         rx: mpsc::Receiver<DownloadCommand>,
-        download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>
+        download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>,
+        metrics: Metrics
     ) -> Self {
         Self {
             pool,
             yt_dlp,
             rx,
             download_states,
-            active_downloads: Arc::new(RwLock::new(HashMap::new()))
+            active_downloads: Arc::new(RwLock::new(HashMap::new())),
+            metrics
         }
     }
 
+    /// Queued downloads wait for a free slot; `max_concurrent_downloads` run
+    /// at once and the pending one with the highest priority starts next.
     pub async fn run(mut self) {
         tracing::info!("Download worker started");
 
-        while let Some(cmd) = self.rx.recv().await {
-            match cmd {
-                DownloadCommand::Start {
-                    download_id,
-                    video_url,
-                    channel_name,
-                    video_meta
-                } => {
-                    let pool = self.pool.clone();
-                    let yt_dlp = self.yt_dlp.read().await.clone();
-                    let download_states = self.download_states.clone();
-                    let active_downloads = self.active_downloads.clone();
-
-                    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
-                    {
-                        let mut downloads = active_downloads.write().await;
-                        downloads.insert(download_id.clone(), cancel_tx);
-                    }
+        let mut queued: HashMap<String, QueuedStart> = HashMap::new();
+        let mut paused: HashMap<String, QueuedStart> = HashMap::new();
+        // Downloads waiting out a retry delay, queued again once it is due
+        let mut backoff: HashMap<String, (tokio::time::Instant, QueuedStart)> = HashMap::new();
+        let mut running = 0;
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        let mut recheck = tokio::time::interval(QUEUE_RECHECK_INTERVAL);
 
-                    tokio::spawn(async move {
-                        process_download(
-                            pool,
-                            yt_dlp,
-                            download_states.clone(),
-                            download_id.clone(),
-                            video_url,
-                            channel_name,
-                            video_meta,
-                            cancel_rx
-                        )
-                        .await;
-
-                        let mut downloads = active_downloads.write().await;
-                        downloads.remove(&download_id);
-                    });
-                }
-                DownloadCommand::Cancel { download_id } => {
-                    let mut downloads = self.active_downloads.write().await;
-                    if let Some(cancel_tx) = downloads.remove(&download_id) {
-                        let _ = cancel_tx.send(());
-                        tracing::info!("Sent cancel signal for download {}", download_id);
+        loop {
+            let next_retry = backoff.values().map(|(due, _)| *due).min();
+            tokio::select! {
+                cmd = self.rx.recv() => match cmd {
+                    Some(DownloadCommand::Start {
+                        download_id,
+                        video_url,
+                        channel_name,
+                        video_meta,
+                        filter
+                    }) => {
+                        // The startup resume can race a request for the same row
+                        let held = queued.contains_key(&download_id)
+                            || paused.contains_key(&download_id)
+                            || backoff.contains_key(&download_id)
+                            || self.active_downloads.read().await.contains_key(&download_id);
+                        if held {
+                            tracing::debug!("Ignoring start of download {} it already holds", download_id);
+                        } else {
+                            queued.insert(download_id, QueuedStart { video_url, channel_name, video_meta, filter });
+                        }
+                    }
+                    Some(DownloadCommand::Cancel { download_id, ack }) => {
+                        let held = queued
+                            .remove(&download_id)
+                            .or_else(|| paused.remove(&download_id))
+                            .or_else(|| backoff.remove(&download_id).map(|(_, start)| start));
+                        let running = if held.is_some() {
+                            tracing::info!("Removed download {} from the queue", download_id);
+                            false
+                        } else {
+                            self.stop(&download_id, Stop::Cancel).await
+                        };
+                        let _ = ack.send(running);
+                    }
+                    Some(DownloadCommand::Pause { download_id, ack }) => {
+                        let waiting = queued
+                            .remove(&download_id)
+                            .or_else(|| backoff.remove(&download_id).map(|(_, start)| start));
+                        let held = if let Some(start) = waiting {
+                            let _ = db::retry_on_busy(|| {
+                                Download::update_status(&self.pool, &download_id, DownloadStatus::Paused)
+                            })
+                            .await;
+                            tracing::info!("Paused queued download {}", download_id);
+                            paused.insert(download_id, start);
+                            true
+                        } else {
+                            self.stop(&download_id, Stop::Pause).await
+                        };
+                        let _ = ack.send(held);
+                    }
+                    Some(DownloadCommand::Resume { download_id, ack }) => {
+                        let held = match paused.remove(&download_id) {
+                            Some(start) => {
+                                tracing::info!("Resuming download {}", download_id);
+                                queued.insert(download_id, start);
+                                true
+                            }
+                            None => false
+                        };
+                        let _ = ack.send(held);
+                    }
+                    None => break
+                },
+                Some((download_id, outcome, start)) = done_rx.recv() => {
+                    running -= 1;
+                    match outcome {
+                        Outcome::Finished => {}
+                        Outcome::Paused => {
+                            paused.insert(download_id, start);
+                        }
+                        Outcome::Retry(delay) => {
+                            backoff.insert(download_id, (tokio::time::Instant::now() + delay, start));
+                        }
                     }
                 }
+                () = tokio::time::sleep_until(next_retry.unwrap_or_else(tokio::time::Instant::now)),
+                    if next_retry.is_some() => {}
+                _ = recheck.tick() => {}
             }
+            let now = tokio::time::Instant::now();
+            let due: Vec<String> = backoff.iter().filter(|(_, (at, _))| *at <= now).map(|(id, _)| id.clone()).collect();
+            for download_id in due {
+                if let Some((_, start)) = backoff.remove(&download_id) {
+                    queued.insert(download_id, start);
+                }
+            }
+            running += self.start_queued(&mut queued, running, &done_tx).await;
         }
 
         tracing::info!("Download worker stopped");
     }
+
+    /// Signals a running download to stop; returns whether one was running.
+    async fn stop(&self, download_id: &str, stop: Stop) -> bool {
+        let mut downloads = self.active_downloads.write().await;
+        match downloads.remove(download_id) {
+            Some(stop_tx) => {
+                tracing::info!("Sent {:?} signal for download {}", stop, download_id);
+                stop_tx.send(stop).is_ok()
+            }
+            None => false
+        }
+    }
+
+    /// Starts queued downloads in queue order while fewer than
+    /// `max_concurrent_downloads` are running. Returns how many started.
+    async fn start_queued(
+        &self,
+        queued: &mut HashMap<String, QueuedStart>,
+        running: usize,
+        done_tx: &mpsc::UnboundedSender<(String, Outcome, QueuedStart)>
+    ) -> usize {
+        if queued.is_empty() {
+            return 0;
+        }
+        let limit = Settings::get_max_concurrent_downloads(&self.pool).await.unwrap_or(2).max(1);
+        if running >= limit {
+            return 0;
+        }
+        let order = match Download::queue(&self.pool).await {
+            Ok(order) => order,
+            Err(e) => {
+                tracing::warn!("Failed to read the download queue: {}", e);
+                return 0;
+            }
+        };
+        // Rows that left `pending` while queued were cancelled or deleted
+        queued.retain(|id, _| order.iter().any(|entry| &entry.download_id == id));
+
+        let mut started = 0;
+        for entry in order {
+            if running + started >= limit {
+                break;
+            }
+            if let Some(start) = queued.remove(&entry.download_id) {
+                self.spawn_download(entry.download_id, start, done_tx.clone()).await;
+                started += 1;
+            }
+        }
+        started
+    }
+
+    /// `done_tx` gets `start` back with the outcome, so a paused or retried
+    /// download can be queued again.
+    async fn spawn_download(
+        &self,
+        download_id: String,
+        start: QueuedStart,
+        done_tx: mpsc::UnboundedSender<(String, Outcome, QueuedStart)>
+    ) {
+        let pool = self.pool.clone();
+        let yt_dlp = self.yt_dlp.read().await.clone();
+        let download_states = self.download_states.clone();
+        let active_downloads = self.active_downloads.clone();
+        let metrics = self.metrics.clone();
+
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+        {
+            let mut downloads = active_downloads.write().await;
+            downloads.insert(download_id.clone(), stop_tx);
+        }
+
+        tokio::spawn(async move {
+            let resume = start.clone();
+            let outcome = process_download(
+                pool,
+                yt_dlp,
+                download_states.clone(),
+                download_id.clone(),
+                start.video_url,
+                start.channel_name,
+                *start.video_meta,
+                start.filter,
+                stop_rx,
+                metrics
+            )
+            .await;
+
+            let mut downloads = active_downloads.write().await;
+            downloads.remove(&download_id);
+            let _ = done_tx.send((download_id, outcome, resume));
+        });
+    }
 }
 
 #[allow(clippy::too_many_lines, clippy::too_many_arguments)]
@@ -129,24 +503,26 @@ async fn process_download(
     video_url: String,
     channel_name: String,
     video_meta: VideoMeta,
-    mut cancel_rx: tokio::sync::oneshot::Receiver<()>
-) {
+    filter: DownloadFilter,
+    stop_rx: tokio::sync::oneshot::Receiver<Stop>,
+    metrics: Metrics
+) -> Outcome {
     tracing::info!("Starting download {} for {} (channel: {})", download_id, video_url, channel_name);
 
-    if let Err(e) = Download::update_status(&pool, &download_id, DownloadStatus::Downloading).await
+    if let Err(e) = db::retry_on_busy(|| {
+        Download::update_status(&pool, &download_id, DownloadStatus::Downloading)
+    })
+    .await
     {
         tracing::error!("Failed to update download status: {}", e);
-        return;
+        return Outcome::Finished;
     }
 
     {
         let mut states = download_states.write().await;
         states.insert(download_id.clone(), DownloadStateInfo {
             status: "started".to_string(),
-            percent: 0.0,
-            speed: None,
-            eta: None,
-            error: None
+            ..Default::default()
         });
     }
 
@@ -156,12 +532,17 @@ async fn process_download(
             tracing::error!("Failed to get download path: {}", e);
             let _ =
                 Download::update_failed(&pool, &download_id, &format!("Config error: {e}")).await;
-            return;
+            return Outcome::Finished;
         }
     };
 
-    let safe_channel_name = sanitize_filename(&channel_name);
-    let download_path = format!("{base_download_path}/{safe_channel_name}");
+    let filename_mode = load_filename_mode(&pool).await;
+    let layout = Settings::get_directory_layout(&pool).await.unwrap_or_default();
+    let download_path = download_dir(
+        &base_download_path,
+        layout.components(&channel_name, video_meta.upload_date.as_deref()),
+        filename_mode
+    );
 
     if let Err(e) = std::fs::create_dir_all(&download_path) {
         tracing::error!("Failed to create download directory: {}", e);
@@ -171,134 +552,344 @@ async fn process_download(
             &format!("Failed to create directory: {e}")
         )
         .await;
-        return;
+        return Outcome::Finished;
     }
 
-    let output_template = format!("{download_path}/%(title)s.%(ext)s");
-    let output_path = PathBuf::from(&output_template);
+    let filename_template = Settings::get_output_template(&pool)
+        .await
+        .unwrap_or_else(|_| DEFAULT_OUTPUT_TEMPLATE.to_string());
+    let output_path = PathBuf::from(format!("{download_path}/{filename_template}"));
+    let temp_dir = Settings::get_temp_dir(&pool).await.ok().flatten();
 
-    let options = DownloadOptions::default();
+    let options = build_download_options(&pool, filter.audio_only)
+        .await
+        .restrict_filenames(filename_mode.restrict)
+        .windows_filenames(filename_mode.windows);
+    let options = match Settings::get_trim_filenames(&pool).await.ok().flatten() {
+        Some(length) => options.trim_filenames(length),
+        None => options
+    };
+    let options = with_output_location(options, &download_path, &filename_template, temp_dir);
+    let options = with_live_options(options, video_meta.live_status.as_deref());
+    let options = filter.apply(options);
 
-    let stream = yt_dlp.download_with_progress(&video_url, &output_path, &options);
+    // A dropped sender means the worker is gone, not a stop request
+    let stop = Arc::new(std::sync::OnceLock::new());
+    let stop_signal = {
+        let stop = stop.clone();
+        async move {
+            match stop_rx.await {
+                Ok(reason) => {
+                    let _ = stop.set(reason);
+                }
+                Err(_) => std::future::pending::<()>().await
+            }
+        }
+    };
+    let stream = yt_dlp.download_with_progress_until(&video_url, &output_path, &options, stop_signal);
     tokio::pin!(stream);
     tracing::info!("Download {} stream created, waiting for events", download_id);
 
     let mut final_filename: Option<String> = None;
     let mut had_error = false;
+    let mut cancelled = false;
+    let mut paused = false;
     let mut error_message: Option<String> = None;
+    let mut skip_reason: Option<String> = None;
     let mut max_percent: f64 = 0.0;
+    let mut reported_bytes: Option<u64> = None;
+    let mut format_id: Option<String> = None;
+    let mut info_json_path: Option<String> = None;
+    let mut total_bytes: Option<u64> = None;
+    let mut destinations: Vec<String> = Vec::new();
 
     loop {
-        tokio::select! {
-            _ = &mut cancel_rx => {
-                tracing::info!("Download {} cancelled", download_id);
-                had_error = true;
-                error_message = Some("Cancelled by user".to_string());
-                break;
-            }
-            event = stream.next() => {
-                match event {
-                    Some(Ok(event)) => {
-                        tracing::debug!("Download {} event: {:?}", download_id, event);
-                        match &event {
-                            DownloadEvent::Progress(progress) => {
-                                let percent = progress.percent.unwrap_or(0.0);
-                                // Track max progress to prevent pulsing when yt-dlp downloads
-                                // multiple formats/fragments (each reports 0-100%)
-                                if percent > max_percent {
-                                    max_percent = percent;
-                                }
-                                let display_percent = max_percent;
-                                tracing::trace!("Download {} progress: {:.1}% (max: {:.1}%)", download_id, percent, display_percent);
-                                let _ = Download::update_progress(&pool, &download_id, display_percent).await;
-
-                                let mut states = download_states.write().await;
-                                states.insert(download_id.clone(), DownloadStateInfo {
-                                    status: "progress".to_string(),
-                                    percent: display_percent,
-                                    speed: progress.format_speed(),
-                                    eta: progress.format_eta(),
-                                    error: None
-                                });
-                            }
-                            DownloadEvent::DownloadStarted { filename } => {
-                                final_filename = Some(filename.clone());
-                                tracing::info!("Download {} started: {}", download_id, filename);
-                            }
-                            DownloadEvent::PostProcessing { status } => {
-                                tracing::info!("Download {} post-processing: {}", download_id, status);
-                                let mut states = download_states.write().await;
-                                states.insert(download_id.clone(), DownloadStateInfo {
-                                    status: "processing".to_string(),
-                                    percent: 100.0,
-                                    speed: None,
-                                    eta: None,
-                                    error: Some(status.clone())
-                                });
-                            }
-                            DownloadEvent::Finished { filename } => {
-                                final_filename = Some(filename.clone());
-                                tracing::info!("Download {} finished: {}", download_id, filename);
-                            }
-                            DownloadEvent::Error { message } => {
-                                tracing::error!("Download {} error: {}", download_id, message);
-                                had_error = true;
-                                error_message = Some(message.clone());
-                            }
-                            _ => {}
+        match stream.next().await {
+            Some(Ok(event)) => {
+                tracing::debug!("Download {} event: {:?}", download_id, event);
+                match &event {
+                    DownloadEvent::SizeEstimated { total_bytes: estimate } => {
+                        tracing::debug!("Download {} estimated size: {} bytes", download_id, estimate);
+                        total_bytes = Some(*estimate);
+                        let mut states = download_states.write().await;
+                        states.insert(download_id.clone(), DownloadStateInfo {
+                            status: "started".to_string(),
+                            total: total_bytes.map(yt_dlp::format_bytes),
+                            total_bytes,
+                            ..Default::default()
+                        });
+                    }
+                    DownloadEvent::Resolving { stage } if max_percent <= 0.0 => {
+                        let mut states = download_states.write().await;
+                        states.insert(download_id.clone(), DownloadStateInfo {
+                            status: "resolving".to_string(),
+                            total: total_bytes.map(yt_dlp::format_bytes),
+                            total_bytes,
+                            error: Some(stage.clone()),
+                            ..Default::default()
+                        });
+                    }
+                    DownloadEvent::Progress(progress) => {
+                        if progress.total_bytes.is_some() {
+                            total_bytes = progress.total_bytes;
+                        }
+                        let percent = progress.percent.unwrap_or(0.0);
+                        // Track max progress to prevent pulsing when yt-dlp downloads
+                        // multiple formats/fragments (each reports 0-100%)
+                        if percent > max_percent {
+                            max_percent = percent;
                         }
+                        let display_percent = max_percent;
+                        tracing::trace!("Download {} progress: {:.1}% (max: {:.1}%)", download_id, percent, display_percent);
+                        let _ = Download::update_progress(&pool, &download_id, display_percent).await;
+
+                        let mut states = download_states.write().await;
+                        states.insert(download_id.clone(), DownloadStateInfo {
+                            status: "progress".to_string(),
+                            percent: display_percent,
+                            speed: progress.format_speed(),
+                            speed_bytes: progress.speed,
+                            eta: progress.format_eta(),
+                            eta_secs: progress.eta,
+                            total: total_bytes.map(yt_dlp::format_bytes),
+                            total_bytes,
+                            fragment: progress
+                                .fragment_index
+                                .zip(progress.fragment_count)
+                                .map(|(index, count)| format!("fragment {index}/{count}")),
+                            ..Default::default()
+                        });
                     }
-                    Some(Err(e)) => {
-                        tracing::error!("Stream error for download {}: {}", download_id, e);
+                    DownloadEvent::DownloadStarted { filename } => {
+                        destinations.push(filename.clone());
+                        final_filename = Some(filename.clone());
+                        tracing::info!("Download {} started: {}", download_id, filename);
+                    }
+                    DownloadEvent::FormatSelected { format_id: selected } => {
+                        tracing::info!("Download {} selected format: {}", download_id, selected);
+                        format_id = Some(selected.clone());
+                    }
+                    DownloadEvent::FormatFallback { requested, fallback } => {
+                        tracing::warn!(
+                            "Download {} format {} not available, retrying with {}",
+                            download_id,
+                            requested,
+                            fallback
+                        );
+                        max_percent = 0.0;
+                        let _ = Download::update_format_fallback(&pool, &download_id, Some(requested)).await;
+                    }
+                    DownloadEvent::FileRenamed { from, to } => {
+                        tracing::info!("Download {} renamed {} to {}", download_id, from, to);
+                        final_filename = Some(to.clone());
+                    }
+                    DownloadEvent::InfoJsonWritten { filename } => {
+                        info_json_path = Some(filename.clone());
+                    }
+                    DownloadEvent::PostProcessing { status } => {
+                        tracing::info!("Download {} post-processing: {}", download_id, status);
+                        let mut states = download_states.write().await;
+                        states.insert(download_id.clone(), DownloadStateInfo {
+                            status: "processing".to_string(),
+                            percent: 100.0,
+                            total: total_bytes.map(yt_dlp::format_bytes),
+                            total_bytes,
+                            error: Some(status.clone()),
+                            ..Default::default()
+                        });
+                    }
+                    DownloadEvent::Finished { filename, total_bytes, elapsed, average_speed } => {
+                        final_filename = Some(filename.clone());
+                        if total_bytes.is_some() {
+                            reported_bytes = *total_bytes;
+                        }
+                        tracing::info!(
+                            total_bytes = ?total_bytes,
+                            elapsed_secs = ?elapsed.map(|e| e.as_secs_f64()),
+                            average_speed = ?average_speed,
+                            "Download {} finished: {}",
+                            download_id,
+                            filename
+                        );
+                    }
+                    DownloadEvent::Skipped { reason } => {
+                        tracing::info!("Download {} skipped by match filter {}", download_id, reason);
+                        skip_reason = Some(format!("Skipped by the channel's match filter {reason}"));
+                    }
+                    DownloadEvent::FileSizeRejected { reason } => {
+                        tracing::info!("Download {} skipped, file is {}", download_id, reason);
+                        skip_reason = Some(format!("Skipped, file is {reason}"));
+                    }
+                    DownloadEvent::Error { message } => {
+                        tracing::error!("Download {} error: {}", download_id, message);
                         had_error = true;
-                        error_message = Some(e.to_string());
-                        break;
+                        // Known failures read as e.g. "video is age-restricted: ..."
+                        error_message = Some(
+                            yt_dlp::Error::from_message(message).map_or_else(|| message.clone(), |e| e.to_string())
+                        );
                     }
-                    None => break
+                    _ => {}
                 }
             }
+            Some(Err(yt_dlp::Error::Cancelled)) if stop.get() == Some(&Stop::Pause) => {
+                // The .part files stay for yt-dlp to continue on resume
+                tracing::info!("Download {} paused, yt-dlp has exited", download_id);
+                paused = true;
+                break;
+            }
+            Some(Err(yt_dlp::Error::Cancelled)) => {
+                tracing::info!("Download {} cancelled, yt-dlp has exited", download_id);
+                had_error = true;
+                cancelled = true;
+                error_message = Some("Cancelled by user".to_string());
+                for destination in &destinations {
+                    remove_partial_files(std::path::Path::new(destination)).await;
+                }
+                break;
+            }
+            Some(Err(e)) => {
+                tracing::error!("Stream error for download {}: {}", download_id, e);
+                had_error = true;
+                error_message = Some(e.to_string());
+                break;
+            }
+            None => break
+        }
+    }
+
+    if paused {
+        let destination = destinations.last().map(String::as_str);
+        let _ = db::retry_on_busy(|| Download::update_paused(&pool, &download_id, destination)).await;
+        {
+            let mut states = download_states.write().await;
+            states.insert(download_id.clone(), DownloadStateInfo {
+                status: "paused".to_string(),
+                percent: max_percent,
+                total: total_bytes.map(yt_dlp::format_bytes),
+                total_bytes,
+                ..Default::default()
+            });
         }
+        schedule_state_cleanup(download_states, download_id);
+        return Outcome::Paused;
+    }
+
+    if let Some(reason) = skip_reason.filter(|_| !cancelled) {
+        for destination in &destinations {
+            remove_partial_files(std::path::Path::new(destination)).await;
+        }
+        let _ = db::retry_on_busy(|| Download::update_skipped(&pool, &download_id, &reason)).await;
+        {
+            let mut states = download_states.write().await;
+            states.insert(download_id.clone(), DownloadStateInfo {
+                status: "skipped".to_string(),
+                error: Some(reason),
+                ..Default::default()
+            });
+        }
+        schedule_state_cleanup(download_states, download_id);
+        return Outcome::Finished;
     }
 
     if had_error {
         let msg = error_message.unwrap_or_else(|| "Unknown error".to_string());
-        let _ = Download::update_failed(&pool, &download_id, &msg).await;
+        if let Some(delay) = retry_delay_after(&pool, &download_id, &msg, cancelled).await {
+            tracing::warn!("Download {} failed, retrying in {}s: {}", download_id, delay.as_secs(), msg);
+            {
+                let mut states = download_states.write().await;
+                states.insert(download_id.clone(), DownloadStateInfo {
+                    status: "retrying".to_string(),
+                    error: Some(format!("Retrying in {}s: {msg}", delay.as_secs())),
+                    ..Default::default()
+                });
+            }
+            schedule_state_cleanup(download_states, download_id);
+            return Outcome::Retry(delay);
+        }
+        let _ = db::retry_on_busy(|| Download::update_failed(&pool, &download_id, &msg)).await;
+        if !cancelled {
+            metrics.record_failure();
+        }
         {
             let mut states = download_states.write().await;
             states.insert(download_id.clone(), DownloadStateInfo {
                 status: "failed".to_string(),
-                percent: 0.0,
-                speed: None,
-                eta: None,
-                error: Some(msg)
+                error: Some(msg),
+                ..Default::default()
             });
         }
         schedule_state_cleanup(download_states, download_id);
-    } else if let Some(filename) = final_filename {
+    } else if let Some(filename) = final_filename.as_ref().filter(|f| !is_empty_file(f)).cloned() {
+        let file_len = std::fs::metadata(&filename)
+            .map(|m| m.len())
+            .ok()
+            .or(reported_bytes);
         #[allow(clippy::cast_possible_wrap)]
-        let file_size = std::fs::metadata(&filename).map(|m| m.len() as i64).ok();
-        let _ = Download::update_completed(&pool, &download_id, &filename, file_size).await;
-
-        let thumb_filename = save_thumb_alongside(&filename, &video_meta).await;
+        let file_size = file_len.map(|len| len as i64);
 
-        let ffprobe_bin = Settings::get(&pool, "ffprobe_path")
+        let ffprobe_bin = Settings::get_ffprobe_path(&pool)
             .await
-            .ok()
-            .flatten()
-            .filter(|s| !s.is_empty())
-            .unwrap_or_else(|| "ffprobe".to_string());
-        let media_info = nfo::probe_media(&filename, &ffprobe_bin).await;
-
-        let nfo_data = VideoNfo {
-            title: video_meta.title,
-            description: video_meta.description,
-            youtube_id: video_meta.youtube_id,
-            channel_name,
-            upload_date: video_meta.upload_date,
-            duration_seconds: video_meta.duration_seconds,
-            thumb_filename,
-            media_info
+            .unwrap_or_else(|_| "ffprobe".to_string());
+        let probe = nfo::try_probe_media(&filename, &ffprobe_bin).await;
+        let problem = match probe {
+            Ok(Some(ref media_info)) => {
+                let tolerance = Settings::get_integrity_tolerance(&pool)
+                    .await
+                    .unwrap_or(DEFAULT_INTEGRITY_TOLERANCE);
+                let expected = video_meta
+                    .duration_seconds
+                    .filter(|_| matches!(video_meta.live_status.as_deref(), None | Some("not_live")));
+                integrity_problem(media_info, expected, tolerance)
+            }
+            // A misconfigured ffprobe or an unknown codec says nothing about the file
+            Ok(None) => {
+                tracing::warn!("Skipping integrity check of {}, ffprobe ({}) could not read it", filename, ffprobe_bin);
+                None
+            }
+            Err(ref e) => {
+                tracing::warn!("Skipping integrity check of {}, ffprobe ({}) could not run: {}", filename, ffprobe_bin, e);
+                None
+            }
         };
+        if let Some(problem) = problem {
+            tracing::warn!("Download {} failed the integrity check: {}", download_id, problem);
+            // yt-dlp would skip a retry as "already downloaded" while the file exists
+            if let Err(e) = tokio::fs::remove_file(&filename).await {
+                tracing::warn!("Failed to remove corrupt download {}: {}", filename, e);
+            }
+            fail_download(&pool, download_states, download_id, problem, &metrics).await;
+            return Outcome::Finished;
+        }
+        let _ = db::retry_on_busy(|| {
+            Download::update_completed(&pool, &download_id, &filename, file_size)
+        })
+        .await;
+        metrics.record_completed(file_len);
+        let media_info = probe.ok().flatten();
+
+        if let Some(ref upload_date) = video_meta.upload_date {
+            if Settings::get_set_mtime(&pool).await.unwrap_or(true) {
+                if let Err(e) = set_upload_mtime(&filename, upload_date) {
+                    tracing::warn!("Failed to set modification time of {}: {}", filename, e);
+                }
+            }
+        }
+
+        let thumbnail_format = Settings::get_thumbnail_format(&pool).await.unwrap_or(THUMBNAIL_FORMATS[0]);
+        let thumb_filename = save_thumb_alongside(&filename, thumbnail_format, &video_meta).await;
+
+        let video_stream = media_info.as_ref().and_then(|m| m.video.as_ref());
+        let _ = Download::update_format(
+            &pool,
+            &download_id,
+            format_id.as_deref(),
+            video_stream.map(|v| v.width),
+            video_stream.map(|v| v.height)
+        )
+        .await;
+
+        let mut nfo_data = base_nfo(&pool, video_meta, channel_name, thumb_filename, media_info).await;
+        ingest_info_json(&pool, &mut nfo_data, &filename, info_json_path).await;
         if let Err(e) = nfo::write_nfo(&filename, &nfo_data).await {
             tracing::warn!("Failed to write NFO for {}: {}", download_id, e);
         }
@@ -308,26 +899,474 @@ async fn process_download(
             states.insert(download_id.clone(), DownloadStateInfo {
                 status: "completed".to_string(),
                 percent: 100.0,
-                speed: None,
-                eta: None,
-                error: None
+                ..Default::default()
             });
         }
         schedule_state_cleanup(download_states, download_id);
     } else {
-        let _ = Download::update_failed(&pool, &download_id, "Download completed but no file found")
-            .await;
-        {
-            let mut states = download_states.write().await;
-            states.insert(download_id.clone(), DownloadStateInfo {
-                status: "failed".to_string(),
-                percent: 0.0,
-                speed: None,
-                eta: None,
-                error: Some("No file found".to_string())
-            });
+        let message = if final_filename.is_some() {
+            "Download completed but the file was empty"
+        } else {
+            "Download completed but no file found"
+        };
+        fail_download(&pool, download_states, download_id, message.to_string(), &metrics).await;
+    }
+    Outcome::Finished
+}
+
+async fn fail_download(
+    pool: &DbPool,
+    download_states: Arc<RwLock<HashMap<String, DownloadStateInfo>>>,
+    download_id: String,
+    message: String,
+    metrics: &Metrics
+) {
+    let _ = db::retry_on_busy(|| Download::update_failed(pool, &download_id, &message)).await;
+    metrics.record_failure();
+    {
+        let mut states = download_states.write().await;
+        states.insert(download_id.clone(), DownloadStateInfo {
+            status: "failed".to_string(),
+            error: Some(message),
+            ..Default::default()
+        });
+    }
+    schedule_state_cleanup(download_states, download_id);
+}
+
+/// Server errors and network trouble that usually pass, for messages
+/// [`yt_dlp::Error::from_message`] does not classify.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "http error 500",
+    "http error 502",
+    "http error 503",
+    "http error 504",
+    "connection reset",
+    "connection refused",
+    "connection aborted",
+    "remote end closed connection",
+    "timed out",
+    "temporary failure in name resolution",
+    "network is unreachable",
+    "incompleteread",
+    "unable to download video data"
+];
+
+/// Automatic retries never wait longer than this.
+const MAX_RETRY_DELAY_SECS: u64 = 3600;
+
+/// Rate limits are transient and private, removed or restricted videos are
+/// not; anything else is retried only when it looks like a network error.
+fn is_transient_error(message: &str) -> bool {
+    if let Some(error) = yt_dlp::Error::from_message(message) {
+        return error.is_transient();
+    }
+    let message = message.to_lowercase();
+    TRANSIENT_ERRORS.iter().any(|pattern| message.contains(pattern))
+}
+
+/// `base_secs` doubled for each failure after the first, capped at an hour.
+fn retry_delay(base_secs: u64, failures: u32) -> std::time::Duration {
+    let factor = 1u64 << failures.saturating_sub(1).min(16);
+    std::time::Duration::from_secs(base_secs.saturating_mul(factor).min(MAX_RETRY_DELAY_SECS))
+}
+
+/// Counts a failed attempt. A transient error with attempts left puts the
+/// row back to pending and returns how long to wait before retrying.
+async fn retry_delay_after(
+    pool: &DbPool,
+    download_id: &str,
+    message: &str,
+    cancelled: bool
+) -> Option<std::time::Duration> {
+    if cancelled {
+        return None;
+    }
+    let failures = match db::retry_on_busy(|| Download::record_failed_attempt(pool, download_id)).await {
+        Ok(failures) => u32::try_from(failures).unwrap_or(u32::MAX),
+        Err(e) => {
+            tracing::warn!("Failed to count the attempt of download {}: {}", download_id, e);
+            return None;
         }
-        schedule_state_cleanup(download_states, download_id);
+    };
+    if !is_transient_error(message) {
+        return None;
+    }
+    let max_attempts = Settings::get_retry_max_attempts(pool)
+        .await
+        .unwrap_or(DEFAULT_RETRY_MAX_ATTEMPTS);
+    if failures >= max_attempts {
+        return None;
+    }
+    let base = Settings::get_retry_base_delay(pool).await.unwrap_or(DEFAULT_RETRY_BASE_DELAY);
+    let delay = retry_delay(base, failures);
+    let note = format!("Retrying in {}s after attempt {failures}: {message}", delay.as_secs());
+    db::retry_on_busy(|| Download::update_retrying(pool, download_id, &note))
+        .await
+        .ok()
+        .map(|()| delay)
+}
+
+/// Seconds of slack so rounding and container padding never count as truncation.
+const INTEGRITY_MIN_SLACK_SECS: i64 = 5;
+
+/// Why a finished file looks truncated: it is more than `tolerance_percent`
+/// shorter than the `expected_seconds` yt-dlp reported. A tolerance of 0
+/// turns the check off.
+fn integrity_problem(
+    media_info: &nfo::MediaInfo,
+    expected_seconds: Option<i64>,
+    tolerance_percent: u32
+) -> Option<String> {
+    if tolerance_percent == 0 {
+        return None;
+    }
+    let expected = expected_seconds.filter(|&s| s > 0)?;
+    let actual = media_info.duration_seconds?;
+    let slack = (expected * i64::from(tolerance_percent) / 100).max(INTEGRITY_MIN_SLACK_SECS);
+    (actual + slack < expected)
+        .then(|| format!("Corrupt or truncated file: {actual}s of the expected {expected}s"))
+}
+
+/// The channel folder is always the home path and the template stays
+/// relative: yt-dlp ignores `--paths` for an absolute template, and
+/// `--trim-filenames` would count the folder against the limit. With a temp
+/// directory the finished file is moved home and reported by `[MoveFiles]`.
+fn with_output_location(
+    options: DownloadOptions,
+    download_path: &str,
+    filename_template: &str,
+    temp_dir: Option<String>
+) -> DownloadOptions {
+    let options = options
+        .path(PathType::Home, download_path)
+        .output_template(filename_template);
+    match temp_dir {
+        Some(temp_dir) => options.path(PathType::Temp, temp_dir),
+        None => options
+    }
+}
+
+/// Live streams are recorded from their start; upcoming ones are polled every
+/// minute until they begin instead of failing straight away.
+fn with_live_options(options: DownloadOptions, live_status: Option<&str>) -> DownloadOptions {
+    match live_status {
+        Some("is_live") => options.live_from_start(true),
+        Some("is_upcoming") => options.live_from_start(true).wait_for_video(LIVE_WAIT_INTERVAL),
+        _ => options
+    }
+}
+
+/// Dates the file by a `YYYYMMDD` upload date (midnight UTC). Any other date
+/// format leaves the file alone.
+fn set_upload_mtime(path: &str, upload_date: &str) -> std::io::Result<()> {
+    let Some(midnight) = chrono::NaiveDate::parse_from_str(upload_date, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    else {
+        return Ok(());
+    };
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(midnight.and_utc().into())
+}
+
+/// The name shared by a destination and its leftovers, without the extension
+/// or the `.fNNN` format suffix of a part that was going to be merged.
+fn partial_stem(file_name: &str) -> &str {
+    let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+    match stem.rsplit_once('.') {
+        Some((base, format)) if is_format_suffix(format) => base,
+        _ => stem
+    }
+}
+
+/// `f137` or `fhls-1080p`; the digit keeps `fr` subtitles out.
+fn is_format_suffix(s: &str) -> bool {
+    s.strip_prefix('f').is_some_and(|id| {
+        id.chars().any(|c| c.is_ascii_digit()) && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// Whether `file_name` is something yt-dlp leaves behind for `stem` while a
+/// download is in flight: `.part`/`.ytdl` files, fragments, the merger's
+/// `.temp` output and the unmerged `.fNNN` streams.
+fn is_partial_file(file_name: &str, stem: &str) -> bool {
+    let Some(rest) = file_name.strip_prefix(stem).and_then(|r| r.strip_prefix('.')) else {
+        return false;
+    };
+    let extension = std::path::Path::new(rest).extension().and_then(|e| e.to_str());
+    if matches!(extension, Some("part" | "ytdl")) || rest.contains(".part-Frag") {
+        return true;
+    }
+    // `temp.mp4` from the merger, `f137.mp4` streams waiting to be merged
+    rest.split_once('.')
+        .is_some_and(|(first, _)| first == "temp" || is_format_suffix(first))
+}
+
+/// Removes the partial files of a cancelled download whose destination was
+/// `destination`. Only runs once yt-dlp has exited, so nothing recreates them.
+pub async fn remove_partial_files(destination: &std::path::Path) {
+    let (Some(dir), Some(file_name)) = (destination.parent(), destination.file_name().and_then(|n| n.to_str())) else {
+        return;
+    };
+    let stem = partial_stem(file_name);
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if is_partial_file(name, stem) {
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(()) => tracing::debug!("Removed partial file {}", entry.path().display()),
+                Err(e) => tracing::warn!("Failed to remove partial file {}: {}", entry.path().display(), e)
+            }
+        }
+    }
+}
+
+/// A stream that ended before any data arrived leaves a zero-byte file
+/// behind; treat it like a missing one rather than a completed download.
+fn is_empty_file(filename: &str) -> bool {
+    let empty = std::fs::metadata(filename).is_ok_and(|m| m.len() == 0);
+    if empty {
+        tracing::warn!("Removing empty download {}", filename);
+        let _ = std::fs::remove_file(filename);
+    }
+    empty
+}
+
+/// Extracts the audio track instead of keeping the video. Remuxing is skipped
+/// in this mode since the video container is discarded anyway.
+fn with_audio_only(
+    options: DownloadOptions,
+    format: String,
+    quality: Option<String>
+) -> DownloadOptions {
+    let options = options.extract_audio(true).audio_format(format);
+    match quality {
+        Some(quality) => options.audio_quality(quality),
+        None => options
+    }
+}
+
+/// Normalizes the container by remuxing. With `recode_fallback` only
+/// `--recode-video` is passed, since yt-dlp does not chain the two: it
+/// leaves files already in that container alone and re-encodes the rest.
+fn with_container(options: DownloadOptions, format: String, recode_fallback: bool) -> DownloadOptions {
+    if recode_fallback {
+        options.recode_video(format)
+    } else {
+        options.remux_video(format)
+    }
+}
+
+/// `audio_only` is the channel's mode; the `audio_format` setting makes
+/// every download audio-only regardless.
+async fn build_download_options(pool: &DbPool, audio_only: bool) -> DownloadOptions {
+    let thumbnail_format = Settings::get_thumbnail_format(pool).await.unwrap_or(THUMBNAIL_FORMATS[0]);
+    let mut options = DownloadOptions::default()
+        .write_info_json(true)
+        .write_thumbnail(true)
+        .convert_thumbnails(thumbnail_format)
+        .embed_thumbnail(Settings::get_bool(pool, "embed_thumbnail").await.unwrap_or(false))
+        .set_mtime(Settings::get_set_mtime(pool).await.unwrap_or(true))
+        .continue_partial(!Settings::get_bool(pool, "no_continue").await.unwrap_or(false))
+        .estimate_size(Settings::get_bool(pool, "estimate_size").await.unwrap_or(false));
+
+    let audio_format = match Settings::get_audio_format(pool).await.ok().flatten() {
+        None if audio_only => Settings::get_audio_only_format(pool).await.ok(),
+        format => format
+    };
+    if let Some(format) = audio_format {
+        let quality = Settings::get_audio_quality(pool).await.ok().flatten();
+        options = with_audio_only(options, format, quality);
+        // Audio-only channels always get the thumbnail as cover art
+        if audio_only {
+            options = options.embed_thumbnail(true);
+        }
+    } else {
+        if let Ok(Some(height)) = Settings::get_max_height(pool).await {
+            options = options.format(OutputFormat::MaxHeight(height));
+        }
+        if let Ok(Some(format)) = Settings::get_remux_video(pool).await {
+            let recode = Settings::get_bool(pool, "recode_video_fallback").await.unwrap_or(false);
+            options = with_container(options, format, recode);
+        }
+    }
+
+    let fragments = Settings::get_concurrent_fragments(pool)
+        .await
+        .unwrap_or(crate::models::DEFAULT_CONCURRENT_FRAGMENTS);
+    options = options.concurrent_fragments(fragments);
+
+    if let Ok(schedule) = Settings::get_rate_schedule(pool).await {
+        if let Some(rate) = schedule.limit_at(chrono::Local::now().time()) {
+            options = options.rate_limit(rate.trim());
+        }
+    }
+
+    if let Ok(Some(rate)) = Settings::get_throttled_rate(pool).await {
+        options = options.throttled_rate(rate.trim());
+    }
+
+    if let Ok(Some(size)) = Settings::get_max_filesize(pool).await {
+        options = options.max_filesize(size.trim());
+    }
+
+    if Settings::get_download_subtitles(pool).await.unwrap_or(false) {
+        let langs = Settings::get_subtitle_langs(pool).await.unwrap_or_default();
+        let include_auto = Settings::get_include_auto_subs(pool).await.unwrap_or(false);
+        let format = Settings::get_subtitle_format(pool).await.ok().flatten();
+        options = with_subtitles(options, langs, include_auto, format);
+    }
+
+    options
+}
+
+/// The NFO from the database row; yt-dlp's metadata is applied on top.
+async fn base_nfo(
+    pool: &DbPool,
+    meta: VideoMeta,
+    channel_name: String,
+    thumb_filename: Option<String>,
+    media_info: Option<nfo::MediaInfo>
+) -> VideoNfo {
+    let scheme = Settings::get_episode_scheme(pool).await.unwrap_or_default();
+    let episode = episode_numbering(pool, scheme, &meta).await;
+    VideoNfo {
+        title: meta.title,
+        description: meta.description,
+        youtube_id: meta.youtube_id,
+        channel_name,
+        upload_date: meta.upload_date,
+        upload_timestamp: None,
+        director: None,
+        duration_seconds: meta.duration_seconds,
+        tags: meta.tags,
+        categories: meta.categories,
+        episode,
+        thumb_filename,
+        media_info
+    }
+}
+
+/// `(season, episode)`, numbering videos in upload order within the channel,
+/// or within the year for [`EpisodeScheme::Year`], so the numbers do not
+/// shift when a sync lists the channel differently.
+async fn episode_numbering(pool: &DbPool, scheme: EpisodeScheme, meta: &VideoMeta) -> Option<(i64, i64)> {
+    let season = scheme.season(meta.upload_date.as_deref())?;
+    let same_year = scheme == EpisodeScheme::Year;
+    let episode = Video::chronological_position(pool, &meta.video_id, same_year)
+        .await
+        .ok()
+        .flatten()?;
+    Some((season, episode))
+}
+
+/// Rewrites a completed download's NFO and `-thumb` sidecar from freshly
+/// fetched metadata. The media file is only probed, never modified; when the
+/// thumbnail cannot be fetched the existing sidecar is kept.
+pub async fn refresh_sidecars(
+    pool: &DbPool,
+    video_file_path: &str,
+    meta: VideoMeta,
+    channel_name: String,
+    info: &yt_dlp::VideoInfo
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let existing_thumb = THUMBNAIL_FORMATS
+        .into_iter()
+        .filter_map(|format| thumb_paths(video_file_path, format).map(|(_, thumb)| thumb))
+        .find(|thumb| thumb.exists());
+    let thumb_path = existing_thumb
+        .clone()
+        .or_else(|| thumb_paths(video_file_path, THUMBNAIL_FORMATS[0]).map(|(_, thumb)| thumb));
+    let mut thumb_filename = existing_thumb.map(|thumb| thumb.to_string_lossy().to_string());
+    if let (Some(url), Some(thumb_path)) = (meta.thumbnail_url.as_deref(), thumb_path) {
+        let thumb_path = thumb_path.to_string_lossy().to_string();
+        match thumbnail::download_image(url, &thumb_path).await {
+            Ok(()) => thumb_filename = Some(thumb_path),
+            Err(e) => tracing::warn!("Failed to refresh thumbnail {}: {}", thumb_path, e)
+        }
+    }
+
+    let ffprobe_bin = Settings::get_ffprobe_path(pool)
+        .await
+        .unwrap_or_else(|_| "ffprobe".to_string());
+    let media_info = nfo::probe_media(video_file_path, &ffprobe_bin).await;
+
+    let mut nfo_data = base_nfo(pool, meta, channel_name, thumb_filename, media_info).await;
+    nfo_data.apply_info_json(info);
+    nfo_data.title.clone_from(&info.title);
+    nfo::write_nfo(video_file_path, &nfo_data).await?;
+    Ok(())
+}
+
+async fn ingest_info_json(
+    pool: &DbPool,
+    nfo_data: &mut VideoNfo,
+    video_file_path: &str,
+    reported_path: Option<String>
+) {
+    let reported = reported_path
+        .map(PathBuf::from)
+        .filter(|p| p.exists());
+    let found = match reported {
+        Some(path) => Some(path),
+        None => nfo::find_info_json(video_file_path, &nfo_data.youtube_id).await
+    };
+    let Some(path) = found else {
+        tracing::debug!("No info.json found for {}", video_file_path);
+        return;
+    };
+
+    if let Some(info) = nfo::read_info_json(&path).await {
+        nfo_data.apply_info_json(&info);
+    }
+
+    if Settings::get_bool(pool, "keep_info_json").await.unwrap_or(false) {
+        keep_as_sidecar(&path, video_file_path).await;
+    } else if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!("Failed to remove {}: {}", path.display(), e);
+    }
+}
+
+/// yt-dlp names the info.json after the pre-merge output, e.g.
+/// `Video.f137.info.json`; a kept one is renamed to `<stem>.info.json` so it
+/// sits next to the final media file like the NFO.
+async fn keep_as_sidecar(path: &std::path::Path, video_file_path: &str) {
+    let sidecar = info_json_sidecar(video_file_path);
+    if path == sidecar {
+        return;
+    }
+    match tokio::fs::rename(path, &sidecar).await {
+        Ok(()) => tracing::debug!("Kept info.json as {}", sidecar.display()),
+        Err(e) => tracing::warn!("Failed to rename {} to {}: {}", path.display(), sidecar.display(), e)
+    }
+}
+
+fn info_json_sidecar(video_file_path: &str) -> PathBuf {
+    std::path::Path::new(video_file_path).with_extension("info.json")
+}
+
+/// Subtitle files are written next to the video so media servers pick them up.
+fn with_subtitles(
+    options: DownloadOptions,
+    langs: Vec<String>,
+    include_auto: bool,
+    format: Option<String>
+) -> DownloadOptions {
+    let options = options
+        .write_subtitles(true)
+        .write_auto_subtitles(include_auto)
+        .subtitles_langs(langs);
+    match format {
+        Some(format) => options.convert_subtitles(format),
+        None => options
     }
 }
 
@@ -342,26 +1381,535 @@ fn schedule_state_cleanup(
     });
 }
 
-async fn save_thumb_alongside(video_file_path: &str, meta: &VideoMeta) -> Option<String> {
-    let thumb_url = format!(
-        "https://i.ytimg.com/vi/{}/maxresdefault.jpg",
-        meta.youtube_id
-    );
+/// Moves the thumbnail yt-dlp wrote and converted (`<stem>.jpg`) to
+/// Jellyfin's `<stem>-thumb.jpg`. If the extractor had none, the candidates
+/// from [`thumb_candidates`] are tried in order and saved as jpg.
+async fn save_thumb_alongside(video_file_path: &str, format: &str, meta: &VideoMeta) -> Option<String> {
+    let (written, thumb_path) = thumb_paths(video_file_path, format)?;
+
+    match tokio::fs::rename(&written, &thumb_path).await {
+        Ok(()) => {
+            tracing::debug!("Saved thumbnail alongside video: {}", thumb_path.display());
+            return Some(thumb_path.to_string_lossy().to_string());
+        }
+        Err(e) => tracing::debug!("No thumbnail written by yt-dlp at {}: {}", written.display(), e)
+    }
+
+    let (_, thumb_path) = thumb_paths(video_file_path, "jpg")?;
+    let thumb_path_str = thumb_path.to_string_lossy().to_string();
+    for url in thumb_candidates(meta) {
+        if !url.starts_with("http") {
+            if let Some(saved) = copy_cached_thumbnail(&url, &thumb_path).await {
+                return Some(saved);
+            }
+            continue;
+        }
+        match thumbnail::download_image(&url, &thumb_path_str).await {
+            Ok(()) => return Some(thumb_path_str),
+            Err(e) if e.is::<thumbnail::HttpStatusError>() => tracing::debug!("{}: {}", url, e),
+            Err(e) => tracing::warn!("Failed to save thumbnail alongside video from {}: {}", url, e)
+        }
+    }
+    None
+}
+
+/// `YouTube`'s fixed-size thumbnails from largest down, since `maxresdefault`
+/// is missing for many older or less popular videos, then the thumbnail
+/// stored at the last sync.
+fn thumb_candidates(meta: &VideoMeta) -> Vec<String> {
+    let mut urls: Vec<String> = if meta.site.is_none() {
+        ["maxresdefault", "hqdefault", "mqdefault"]
+            .iter()
+            .map(|size| format!("https://i.ytimg.com/vi/{}/{size}.jpg", meta.youtube_id))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    urls.extend(meta.thumbnail_url.clone());
+    urls
+}
+
+/// The thumbnail yt-dlp writes next to the media and its `-thumb` sidecar name.
+fn thumb_paths(video_file_path: &str, format: &str) -> Option<(PathBuf, PathBuf)> {
     let video_path = std::path::Path::new(video_file_path);
     let stem = video_path.file_stem()?.to_string_lossy();
-    let parent = video_path.parent()?;
-    let thumb_name = format!("{stem}-thumb.jpg");
-    let thumb_path = parent.join(&thumb_name);
-    let thumb_path_str = thumb_path.to_string_lossy().to_string();
+    let thumb_path = video_path.parent()?.join(format!("{stem}-thumb.{format}"));
+    Some((video_path.with_extension(format), thumb_path))
+}
 
-    match thumbnail::download_image(&thumb_url, &thumb_path_str).await {
-        Ok(()) => {
-            tracing::debug!("Saved thumbnail alongside video: {}", thumb_path_str);
-            Some(thumb_path_str)
+async fn load_filename_mode(pool: &DbPool) -> FilenameMode {
+    FilenameMode {
+        restrict: Settings::get_bool(pool, "restrict_filenames").await.unwrap_or(false),
+        windows: Settings::get_bool(pool, "windows_filenames").await.unwrap_or(false)
+    }
+}
+
+/// Joins the sanitized `components` onto the download path. A component that
+/// sanitizes to nothing, `.` or `..` becomes `_` so it cannot leave the path.
+fn download_dir<'a>(
+    base_download_path: &str,
+    components: impl IntoIterator<Item = &'a str>,
+    mode: FilenameMode
+) -> String {
+    components.into_iter().fold(base_download_path.to_string(), |path, component| {
+        let component = sanitize_filename(component, mode);
+        if matches!(component.as_str(), "" | "." | "..") {
+            format!("{path}/_")
+        } else {
+            format!("{path}/{component}")
         }
+    })
+}
+
+/// Folder a channel's downloads are written to, or `None` when the flat
+/// layout puts them straight into the download path.
+pub async fn channel_download_dir(pool: &DbPool, channel_name: &str) -> Result<Option<String>, sqlx::Error> {
+    if Settings::get_directory_layout(pool).await? == DirectoryLayout::Flat {
+        return Ok(None);
+    }
+    let base_download_path = Settings::get_download_path(pool).await?;
+    let mode = load_filename_mode(pool).await;
+    Ok(Some(download_dir(&base_download_path, [channel_name], mode)))
+}
+
+/// Reuses the image fetched from yt-dlp's metadata during channel sync.
+async fn copy_cached_thumbnail(web_path: &str, dest: &std::path::Path) -> Option<String> {
+    let source = web_path.strip_prefix('/').unwrap_or(web_path);
+    match tokio::fs::copy(source, dest).await {
+        Ok(_) => Some(dest.to_string_lossy().to_string()),
         Err(e) => {
-            tracing::warn!("Failed to save thumbnail alongside video: {}", e);
+            tracing::warn!("Failed to copy cached thumbnail {}: {}", source, e);
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESTRICT: FilenameMode = FilenameMode { restrict: true, windows: false };
+    const WINDOWS: FilenameMode = FilenameMode { restrict: false, windows: true };
+
+    #[test]
+    fn test_sanitize_filename_default_mode() {
+        assert_eq!(sanitize_filename("AC/DC: Live ", FilenameMode::default()), "AC_DC_ Live");
+        assert_eq!(sanitize_filename("Café 🦀", FilenameMode::default()), "Café 🦀");
+    }
+
+    #[test]
+    fn test_sanitize_filename_restrict_unicode() {
+        assert_eq!(sanitize_filename("Café Crème", RESTRICT), "Cafe_Creme");
+        assert_eq!(sanitize_filename("Straße – Teil 2", RESTRICT), "Strasse_Teil_2");
+        assert_eq!(sanitize_filename("東京", RESTRICT), "_");
+        assert_eq!(sanitize_filename("Talk: Q&A (2024)", RESTRICT), "Talk_-_Q_A_2024");
+        assert_eq!(sanitize_filename("Live at 12:30", RESTRICT), "Live_at_12_30");
+    }
+
+    #[test]
+    fn test_sanitize_filename_restrict_emoji() {
+        assert_eq!(sanitize_filename("Rust 🦀 Talks", RESTRICT), "Rust_Talks");
+        assert_eq!(sanitize_filename("👨\u{200D}💻 Coding", RESTRICT), "Coding");
+        assert_eq!(sanitize_filename("❤\u{FE0F}", RESTRICT), "_");
+    }
+
+    #[test]
+    fn test_sanitize_filename_trailing_dot() {
+        assert_eq!(sanitize_filename("Vol. 2...", RESTRICT), "Vol._2...");
+        assert_eq!(sanitize_filename("Vol. 2...", WINDOWS), "Vol. 2..#");
+        assert_eq!(sanitize_filename("...hidden", RESTRICT), "hidden");
+        assert_eq!(
+            sanitize_filename("Vol. 2.", FilenameMode { restrict: true, windows: true }),
+            "Vol._2#"
+        );
+    }
+
+    #[test]
+    fn test_set_upload_mtime() {
+        let path = std::env::temp_dir().join(format!("toobarr-mtime-{}.mp4", uuid7::uuid7()));
+        std::fs::write(&path, b"media").unwrap();
+        let path_str = path.to_string_lossy();
+
+        set_upload_mtime(&path_str, "20240102").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let expected = chrono::DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z").unwrap();
+        assert_eq!(modified, std::time::SystemTime::from(expected));
+
+        set_upload_mtime(&path_str, "NA").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_remove_partial_files() {
+        let dir = std::env::temp_dir().join(format!("toobarr-partial-test-{}", uuid7::uuid7()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let leftovers = [
+            "Video [abc].f137.mp4.part",
+            "Video [abc].f137.mp4.ytdl",
+            "Video [abc].f251.webm",
+            "Video [abc].f137.mp4.part-Frag3",
+            "Video [abc].temp.mp4"
+        ];
+        let kept = ["Video [abc].fr.vtt", "Video [abc].info.json", "Video [abcd].mp4.part", "Other.mp4.part"];
+        for name in leftovers.iter().chain(&kept) {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+
+        remove_partial_files(&dir.join("Video [abc].f137.mp4")).await;
+
+        for name in leftovers {
+            assert!(!dir.join(name).exists(), "{name} should be removed");
+        }
+        for name in kept {
+            assert!(dir.join(name).exists(), "{name} should be kept");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error("ERROR: unable to download video data: HTTP Error 429: Too Many Requests"));
+        assert!(is_transient_error("ERROR: [Errno 104] Connection reset by peer"));
+        assert!(is_transient_error("ERROR: The read operation timed out"));
+        assert!(!is_transient_error("ERROR: [youtube] abc: Private video. Sign in if you've been granted access"));
+        assert!(!is_transient_error("ERROR: [youtube] abc: Video unavailable. This video has been removed by the uploader"));
+        assert!(!is_transient_error("Skipped by the channel's match filter"));
+        assert!(!is_transient_error("[youtube] abc: Sign in to confirm your age"));
+        assert!(!is_transient_error("[youtube] abc: Join this channel to get access to members-only content"));
+        assert!(!is_transient_error("[youtube] abc: Requested format is not available"));
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_an_hour() {
+        assert_eq!(retry_delay(30, 1).as_secs(), 30);
+        assert_eq!(retry_delay(30, 2).as_secs(), 60);
+        assert_eq!(retry_delay(30, 3).as_secs(), 120);
+        assert_eq!(retry_delay(30, 10).as_secs(), 3600);
+        assert_eq!(retry_delay(30, u32::MAX).as_secs(), 3600);
+    }
+
+    #[test]
+    fn test_integrity_problem() {
+        let probed = |duration_seconds| nfo::MediaInfo { video: None, audio: None, duration_seconds };
+        assert_eq!(integrity_problem(&probed(Some(598)), Some(600), 10), None);
+        assert_eq!(integrity_problem(&probed(Some(545)), Some(600), 10), None);
+        assert_eq!(
+            integrity_problem(&probed(Some(120)), Some(600), 10).as_deref(),
+            Some("Corrupt or truncated file: 120s of the expected 600s")
+        );
+        // Short videos get a few seconds of slack
+        assert_eq!(integrity_problem(&probed(Some(27)), Some(30), 1), None);
+        assert_eq!(integrity_problem(&probed(None), Some(600), 10), None);
+        assert_eq!(integrity_problem(&probed(Some(10)), None, 10), None);
+        assert_eq!(integrity_problem(&probed(Some(10)), Some(600), 0), None);
+    }
+
+    #[test]
+    fn test_download_dir() {
+        let mode = FilenameMode::default();
+        assert_eq!(download_dir("/media", ["Chan: One"], mode), "/media/Chan_ One");
+        assert_eq!(download_dir("/media", ["Chan", "2024"], mode), "/media/Chan/2024");
+        assert_eq!(download_dir("/media", [], mode), "/media");
+        assert_eq!(download_dir("/media", [".."], mode), "/media/_");
+        assert_eq!(download_dir("/media", ["  "], mode), "/media/_");
+    }
+
+    #[test]
+    fn test_with_subtitles_sets_langs() {
+        let options = with_subtitles(
+            DownloadOptions::default(),
+            vec!["en".to_string(), "de".to_string()],
+            false,
+            None
+        );
+        assert!(options.write_subtitles);
+        assert!(!options.write_auto_subtitles);
+        assert_eq!(options.subtitles_langs, vec!["en", "de"]);
+        assert_eq!(options.convert_subtitles, None);
+    }
+
+    #[test]
+    fn test_with_subtitles_includes_auto_subs() {
+        let options = with_subtitles(DownloadOptions::default(), vec!["en".to_string()], true, None);
+        assert!(options.write_subtitles);
+        assert!(options.write_auto_subtitles);
+    }
+
+    #[test]
+    fn test_with_subtitles_converts_format() {
+        let options = with_subtitles(
+            DownloadOptions::default(),
+            vec!["en".to_string()],
+            false,
+            Some("srt".to_string())
+        );
+        assert_eq!(options.convert_subtitles.as_deref(), Some("srt"));
+    }
+
+    #[test]
+    fn test_with_audio_only() {
+        let options =
+            with_audio_only(DownloadOptions::default(), "m4a".to_string(), Some("128K".to_string()));
+        assert!(options.extract_audio);
+        assert_eq!(options.audio_format.as_deref(), Some("m4a"));
+        assert_eq!(options.audio_quality.as_deref(), Some("128K"));
+
+        let options = with_audio_only(DownloadOptions::default(), "mp3".to_string(), None);
+        assert_eq!(options.audio_quality, None);
+    }
+
+    #[test]
+    fn test_info_json_sidecar() {
+        assert_eq!(
+            info_json_sidecar("/downloads/Chan/Video [abc123].mkv"),
+            PathBuf::from("/downloads/Chan/Video [abc123].info.json")
+        );
+        assert_eq!(
+            info_json_sidecar("/downloads/Chan/Talk v1.5 [abc123].m4a"),
+            PathBuf::from("/downloads/Chan/Talk v1.5 [abc123].info.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keep_as_sidecar_renames_pre_merge_info_json() {
+        let dir = std::env::temp_dir().join(format!("toobarr-info-json-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let pre_merge = dir.join("Video.f137.info.json");
+        tokio::fs::write(&pre_merge, r#"{"id": "abc"}"#).await.unwrap();
+        let video = dir.join("Video.mkv").to_string_lossy().to_string();
+
+        keep_as_sidecar(&pre_merge, &video).await;
+        assert!(!pre_merge.exists());
+        assert!(dir.join("Video.info.json").exists());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_thumb_alongside_uses_written_thumbnail() {
+        let dir = std::env::temp_dir().join(format!("toobarr-thumb-test-{}", uuid7::uuid7()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("Video [abc].jpg"), b"jpeg").await.unwrap();
+        let video = dir.join("Video [abc].mkv").to_string_lossy().to_string();
+        let meta = VideoMeta {
+            youtube_id: "abc".to_string(),
+            site: None,
+            title: "Video".to_string(),
+            description: None,
+            duration_seconds: None,
+            upload_date: None,
+            thumbnail_url: None,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            live_status: None,
+            video_id: String::new()
+        };
+
+        let saved = save_thumb_alongside(&video, "jpg", &meta).await;
+        let thumb = dir.join("Video [abc]-thumb.jpg");
+        assert_eq!(saved.as_deref(), Some(thumb.to_string_lossy().as_ref()));
+        assert!(!dir.join("Video [abc].jpg").exists());
+        assert_eq!(tokio::fs::read(&thumb).await.unwrap(), b"jpeg");
+
+        tokio::fs::write(dir.join("Video [abc].png"), b"png").await.unwrap();
+        let saved = save_thumb_alongside(&video, "png", &meta).await;
+        let thumb = dir.join("Video [abc]-thumb.png");
+        assert_eq!(saved.as_deref(), Some(thumb.to_string_lossy().as_ref()));
+        assert_eq!(tokio::fs::read(&thumb).await.unwrap(), b"png");
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_thumb_candidates() {
+        let mut meta = VideoMeta {
+            youtube_id: "dQw4w9WgXcQ".to_string(),
+            site: None,
+            title: "Video".to_string(),
+            description: None,
+            duration_seconds: None,
+            upload_date: None,
+            thumbnail_url: Some("/static/thumbnails/videos/dQw4w9WgXcQ.jpg".to_string()),
+            tags: Vec::new(),
+            categories: Vec::new(),
+            live_status: None,
+            video_id: String::new()
+        };
+        assert_eq!(thumb_candidates(&meta), vec![
+            "https://i.ytimg.com/vi/dQw4w9WgXcQ/maxresdefault.jpg",
+            "https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg",
+            "https://i.ytimg.com/vi/dQw4w9WgXcQ/mqdefault.jpg",
+            "/static/thumbnails/videos/dQw4w9WgXcQ.jpg"
+        ]);
+
+        meta.youtube_id = "123456789".to_string();
+        meta.site = Some("vimeo".to_string());
+        meta.thumbnail_url = Some("https://i.vimeocdn.com/video/1.jpg".to_string());
+        assert_eq!(thumb_candidates(&meta), vec!["https://i.vimeocdn.com/video/1.jpg"]);
+
+        // Ids of YouTube's shape from other sites get no ytimg URLs either
+        meta.youtube_id = "x8mK2pQ_a-Z".to_string();
+        assert_eq!(thumb_candidates(&meta), vec!["https://i.vimeocdn.com/video/1.jpg"]);
+
+        meta.thumbnail_url = None;
+        assert!(thumb_candidates(&meta).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_applies_rate_limit() {
+        let pool = crate::db::test_pool().await;
+        assert_eq!(build_download_options(&pool, false).await.rate_limit, None);
+
+        Settings::set(&pool, "rate_limit", "2M").await.unwrap();
+        assert_eq!(build_download_options(&pool, false).await.rate_limit.as_deref(), Some("2M"));
+
+        assert_eq!(build_download_options(&pool, false).await.max_filesize, None);
+        Settings::set(&pool, "max_filesize", " 2G ").await.unwrap();
+        assert_eq!(build_download_options(&pool, false).await.max_filesize.as_deref(), Some("2G"));
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_thumbnails() {
+        let pool = crate::db::test_pool().await;
+        let options = build_download_options(&pool, false).await;
+        assert_eq!(options.convert_thumbnails.as_deref(), Some("jpg"));
+        assert!(!options.embed_thumbnail);
+
+        Settings::set(&pool, "thumbnail_format", "png").await.unwrap();
+        Settings::set(&pool, "embed_thumbnail", "true").await.unwrap();
+        let options = build_download_options(&pool, false).await;
+        assert_eq!(options.convert_thumbnails.as_deref(), Some("png"));
+        assert!(options.embed_thumbnail);
+
+        Settings::set(&pool, "thumbnail_format", "webp").await.unwrap();
+        assert_eq!(build_download_options(&pool, false).await.convert_thumbnails.as_deref(), Some("jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_no_mtime() {
+        let pool = crate::db::test_pool().await;
+        assert!(!build_download_options(&pool, false).await.no_mtime);
+        Settings::set(&pool, "set_mtime", "false").await.unwrap();
+        assert!(build_download_options(&pool, false).await.no_mtime);
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_no_continue() {
+        let pool = crate::db::test_pool().await;
+        assert!(!build_download_options(&pool, false).await.no_continue);
+        Settings::set(&pool, "no_continue", "true").await.unwrap();
+        assert!(build_download_options(&pool, false).await.no_continue);
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_estimate_size() {
+        let pool = crate::db::test_pool().await;
+        assert!(!build_download_options(&pool, false).await.estimate_size);
+        Settings::set(&pool, "estimate_size", "true").await.unwrap();
+        assert!(build_download_options(&pool, false).await.estimate_size);
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_max_height() {
+        let pool = crate::db::test_pool().await;
+        assert_eq!(build_download_options(&pool, false).await.format.as_arg(), None);
+
+        Settings::set(&pool, "max_height", "1080").await.unwrap();
+        assert_eq!(
+            build_download_options(&pool, false).await.format.as_arg().as_deref(),
+            Some("bv*[height<=1080]+ba/b[height<=1080]/best")
+        );
+
+        Settings::set(&pool, "audio_format", "mp3").await.unwrap();
+        assert_eq!(build_download_options(&pool, false).await.format.as_arg(), None);
+        assert!(!build_download_options(&pool, false).await.embed_thumbnail);
+    }
+
+    #[tokio::test]
+    async fn test_build_download_options_audio_only_channel() {
+        let pool = crate::db::test_pool().await;
+        Settings::set(&pool, "max_height", "1080").await.unwrap();
+        Settings::set(&pool, "remux_video", "mp4").await.unwrap();
+
+        let options = build_download_options(&pool, true).await;
+        assert!(options.extract_audio);
+        assert_eq!(options.audio_format.as_deref(), Some("mp3"));
+        assert!(options.embed_thumbnail);
+        assert_eq!(options.format.as_arg(), None);
+        assert_eq!(options.remux_video, None);
+
+        Settings::set(&pool, "audio_only_format", "opus").await.unwrap();
+        assert_eq!(build_download_options(&pool, true).await.audio_format.as_deref(), Some("opus"));
+        assert!(!build_download_options(&pool, false).await.extract_audio);
+
+        Settings::set(&pool, "audio_format", "m4a").await.unwrap();
+        assert_eq!(build_download_options(&pool, true).await.audio_format.as_deref(), Some("m4a"));
+    }
+
+    #[test]
+    fn test_with_output_location() {
+        let options = with_output_location(DownloadOptions::default(), "/media/Chan", "%(title)s.%(ext)s", None);
+        assert_eq!(options.output_template.as_deref(), Some("%(title)s.%(ext)s"));
+        assert_eq!(options.paths.get(&PathType::Home), Some(&PathBuf::from("/media/Chan")));
+        assert_eq!(options.paths.get(&PathType::Temp), None);
+
+        let options = with_output_location(
+            DownloadOptions::default(),
+            "/media/Chan",
+            "%(title)s.%(ext)s",
+            Some("/ssd/tmp".to_string())
+        );
+        assert_eq!(options.output_template.as_deref(), Some("%(title)s.%(ext)s"));
+        assert_eq!(options.paths.get(&PathType::Home), Some(&PathBuf::from("/media/Chan")));
+        assert_eq!(options.paths.get(&PathType::Temp), Some(&PathBuf::from("/ssd/tmp")));
+    }
+
+    #[test]
+    fn test_with_container() {
+        let options = with_container(DownloadOptions::default(), "mp4".to_string(), false);
+        assert_eq!(options.remux_video.as_deref(), Some("mp4"));
+        assert_eq!(options.recode_video, None);
+
+        let options = with_container(DownloadOptions::default(), "mp4".to_string(), true);
+        assert_eq!(options.remux_video, None);
+        assert_eq!(options.recode_video.as_deref(), Some("mp4"));
+    }
+
+    #[test]
+    fn test_download_filter_apply() {
+        let filter = DownloadFilter { match_filter: Some("duration > 600".to_string()), no_shorts: true, ..Default::default() };
+        let options = filter.apply(DownloadOptions::default());
+        assert_eq!(
+            options.effective_match_filter().as_deref(),
+            Some("duration > 600 & original_url!*=/shorts/")
+        );
+
+        let filter = DownloadFilter {
+            match_filter: Some("duration > 600".to_string()),
+            date_range: DateRange::parse(Some("20240101"), None).unwrap(),
+            ..Default::default()
+        };
+        let options = filter.apply(DownloadOptions::default());
+        assert_eq!(options.match_filter.as_deref(), Some("duration > 600 & upload_date >= 20240101"));
+
+        let options = DownloadFilter::default().apply(DownloadOptions::default());
+        assert_eq!(options.effective_match_filter(), None);
+    }
+
+    #[test]
+    fn test_with_live_options() {
+        let options = with_live_options(DownloadOptions::default(), Some("is_live"));
+        assert!(options.live_from_start);
+        assert_eq!(options.wait_for_video, None);
+
+        let options = with_live_options(DownloadOptions::default(), Some("is_upcoming"));
+        assert!(options.live_from_start);
+        assert_eq!(options.wait_for_video.as_deref(), Some(LIVE_WAIT_INTERVAL));
+
+        let options = with_live_options(DownloadOptions::default(), Some("was_live"));
+        assert!(!options.live_from_start);
+        assert_eq!(options.wait_for_video, None);
+    }
+}
@@ -1 +1,3 @@
 pub mod download;
+pub mod retention;
+pub mod thumbnails;
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use crate::db::DbPool;
+use crate::models::{Channel, Download, RetentionCandidate, RetentionPolicy, Video};
+use crate::purge;
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+/// Periodically applies each channel's retention policy. Pruned downloads
+/// keep their row, so `start_download` still treats the video as downloaded;
+/// with `delete_videos` the video row goes too and later syncs skip it.
+pub struct RetentionWorker {
+    pool: DbPool,
+    interval: Duration
+}
+
+impl RetentionWorker {
+    /// The interval can be overridden with `RETENTION_INTERVAL_SECS`.
+    pub fn new(pool: DbPool) -> Self {
+        let secs = std::env::var("RETENTION_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&secs| secs > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        Self {
+            pool,
+            interval: Duration::from_secs(secs)
+        }
+    }
+
+    pub async fn run(self) {
+        tracing::info!("Retention worker started, checking every {:?}", self.interval);
+
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.apply_all().await {
+                tracing::error!("Retention pass failed: {}", e);
+            }
+        }
+    }
+
+    async fn apply_all(&self) -> Result<(), sqlx::Error> {
+        for channel in Channel::find_all(&self.pool).await? {
+            if let Some(policy) = channel.retention_policy() {
+                self.apply(&channel, &policy).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply(&self, channel: &Channel, policy: &RetentionPolicy) -> Result<(), sqlx::Error> {
+        let candidates = Download::find_retention_candidates(&self.pool, &channel.id).await?;
+        let expired = select_expired(&candidates, policy);
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let download_root = purge::download_root(&self.pool).await?;
+        for candidate in &expired {
+            if let (Some(root), Some(file_path)) = (&download_root, &candidate.file_path) {
+                purge::remove_media(root, file_path).await;
+            }
+            if policy.delete_videos {
+                Video::prune(&self.pool, &candidate.video_id).await?;
+            } else {
+                Download::mark_pruned(&self.pool, &candidate.download_id).await?;
+            }
+            tracing::info!("Pruned {} from channel {}", candidate.youtube_id, channel.name);
+        }
+
+        Ok(())
+    }
+}
+
+/// Candidates must be ordered newest first. A download expires when it falls
+/// outside the newest `keep_latest` or is older than `max_age_days`.
+fn select_expired<'a>(
+    candidates: &'a [RetentionCandidate],
+    policy: &RetentionPolicy
+) -> Vec<&'a RetentionCandidate> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(index, candidate)| {
+            let beyond_latest = policy
+                .keep_latest
+                .is_some_and(|keep| i64::try_from(*index).unwrap_or(i64::MAX) >= keep);
+            let too_old = policy.max_age_days.is_some_and(|days| candidate.age_days > days);
+            beyond_latest || too_old
+        })
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, age_days: i64) -> RetentionCandidate {
+        RetentionCandidate {
+            download_id: id.to_string(),
+            video_id: format!("video-{id}"),
+            youtube_id: format!("yt-{id}"),
+            file_path: None,
+            age_days
+        }
+    }
+
+    fn ids<'a>(expired: &[&'a RetentionCandidate]) -> Vec<&'a str> {
+        expired.iter().map(|c| c.download_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_select_expired_keep_latest() {
+        let candidates = vec![candidate("a", 1), candidate("b", 2), candidate("c", 3)];
+        let policy = RetentionPolicy { keep_latest: Some(2), max_age_days: None, delete_videos: false };
+        assert_eq!(ids(&select_expired(&candidates, &policy)), vec!["c"]);
+
+        let policy = RetentionPolicy { keep_latest: Some(0), max_age_days: None, delete_videos: false };
+        assert_eq!(ids(&select_expired(&candidates, &policy)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_select_expired_max_age() {
+        let candidates = vec![candidate("a", 5), candidate("b", 40), candidate("c", 30)];
+        let policy = RetentionPolicy { keep_latest: None, max_age_days: Some(30), delete_videos: false };
+        assert_eq!(ids(&select_expired(&candidates, &policy)), vec!["b"]);
+    }
+
+    #[test]
+    fn test_select_expired_combined() {
+        let candidates = vec![candidate("a", 50), candidate("b", 1), candidate("c", 2)];
+        let policy = RetentionPolicy { keep_latest: Some(2), max_age_days: Some(30), delete_videos: true };
+        assert_eq!(ids(&select_expired(&candidates, &policy)), vec!["a", "c"]);
+    }
+}
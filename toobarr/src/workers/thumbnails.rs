@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::db::DbPool;
+use crate::models::{Channel, Video};
+use crate::state::ThumbnailProgress;
+use crate::thumbnail;
+
+pub type ThumbnailProgressMap = Arc<RwLock<HashMap<String, ThumbnailProgress>>>;
+
+#[derive(Debug, Clone)]
+pub enum ThumbnailJob {
+    Channel { channel_id: String, url: String },
+    Video { youtube_id: String, url: String }
+}
+
+/// Registers the jobs in the channel's progress entry, then fetches them in
+/// the background so syncs return as soon as the video rows are written.
+/// Overlapping syncs of the same channel add to the same entry.
+pub async fn spawn_fetch(
+    pool: DbPool,
+    progress: ThumbnailProgressMap,
+    channel_id: String,
+    jobs: Vec<ThumbnailJob>
+) {
+    if jobs.is_empty() {
+        return;
+    }
+
+    progress
+        .write()
+        .await
+        .entry(channel_id.clone())
+        .or_insert(ThumbnailProgress { done: 0, total: 0 })
+        .total += jobs.len();
+
+    tokio::spawn(async move {
+        for job in jobs {
+            fetch(&pool, &job).await;
+
+            let mut progress = progress.write().await;
+            if let Some(entry) = progress.get_mut(&channel_id) {
+                entry.done += 1;
+                if entry.done >= entry.total {
+                    progress.remove(&channel_id);
+                }
+            }
+        }
+        tracing::info!("Finished fetching thumbnails for channel {}", channel_id);
+    });
+}
+
+async fn fetch(pool: &DbPool, job: &ThumbnailJob) {
+    let result = match job {
+        ThumbnailJob::Channel { channel_id, url } => {
+            match thumbnail::download_channel_thumbnail(channel_id, url).await {
+                Ok(path) => Channel::update_thumbnail(pool, channel_id, &path)
+                    .await
+                    .map_err(Into::into),
+                Err(e) => Err(e)
+            }
+        }
+        ThumbnailJob::Video { youtube_id, url } => {
+            match thumbnail::download_video_thumbnail(&file_id(youtube_id), url).await {
+                Ok(path) => Video::update_thumbnail_by_youtube_id(pool, youtube_id, &path)
+                    .await
+                    .map_err(Into::into),
+                Err(e) => Err(e)
+            }
+        }
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to fetch thumbnail {:?}: {}", job, e);
+    }
+}
+
+/// Whether a sync should queue the video's thumbnail: it is missing, or the
+/// listing now offers another URL for it.
+pub async fn video_needs_fetch(youtube_id: &str, url: &str) -> bool {
+    !thumbnail::has_video_thumbnail(&file_id(youtube_id), url).await
+}
+
+/// Keys of other sites contain a `:`, which some filesystems reject.
+fn file_id(youtube_id: &str) -> String {
+    youtube_id.replace(':', "_")
+}
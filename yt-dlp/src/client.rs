@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use futures_core::Stream;
+use serde::Deserialize;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio_stream::StreamExt;
@@ -11,16 +14,49 @@ use crate::command::CommandBuilder;
 use crate::error::{Error, Result};
 use crate::types::{
     Container, DownloadEvent, DownloadOptions, DownloadProgress, Format, OutputFormat,
-    PlaylistInfo, VideoInfo
+    PathType, PlaylistInfo, SearchProvider, VideoInfo, YtDlpVersion
 };
 
 #[derive(Debug, Clone)]
 pub struct YtDlp {
     binary: PathBuf,
     cookies_file: Option<PathBuf>,
+    cookies_from_browser: Option<String>,
     extra_args: Vec<String>,
     ffmpeg_location: Option<PathBuf>,
-    env_vars: HashMap<String, String>
+    cache_dir: Option<PathBuf>,
+    config_location: Option<PathBuf>,
+    impersonate: Option<String>,
+    env_vars: HashMap<String, String>,
+    credentials: Credentials,
+    /// The last successful [`YtDlp::check_binary`], shared between clones of
+    /// the same binary.
+    cached_version: Arc<Mutex<Option<(String, Instant)>>>
+}
+
+/// How long a `--version` result from [`YtDlp::check_binary`] is reused.
+const CHECK_BINARY_TTL: Duration = Duration::from_mins(1);
+
+/// Login details passed to yt-dlp. `Debug` is implemented by hand so that
+/// secrets never end up in logs.
+#[derive(Clone, Default)]
+struct Credentials {
+    username: Option<String>,
+    password: Option<String>,
+    video_password: Option<String>,
+    twofactor: Option<String>
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |v: &Option<String>| v.as_ref().map(|_| "***");
+        f.debug_struct("Credentials")
+            .field("username", &redact(&self.username))
+            .field("password", &redact(&self.password))
+            .field("video_password", &redact(&self.video_password))
+            .field("twofactor", &redact(&self.twofactor))
+            .finish()
+    }
 }
 
 impl Default for YtDlp {
@@ -35,30 +71,50 @@ impl YtDlp {
         Self {
             binary: PathBuf::from("yt-dlp"),
             cookies_file: None,
+            cookies_from_browser: None,
             extra_args: Vec::new(),
             ffmpeg_location: None,
-            env_vars: HashMap::new()
+            cache_dir: None,
+            config_location: None,
+            impersonate: None,
+            env_vars: HashMap::new(),
+            credentials: Credentials::default(),
+            cached_version: Arc::default()
         }
     }
 
     pub fn with_binary(path: impl Into<PathBuf>) -> Self {
         Self {
             binary: path.into(),
-            cookies_file: None,
-            extra_args: Vec::new(),
-            ffmpeg_location: None,
-            env_vars: HashMap::new()
+            ..Self::new()
         }
     }
 
+    /// A client that loads the yt-dlp config at `path` on every run; see
+    /// [`Self::set_config_location`].
+    pub fn with_config(path: impl Into<PathBuf>) -> Self {
+        Self {
+            config_location: Some(path.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Also drops the cached version; clones keep the old binary's.
     pub fn set_binary(&mut self, path: PathBuf) {
         self.binary = path;
+        self.cached_version = Arc::default();
     }
 
     pub fn set_cookies_file(&mut self, path: Option<PathBuf>) {
         self.cookies_file = path;
     }
 
+    /// Reads cookies from a local browser (`--cookies-from-browser`). A
+    /// cookies file set with [`Self::set_cookies_file`] takes precedence.
+    pub fn set_cookies_from_browser(&mut self, browser: Option<String>) {
+        self.cookies_from_browser = browser;
+    }
+
     pub fn set_extra_args(&mut self, args: Vec<String>) {
         self.extra_args = args;
     }
@@ -67,26 +123,166 @@ impl YtDlp {
         self.ffmpeg_location = path;
     }
 
+    /// A yt-dlp config file, or a directory holding `yt-dlp.conf`, loaded on
+    /// every run (`--config-locations`). yt-dlp reads it before the command
+    /// line, so every flag this crate passes wins over the config's.
+    pub fn set_config_location(&mut self, path: Option<PathBuf>) {
+        self.config_location = path;
+    }
+
+    /// Where yt-dlp keeps its player JS and PO token cache (`--cache-dir`).
+    pub fn set_cache_dir(&mut self, path: Option<PathBuf>) {
+        self.cache_dir = path;
+    }
+
+    /// Browser TLS fingerprint to impersonate (`--impersonate`), e.g.
+    /// `chrome` or `safari:ios`. Applies to info fetches and downloads.
+    pub fn set_impersonate(&mut self, target: Option<String>) {
+        self.impersonate = target;
+    }
+
     pub fn set_env(&mut self, key: String, value: String) {
         self.env_vars.insert(key, value);
     }
 
+    pub fn remove_env(&mut self, key: &str) {
+        self.env_vars.remove(key);
+    }
+
+    pub fn set_username(&mut self, username: Option<String>) {
+        self.credentials.username = username;
+    }
+
+    pub fn set_password(&mut self, password: Option<String>) {
+        self.credentials.password = password;
+    }
+
+    pub fn set_video_password(&mut self, password: Option<String>) {
+        self.credentials.video_password = password;
+    }
+
+    pub fn set_twofactor(&mut self, code: Option<String>) {
+        self.credentials.twofactor = code;
+    }
+
     /// # Errors
     ///
     /// Returns an error if the binary is not found or not executable.
     pub async fn check_binary(&self) -> Result<String> {
+        if let Some(version) = self.cached_version() {
+            return Ok(version);
+        }
+
         let output = Command::new(&self.binary)
             .arg("--version")
             .output()
             .await?;
 
         if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            self.lock_version_cache().replace((version.clone(), Instant::now()));
+            Ok(version)
         } else {
             Err(Error::BinaryNotExecutable(self.binary.clone()))
         }
     }
 
+    /// Updates the binary in place with `--update-to stable` and returns the
+    /// version reported afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UpdateUnsupported` when yt-dlp was installed through a
+    /// package manager that must be used instead, or `Error::UpdateFailed`.
+    pub async fn update_binary(&self) -> Result<String> {
+        let output = Command::new(&self.binary)
+            .args(["--update-to", "stable"])
+            .output()
+            .await?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        tracing::debug!(stdout = %stdout, stderr = %stderr, "yt-dlp update finished");
+
+        if !output.status.success() {
+            return Err(classify_update_failure(&stdout, &stderr));
+        }
+
+        self.lock_version_cache().take();
+        self.check_binary().await
+    }
+
+    /// A version checked less than [`CHECK_BINARY_TTL`] ago. Failures are
+    /// never cached, so a fixed install is picked up on the next check.
+    fn cached_version(&self) -> Option<String> {
+        self.lock_version_cache()
+            .as_ref()
+            .filter(|(_, checked_at)| checked_at.elapsed() < CHECK_BINARY_TTL)
+            .map(|(version, _)| version.clone())
+    }
+
+    fn lock_version_cache(&self) -> std::sync::MutexGuard<'_, Option<(String, Instant)>> {
+        self.cached_version.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Checks that the installed yt-dlp can impersonate `target`, which
+    /// needs the optional `curl_cffi` dependency.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ImpersonationUnavailable` if no usable target matches.
+    pub async fn check_impersonate(&self, target: &str) -> Result<()> {
+        // A target that is already set but unusable would fail the listing
+        let probe = Self { impersonate: None, ..self.clone() };
+        let output = probe
+            .command()
+            .arg("--list-impersonate-targets")
+            .build_with_env(&self.env_vars)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(command_failed(output.status.code(), &String::from_utf8_lossy(&output.stderr)));
+        }
+
+        if impersonate_target_available(&String::from_utf8_lossy(&output.stdout), target) {
+            Ok(())
+        } else {
+            Err(Error::ImpersonationUnavailable(target.to_string()))
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the binary cannot be run or reports an unrecognized version.
+    pub async fn version(&self) -> Result<YtDlpVersion> {
+        let version = self.check_binary().await?;
+        YtDlpVersion::parse(&version).ok_or(Error::InvalidVersion(version))
+    }
+
+    /// Installed version as `(year, month, day)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary cannot be run or reports an unrecognized version.
+    pub async fn version_tuple(&self) -> Result<(u32, u32, u32)> {
+        Ok(self.version().await?.date())
+    }
+
+    /// # Errors
+    ///
+    /// Returns `Error::VersionTooOld` if the installed binary predates the given release.
+    pub async fn require_min_version(&self, year: u32, month: u32, day: u32) -> Result<()> {
+        let installed = self.version_tuple().await?;
+        if installed < (year, month, day) {
+            return Err(Error::VersionTooOld {
+                installed: format!("{}.{:02}.{:02}", installed.0, installed.1, installed.2),
+                required: format!("{year}.{month:02}.{day:02}")
+            });
+        }
+        Ok(())
+    }
+
     /// # Errors
     ///
     /// Returns an error if the command fails or the output cannot be parsed.
@@ -102,78 +298,202 @@ impl YtDlp {
             .await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::CommandFailed {
-                code: output.status.code().unwrap_or(-1),
-                stderr
-            });
+            return Err(command_failed(output.status.code(), &String::from_utf8_lossy(&output.stderr)));
         }
 
         let info: VideoInfo = serde_json::from_slice(&output.stdout)?;
         Ok(info)
     }
 
+    /// Fetches playlist metadata with `--flat-playlist`. Fast, but entries
+    /// often lack `duration`, `view_count` and thumbnails.
+    ///
     /// # Errors
     ///
     /// Returns an error if the command fails or no playlist entries are found.
     pub async fn get_playlist_info(&self, url: &str) -> Result<PlaylistInfo> {
-        let output = self
+        self.fetch_playlist(url, true).await
+    }
+
+    /// Like [`Self::get_playlist_info`] but extracts every entry, so per-video
+    /// fields are populated. Runs one extraction per video and is much slower
+    /// on large channels. Entries that fail (private, members-only) are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails without yielding any entries.
+    pub async fn get_playlist_info_detailed(&self, url: &str) -> Result<PlaylistInfo> {
+        self.fetch_playlist(url, false).await
+    }
+
+    /// Lists only the first entry of the playlist, which is enough to learn
+    /// its id and channel without enumerating it. `entries` holds at most
+    /// one video.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails or the playlist is empty.
+    pub async fn get_playlist_metadata(&self, url: &str) -> Result<PlaylistInfo> {
+        self.run_playlist_command(self.metadata_command(url), true).await
+    }
+
+    /// Streams the entries as yt-dlp lists them, flat or, with `detailed`,
+    /// extracting each one. Only `playlist_items`, `playlist_start`/
+    /// `playlist_end` and `playlist_reverse` are taken from `options`.
+    /// Dropping the stream kills yt-dlp, so a caller that has seen enough
+    /// ends the listing there.
+    ///
+    /// Failures follow [`Self::get_playlist_info`] and
+    /// [`Self::get_playlist_info_detailed`]: the stream ends with the error
+    /// after any entries that were listed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if stdout or stderr cannot be captured from the child process.
+    #[must_use]
+    pub fn stream_playlist(
+        &self,
+        url: &str,
+        detailed: bool,
+        options: &DownloadOptions
+    ) -> Pin<Box<dyn Stream<Item = Result<VideoInfo>> + Send + '_>> {
+        let flat = !detailed;
+        let builder = self.playlist_command(url, flat, options);
+        let validation = options.validate();
+
+        Box::pin(async_stream::try_stream! {
+            validation?;
+            tracing::debug!(
+                binary = %self.binary.display(),
+                args = ?builder.redacted_args(),
+                "spawning yt-dlp"
+            );
+
+            let mut cmd = builder.build_with_env(&self.env_vars);
+            cmd.stdout(std::process::Stdio::piped());
+            cmd.stderr(std::process::Stdio::piped());
+            cmd.kill_on_drop(true);
+
+            let mut child = cmd.spawn()?;
+
+            let stderr = child.stderr.take().expect("stderr not captured");
+            let stderr_task = tokio::spawn(async move {
+                let mut reader = BufReader::new(stderr).lines();
+                let mut text = String::new();
+                while let Ok(Some(line)) = reader.next_line().await {
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+                text
+            });
+
+            let stdout = child.stdout.take().expect("stdout not captured");
+            let mut reader = BufReader::new(stdout).lines();
+            let mut listed = 0usize;
+            while let Some(line) = reader.next_line().await? {
+                if let Ok(info) = serde_json::from_str::<VideoInfo>(&line) {
+                    listed += 1;
+                    yield info;
+                }
+            }
+
+            let status = child.wait().await?;
+            let stderr = stderr_task.await.unwrap_or_default();
+
+            // As in run_playlist_command, an unavailable video only fails an
+            // extracting listing when nothing was listed
+            if !status.success() && (flat || listed == 0) {
+                Err::<(), _>(command_failed(status.code(), &stderr))?;
+            }
+            if listed == 0 {
+                Err::<(), _>(Error::EmptyPlaylist)?;
+            }
+        })
+    }
+
+    async fn fetch_playlist(&self, url: &str, flat: bool) -> Result<PlaylistInfo> {
+        let command = self.playlist_command(url, flat, &DownloadOptions::default());
+        self.run_playlist_command(command, flat).await
+    }
+
+    async fn run_playlist_command(&self, command: CommandBuilder, flat: bool) -> Result<PlaylistInfo> {
+        let output = command
+            .build_with_env(&self.env_vars)
+            .output()
+            .await?;
+
+        let playlist_info = parse_playlist_output(&String::from_utf8_lossy(&output.stdout));
+
+        // With --ignore-errors a single unavailable video makes yt-dlp exit
+        // non-zero even though the rest of the playlist was extracted.
+        if !output.status.success() && (flat || playlist_info.is_none()) {
+            return Err(command_failed(output.status.code(), &String::from_utf8_lossy(&output.stderr)));
+        }
+
+        playlist_info.ok_or(Error::EmptyPlaylist)
+    }
+
+    fn playlist_command(&self, url: &str, flat: bool, options: &DownloadOptions) -> CommandBuilder {
+        let builder = self
             .command()
+            .json_output()
+            .skip_download()
+            .yes_playlist()
+            .with_playlist_selection(options);
+        let builder = if flat {
+            builder.flat_playlist()
+        } else {
+            builder.arg("--ignore-errors")
+        };
+        builder.url(url)
+    }
+
+    fn metadata_command(&self, url: &str) -> CommandBuilder {
+        self.command()
             .json_output()
             .skip_download()
             .yes_playlist()
             .flat_playlist()
+            .playlist_items("1")
             .url(url)
+    }
+
+    /// Searches `YouTube`, returning up to `limit` results.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails.
+    pub async fn search(&self, query: &str, limit: u32) -> Result<Vec<VideoInfo>> {
+        self.search_with_provider(&SearchProvider::YouTube, query, limit).await
+    }
+
+    /// Runs a search through a pseudo-URL such as `ytsearch10:query`.
+    /// No matches yields an empty `Vec`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails.
+    pub async fn search_with_provider(
+        &self,
+        provider: &SearchProvider,
+        query: &str,
+        limit: u32
+    ) -> Result<Vec<VideoInfo>> {
+        let output = self
+            .command()
+            .json_output()
+            .skip_download()
+            .flat_playlist()
+            .url(search_url(provider, query, limit))
             .build_with_env(&self.env_vars)
             .output()
             .await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::CommandFailed {
-                code: output.status.code().unwrap_or(-1),
-                stderr
-            });
+            return Err(command_failed(output.status.code(), &String::from_utf8_lossy(&output.stderr)));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut entries = Vec::new();
-        let mut playlist_info: Option<PlaylistInfo> = None;
-
-        for line in stdout.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            if let Ok(info) = serde_json::from_str::<VideoInfo>(line) {
-                if playlist_info.is_none() {
-                    playlist_info = Some(PlaylistInfo {
-                        id: info.playlist_id.clone().unwrap_or_default(),
-                        title: info.playlist_title.clone(),
-                        description: None,
-                        uploader: info.uploader.clone(),
-                        uploader_id: info.uploader_id.clone(),
-                        uploader_url: info.uploader_url.clone(),
-                        channel: info.channel.clone(),
-                        channel_id: info.channel_id.clone(),
-                        channel_url: info.channel_url.clone(),
-                        webpage_url: None,
-                        entries: Vec::new(),
-                        playlist_count: info.playlist_count,
-                        extractor: info.extractor.clone(),
-                        extractor_key: info.extractor_key.clone()
-                    });
-                }
-                entries.push(info);
-            }
-        }
-
-        match playlist_info {
-            Some(mut info) => {
-                info.entries = entries;
-                Ok(info)
-            }
-            None => Err(Error::EmptyPlaylist)
-        }
+        Ok(parse_json_lines(&String::from_utf8_lossy(&output.stdout)))
     }
 
     /// # Errors
@@ -188,6 +508,51 @@ impl YtDlp {
         }
     }
 
+    /// Writes only the uploaded subtitles for `langs` (all languages when
+    /// empty) into `out_dir` as `<id>.<lang>.<ext>`, without the video. A
+    /// video without subtitles in those languages yields an empty `Vec`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails for any other reason.
+    pub async fn download_subtitles(
+        &self,
+        url: &str,
+        langs: &[String],
+        out_dir: impl AsRef<Path>
+    ) -> Result<Vec<PathBuf>> {
+        let output = self
+            .subtitles_command(url, langs, out_dir.as_ref())
+            .build_with_env(&self.env_vars)
+            .output()
+            .await?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            if stderr.contains("no subtitles") {
+                return Ok(Vec::new());
+            }
+            return Err(command_failed(output.status.code(), &stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(written_subtitle)
+            .collect())
+    }
+
+    fn subtitles_command(&self, url: &str, langs: &[String], out_dir: &Path) -> CommandBuilder {
+        let langs = if langs.is_empty() { &["all".to_string()][..] } else { langs };
+        self.command()
+            .no_quiet()
+            .skip_download()
+            .no_playlist()
+            .write_subtitles()
+            .subtitles_langs(langs)
+            .output(out_dir.join("%(id)s.%(ext)s"))
+            .url(url)
+    }
+
     /// # Errors
     ///
     /// Returns an error if the download command fails.
@@ -205,26 +570,45 @@ impl YtDlp {
         output: impl AsRef<Path>,
         options: &DownloadOptions
     ) -> Result<PathBuf> {
+        options.validate()?;
         let output_path = output.as_ref().to_path_buf();
 
+        match self.run_download(url, &output_path, options).await {
+            Err(Error::FormatUnavailable(message)) if options.format.fallback().is_some() => {
+                let fallback = options.format.fallback().unwrap_or_default();
+                tracing::warn!(
+                    requested = ?options.format.as_arg(),
+                    fallback = ?fallback.as_arg(),
+                    "{message}; retrying with fallback format"
+                );
+                self.run_download(url, &output_path, &options.clone().format(fallback)).await
+            }
+            result => result
+        }
+    }
+
+    async fn run_download(
+        &self,
+        url: &str,
+        output_path: &Path,
+        options: &DownloadOptions
+    ) -> Result<PathBuf> {
         let result = self
-            .command()
-            .with_options(options)
-            .output(&output_path)
-            .url(url)
+            .download_command(url, output_path, options)
             .build_with_env(&self.env_vars)
             .output()
             .await?;
 
-        if !result.status.success() {
-            let stderr = String::from_utf8_lossy(&result.stderr).to_string();
-            return Err(Error::CommandFailed {
-                code: result.status.code().unwrap_or(-1),
-                stderr
-            });
+        if !download_succeeded(result.status.code(), options.max_downloads) {
+            return Err(command_failed(result.status.code(), &String::from_utf8_lossy(&result.stderr)));
         }
 
-        Ok(output_path)
+        let stdout = String::from_utf8_lossy(&result.stdout);
+        Ok(stdout
+            .lines()
+            .rev()
+            .find_map(printed_filepath)
+            .map_or_else(|| final_output_path(output_path, options), PathBuf::from))
     }
 
     /// # Panics
@@ -235,74 +619,130 @@ impl YtDlp {
         url: &str,
         output: impl AsRef<Path>,
         options: &DownloadOptions
+    ) -> Pin<Box<dyn Stream<Item = Result<DownloadEvent>> + Send + '_>> {
+        self.download_with_progress_until(url, output, options, std::future::pending())
+    }
+
+    /// Like [`Self::download_with_progress`], but kills yt-dlp once `cancel`
+    /// completes. The stream then ends with [`Error::Cancelled`], after the
+    /// process has exited, so no more data is written to partial files.
+    ///
+    /// # Panics
+    ///
+    /// Panics if stdout or stderr cannot be captured from the child process.
+    pub fn download_with_progress_until(
+        &self,
+        url: &str,
+        output: impl AsRef<Path>,
+        options: &DownloadOptions,
+        cancel: impl Future<Output = ()> + Send + 'static
     ) -> Pin<Box<dyn Stream<Item = Result<DownloadEvent>> + Send + '_>> {
         let output_path = output.as_ref().to_path_buf();
         let url = url.to_string();
-        let options = options.clone();
         let binary = self.binary.clone();
-        let cookies_file = self.cookies_file.clone();
-        let extra_args = self.extra_args.clone();
-        let ffmpeg_location = self.ffmpeg_location.clone();
         let env_vars = self.env_vars.clone();
 
+        let progress_command = |options: &DownloadOptions| self.progress_command(&url, &output_path, options);
+        let builder = progress_command(options);
+        let fallback = options.format.fallback().map(|format| {
+            let event = DownloadEvent::FormatFallback {
+                requested: options.format.as_arg().unwrap_or_default(),
+                fallback: format.as_arg().unwrap_or_default()
+            };
+            (event, progress_command(&options.clone().format(format)))
+        });
+
+        let validation = options.validate();
+        let max_downloads = options.max_downloads;
+        let size_probe = options.estimate_size.then(|| self.size_probe_command(&url, options));
+
         Box::pin(async_stream::try_stream! {
+            validation?;
+            tokio::pin!(cancel);
             yield DownloadEvent::Extracting { url: url.clone() };
 
-            let mut builder = CommandBuilder::new(&binary)
-                .cookies_file_opt(cookies_file.as_ref())
-                .args(extra_args.iter().map(String::as_str))
-                .with_options(&options)
-                .output(&output_path)
-                .newline_progress()
-                .progress_template("download:%(progress._percent_str)s %(progress._total_bytes_str)s %(progress._speed_str)s %(progress._eta_str)s")
-                .url(&url);
-
-            if let Some(ref ffmpeg_path) = ffmpeg_location {
-                builder = builder.ffmpeg_location(ffmpeg_path);
+            if let Some(probe) = size_probe
+                && let Some(total_bytes) = run_size_probe(&probe, &env_vars).await
+            {
+                yield DownloadEvent::SizeEstimated { total_bytes };
             }
 
-            tracing::debug!(
-                binary = %binary.display(),
-                args = ?builder.get_args(),
-                "spawning yt-dlp"
-            );
-
-            let mut cmd = builder.build_with_env(&env_vars);
-            cmd.stdout(std::process::Stdio::piped());
-            cmd.stderr(std::process::Stdio::piped());
-
-            let mut child = cmd.spawn()?;
-
-            let stderr = child.stderr.take().expect("stderr not captured");
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    tracing::trace!(line = %line, "yt-dlp stderr");
+            let mut builder = builder;
+            let mut fallback = fallback;
+            loop {
+                tracing::debug!(
+                    binary = %binary.display(),
+                    args = ?builder.redacted_args(),
+                    "spawning yt-dlp"
+                );
+
+                let mut cmd = builder.build_with_env(&env_vars);
+                cmd.stdout(std::process::Stdio::piped());
+                cmd.stderr(std::process::Stdio::piped());
+                cmd.kill_on_drop(true);
+
+                let mut child = cmd.spawn()?;
+
+                let stderr = child.stderr.take().expect("stderr not captured");
+                let stderr_task = tokio::spawn(async move {
+                    let mut reader = BufReader::new(stderr).lines();
+                    let mut error = None;
+                    while let Ok(Some(line)) = reader.next_line().await {
+                        tracing::trace!(line = %line, "yt-dlp stderr");
+                        if error.is_none() && let Some(message) = line.strip_prefix("ERROR:") {
+                            error = Some(message.trim().to_string());
+                        }
+                    }
+                    error
+                });
+
+                let stdout = child.stdout.take().expect("stdout not captured");
+                let mut reader = BufReader::new(stdout).lines();
+
+                let mut current_filename: Option<String> = None;
+                let mut summary = SummaryTracker::new();
+
+                loop {
+                    let next_line = tokio::select! {
+                        () = &mut cancel => None,
+                        line = reader.next_line() => Some(line)
+                    };
+                    let Some(line) = next_line else {
+                        child.kill().await?;
+                        tracing::debug!("yt-dlp killed on cancellation");
+                        Err::<(), _>(Error::Cancelled)?;
+                        return;
+                    };
+                    let Some(line) = line? else {
+                        break;
+                    };
+                    tracing::trace!(line = %line, "yt-dlp stdout");
+                    if let Some(event) = parse_progress_line(&line, &mut current_filename) {
+                        summary.observe(&event);
+                        yield event;
+                    }
                 }
-            });
 
-            let stdout = child.stdout.take().expect("stdout not captured");
-            let mut reader = BufReader::new(stdout).lines();
+                let status = child.wait().await?;
+                let error = stderr_task.await.ok().flatten();
 
-            let mut current_filename: Option<String> = None;
-
-            while let Some(line) = reader.next_line().await? {
-                tracing::trace!(line = %line, "yt-dlp stdout");
-                if let Some(event) = parse_progress_line(&line, &mut current_filename) {
+                if download_succeeded(status.code(), max_downloads) {
+                    let filename = current_filename
+                        .unwrap_or_else(|| output_path.to_string_lossy().to_string());
+                    yield summary.finish(filename);
+                } else if error.as_deref().is_some_and(is_format_unavailable)
+                    && let Some((event, next)) = fallback.take()
+                {
                     yield event;
+                    builder = next;
+                    continue;
+                } else {
+                    // The stderr message is what callers classify with Error::from_message
+                    yield DownloadEvent::Error {
+                        message: error.unwrap_or_else(|| format!("yt-dlp exited with code {}", status.code().unwrap_or(-1)))
+                    };
                 }
-            }
-
-            let status = child.wait().await?;
-
-            if status.success() {
-                let filename = current_filename
-                    .unwrap_or_else(|| output_path.to_string_lossy().to_string());
-                yield DownloadEvent::Finished { filename };
-            } else {
-                yield DownloadEvent::Error {
-                    message: format!("yt-dlp exited with code {}", status.code().unwrap_or(-1))
-                };
+                break;
             }
         })
     }
@@ -328,22 +768,301 @@ impl YtDlp {
         DownloadBuilder::new(self.clone(), url.to_string())
     }
 
-    fn command(&self) -> CommandBuilder {
-        let mut builder = CommandBuilder::new(&self.binary)
-            .cookies_file_opt(self.cookies_file.as_ref())
-            .args(self.extra_args.iter().map(String::as_str));
+    /// Prints the file's final path once every post-processor has run and it
+    /// has been moved into place, since the output template, remuxing and
+    /// audio extraction can all change the name.
+    fn download_command(&self, url: &str, output: &Path, options: &DownloadOptions) -> CommandBuilder {
+        self.command()
+            .with_options(options)
+            .output_unless_templated(output, options)
+            .print(format!("after_move:{FILEPATH_PREFIX} %(filepath)s"))
+            .url(url)
+    }
 
-        if let Some(ref ffmpeg_path) = self.ffmpeg_location {
-            builder = builder.ffmpeg_location(ffmpeg_path);
-        }
+    /// [`Self::download_command`] with one JSON progress line per update.
+    fn progress_command(&self, url: &str, output: &Path, options: &DownloadOptions) -> CommandBuilder {
+        self.download_command(url, output, options)
+            .no_quiet()
+            .newline_progress()
+            .progress_template(format!("download:{PROGRESS_JSON_PREFIX} %(progress)j"))
+    }
 
+    /// Prints the selected format's size without downloading anything.
+    fn size_probe_command(&self, url: &str, options: &DownloadOptions) -> CommandBuilder {
+        let mut builder = self.command();
+        if let Some(format_arg) = options.format.as_arg() {
+            builder = builder.format(format_arg);
+        }
         builder
+            .arg("--no-playlist")
+            .arg("--print")
+            .arg("%(filesize,filesize_approx)s")
+            .url(url)
     }
-}
 
-fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Option<DownloadEvent> {
+    fn command(&self) -> CommandBuilder {
+        let mut builder = CommandBuilder::new(&self.binary);
+
+        if let Some(ref path) = self.config_location {
+            builder = builder.config_location(path);
+        }
+
+        builder = builder.cookies_file_opt(self.cookies_file.as_ref());
+
+        if self.cookies_file.is_none()
+            && let Some(ref browser) = self.cookies_from_browser
+        {
+            builder = builder.cookies_from_browser(browser.clone());
+        }
+
+        builder = builder.args(self.extra_args.iter().map(String::as_str));
+
+        if let Some(ref ffmpeg_path) = self.ffmpeg_location {
+            builder = builder.ffmpeg_location(ffmpeg_path);
+        }
+
+        if let Some(ref cache_dir) = self.cache_dir {
+            builder = builder.cache_dir(cache_dir);
+        }
+
+        if let Some(ref target) = self.impersonate {
+            builder = builder.impersonate(target.clone());
+        }
+
+        if let Some(ref username) = self.credentials.username {
+            builder = builder.username(username.clone());
+        }
+
+        if let Some(ref password) = self.credentials.password {
+            builder = builder.password(password.clone());
+        }
+
+        if let Some(ref password) = self.credentials.video_password {
+            builder = builder.video_password(password.clone());
+        }
+
+        if let Some(ref code) = self.credentials.twofactor {
+            builder = builder.twofactor(code.clone());
+        }
+
+        builder
+    }
+}
+
+fn search_url(provider: &SearchProvider, query: &str, limit: u32) -> String {
+    format!("{}{}:{}", provider.prefix(), limit, query.trim())
+}
+
+/// Builds a `PlaylistInfo` from `--dump-json` output, one entry per line.
+/// Playlist-level fields come from the first entry.
+fn parse_playlist_output(stdout: &str) -> Option<PlaylistInfo> {
+    let mut entries = Vec::new();
+    let mut playlist_info: Option<PlaylistInfo> = None;
+
+    for line in stdout.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(info) = serde_json::from_str::<VideoInfo>(line) {
+            if playlist_info.is_none() {
+                playlist_info = Some(PlaylistInfo {
+                    id: info.playlist_id.clone().unwrap_or_default(),
+                    title: info.playlist_title.clone(),
+                    description: None,
+                    uploader: info.uploader.clone(),
+                    uploader_id: info.uploader_id.clone(),
+                    uploader_url: info.uploader_url.clone(),
+                    channel: info.channel.clone(),
+                    channel_id: info.channel_id.clone(),
+                    channel_url: info.channel_url.clone(),
+                    webpage_url: None,
+                    entries: Vec::new(),
+                    playlist_count: info.playlist_count,
+                    extractor: info.extractor.clone(),
+                    extractor_key: info.extractor_key.clone()
+                });
+            }
+            entries.push(info);
+        }
+    }
+
+    playlist_info.map(|mut info| {
+        info.entries = entries;
+        info
+    })
+}
+
+async fn run_size_probe(probe: &CommandBuilder, env_vars: &HashMap<String, String>) -> Option<u64> {
+    let output = match probe.build_with_env(env_vars).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            tracing::debug!(code = ?output.status.code(), "size probe failed");
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!(error = %e, "size probe could not run");
+            return None;
+        }
+    };
+    parse_size_estimate(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// yt-dlp prints `NA` when neither `filesize` nor `filesize_approx` is known.
+fn parse_size_estimate(stdout: &str) -> Option<u64> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?
+        .parse()
+        .ok()
+        .filter(|&bytes| bytes > 0)
+}
+
+fn parse_json_lines(stdout: &str) -> Vec<VideoInfo> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<VideoInfo>(line).ok())
+        .collect()
+}
+
+/// yt-dlp's exit code once `--max-downloads` files have been downloaded.
+const MAX_DOWNLOADS_EXIT_CODE: i32 = 101;
+
+/// Reaching `max_downloads` ends yt-dlp with its own exit code, which is not
+/// a failure.
+fn download_succeeded(code: Option<i32>, max_downloads: Option<u32>) -> bool {
+    code == Some(0) || (max_downloads.is_some() && code == Some(MAX_DOWNLOADS_EXIT_CODE))
+}
+
+/// The path reported by the `after_move` print of [`YtDlp::download_command`].
+fn printed_filepath(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix(FILEPATH_PREFIX)
+        .map(str::trim)
+        .filter(|path| !path.is_empty() && *path != "NA")
+}
+
+/// Best guess when yt-dlp printed no final path.
+fn final_output_path(output: &Path, options: &DownloadOptions) -> PathBuf {
+    if options.extract_audio
+        && let Some(ext) = options.audio_format.as_deref().and_then(audio_extension)
+    {
+        return output.with_extension(ext);
+    }
+    output.to_path_buf()
+}
+
+fn audio_extension(format: &str) -> Option<&'static str> {
+    match format {
+        "mp3" => Some("mp3"),
+        "aac" | "m4a" | "alac" => Some("m4a"),
+        "opus" => Some("opus"),
+        "vorbis" => Some("ogg"),
+        "flac" => Some("flac"),
+        "wav" => Some("wav"),
+        _ => None
+    }
+}
+
+/// A failed command's error. yt-dlp refuses to run when `--impersonate`
+/// names a target it has no backend for, which is reported separately;
+/// other known failures map to their variant through [`Error::from_stderr`].
+fn command_failed(code: Option<i32>, stderr: &str) -> Error {
+    if is_format_unavailable(stderr) {
+        let message = stderr
+            .lines()
+            .find(|line| is_format_unavailable(line))
+            .unwrap_or_default();
+        return Error::FormatUnavailable(message.trim_start_matches("ERROR:").trim().to_string());
+    }
+    if let Some(line) = stderr
+        .lines()
+        .find(|line| line.contains("Impersonate target") && line.contains("is not available"))
+    {
+        let target = line.split('"').nth(1).unwrap_or_default();
+        return Error::ImpersonationUnavailable(target.to_string());
+    }
+    if let Some(error) = Error::from_stderr(stderr) {
+        return error;
+    }
+    Error::CommandFailed { code: code.unwrap_or(-1), stderr: stderr.to_string() }
+}
+
+/// The path from yt-dlp's `[info] Writing video subtitles to: <path>` line.
+fn written_subtitle(line: &str) -> Option<PathBuf> {
+    line.strip_prefix("[info] Writing video subtitles to:")
+        .map(|path| PathBuf::from(path.trim()))
+}
+
+fn is_format_unavailable(stderr: &str) -> bool {
+    stderr.contains("Requested format is not available")
+}
+
+/// Whether `--list-impersonate-targets` output has a usable row for a
+/// `CLIENT[:OS]` target. `chrome` matches versioned rows such as `Chrome-124`;
+/// rows whose source is marked `(unavailable)` lack `curl_cffi`.
+fn impersonate_target_available(listing: &str, target: &str) -> bool {
+    let client = target.split(':').next().unwrap_or_default().trim().to_lowercase();
+    let os = target.split_once(':').map(|(_, os)| os.trim().to_lowercase());
+    listing
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1)
+        .filter(|line| !line.contains("(unavailable)"))
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            Some((columns.next()?.to_lowercase(), columns.next()?.to_lowercase()))
+        })
+        .any(|(row_client, row_os)| {
+            (client.is_empty() || row_client == client || row_client.starts_with(&format!("{client}-")))
+                && os.as_ref().is_none_or(|os| row_os == *os || row_os.starts_with(&format!("{os}-")))
+        })
+}
+
+fn classify_update_failure(stdout: &str, stderr: &str) -> Error {
+    let message = stderr
+        .lines()
+        .chain(stdout.lines())
+        .map(str::trim)
+        .find(|line| line.starts_with("ERROR:"))
+        .map_or_else(|| stderr.trim().to_string(), |line| line.trim_start_matches("ERROR:").trim().to_string());
+
+    let lower = message.to_lowercase();
+    if lower.contains("pip") || lower.contains("package manager") || lower.contains("not supported") {
+        Error::UpdateUnsupported(message)
+    } else {
+        Error::UpdateFailed(message)
+    }
+}
+
+/// Parses a yt-dlp `YYYY.MM.DD` version string. Nightly builds append a
+/// fourth component (e.g. `2024.03.10.232650`), which is ignored.
+#[must_use]
+pub fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    YtDlpVersion::parse(version).map(|v| v.date())
+}
+
+/// Marks progress lines emitted by the `%(progress)j` template.
+const PROGRESS_JSON_PREFIX: &str = "[progress]";
+
+/// Marks the final file path printed once the download has been moved into place.
+const FILEPATH_PREFIX: &str = "[filepath]";
+
+fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Option<DownloadEvent> {
     let line = line.trim();
 
+    if let Some(json) = line.strip_prefix(PROGRESS_JSON_PREFIX) {
+        return parse_json_progress(json).map(DownloadEvent::Progress);
+    }
+
+    if line.starts_with(FILEPATH_PREFIX) {
+        if let Some(path) = printed_filepath(line) {
+            *current_filename = Some(path.to_string());
+        }
+        return None;
+    }
+
     if line.starts_with("[download] Destination:") {
         let filename = line.trim_start_matches("[download] Destination:").trim();
         *current_filename = Some(filename.to_string());
@@ -352,6 +1071,28 @@ fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Opt
         });
     }
 
+    if let Some(filename) = line.strip_prefix("[info] Writing video metadata as JSON to:") {
+        return Some(DownloadEvent::InfoJsonWritten {
+            filename: filename.trim().to_string()
+        });
+    }
+
+    if line.starts_with("[info]")
+        && let Some((_, format_id)) = line.split_once("format(s):")
+    {
+        return Some(DownloadEvent::FormatSelected {
+            format_id: format_id.trim().to_string()
+        });
+    }
+
+    if let Some(stage) = resolving_stage(line) {
+        return Some(DownloadEvent::Resolving { stage: stage.to_string() });
+    }
+
+    if let Some(event) = rejection_event(line) {
+        return Some(event);
+    }
+
     if line.starts_with("[download]") && line.contains('%') {
         return Some(DownloadEvent::Progress(parse_download_progress(line)));
     }
@@ -386,6 +1127,19 @@ fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Opt
         return Some(DownloadEvent::EmbeddingMetadata);
     }
 
+    if let Some(deleted) = deleted_original(line)
+        && current_filename.as_deref() == Some(deleted)
+    {
+        *current_filename = None;
+    }
+
+    if let Some(filename) = postprocessed_filename(line)
+        && current_filename.as_deref() != Some(filename)
+    {
+        let from = current_filename.replace(filename.to_string()).unwrap_or_default();
+        return Some(DownloadEvent::FileRenamed { from, to: filename.to_string() });
+    }
+
     if line.starts_with("[ExtractAudio]") || line.starts_with("[ffmpeg]") {
         return Some(DownloadEvent::PostProcessing {
             status: line.to_string()
@@ -394,7 +1148,12 @@ fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Opt
 
     if line.contains("has already been downloaded") {
         let filename = current_filename.clone().unwrap_or_default();
-        return Some(DownloadEvent::Finished { filename });
+        return Some(DownloadEvent::Finished {
+            filename,
+            total_bytes: None,
+            elapsed: None,
+            average_speed: None
+        });
     }
 
     if line.starts_with("WARNING:") {
@@ -412,29 +1171,212 @@ fn parse_progress_line(line: &str, current_filename: &mut Option<String>) -> Opt
     None
 }
 
+/// The extractor step in `[youtube] Extracting URL: ...` or
+/// `[youtube] <id>: Downloading webpage`, logged before the first progress line.
+fn resolving_stage(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix('[')?.split_once("] ")?.1;
+    if rest.starts_with("Extracting URL") {
+        return Some("Extracting URL");
+    }
+    let (id, stage) = rest.split_once(": ")?;
+    (!id.is_empty() && !id.contains(char::is_whitespace) && stage.starts_with("Downloading ")).then_some(stage)
+}
+
+/// A video yt-dlp decided not to download because of `--match-filter` or
+/// the file size limits.
+fn rejection_event(line: &str) -> Option<DownloadEvent> {
+    if line.starts_with("[download]")
+        && let Some((_, reason)) = line.split_once(" does not pass filter ")
+    {
+        return Some(DownloadEvent::Skipped {
+            reason: reason.trim_end_matches(['.', ' ']).trim_end_matches(", skipping").to_string()
+        });
+    }
+
+    if let Some(rest) = line.strip_prefix("[download] File is ")
+        && (rest.contains("than max-filesize") || rest.contains("than min-filesize"))
+    {
+        return Some(DownloadEvent::FileSizeRejected {
+            reason: rest.trim_end_matches(['.', ' ']).trim_end_matches(". Aborting").to_string()
+        });
+    }
+
+    None
+}
+
+/// The file a post-processor leaves behind. Audio extraction and remuxing
+/// write a new file and delete the download, so the reported filename has to
+/// follow them.
+fn postprocessed_filename(line: &str) -> Option<&str> {
+    if line.starts_with("[ExtractAudio]") || line.starts_with("[VideoRemuxer]") {
+        if let Some((_, destination)) = line.split_once("Destination:") {
+            return Some(destination.trim());
+        }
+        if let Some(rest) = line.strip_prefix("[ExtractAudio] Not converting audio ")
+            && let Some((filename, _)) = rest.split_once("; file is already in target format")
+        {
+            return Some(filename.trim());
+        }
+    }
+    if let Some(rest) = line.strip_prefix("[MoveFiles] Moving file ")
+        && let Some((_, destination)) = rest.split_once("\" to \"")
+    {
+        return Some(destination.trim_end_matches('"'));
+    }
+    None
+}
+
+/// The intermediate file yt-dlp removes after merging or converting, from
+/// `Deleting original file NAME (pass -k to keep)`.
+fn deleted_original(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("Deleting original file ")?;
+    Some(rest.strip_suffix(" (pass -k to keep)").unwrap_or(rest).trim())
+}
+
+/// Accumulates progress events so the final `Finished` event can carry a summary.
+///
+/// yt-dlp reports each format (e.g. video and audio before a merge) as its own
+/// 0-100% run, so totals are summed per `DownloadStarted` segment.
+struct SummaryTracker {
+    started: Instant,
+    completed_bytes: u64,
+    segment_bytes: Option<u64>
+}
+
+impl SummaryTracker {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            completed_bytes: 0,
+            segment_bytes: None
+        }
+    }
+
+    fn observe(&mut self, event: &DownloadEvent) {
+        match event {
+            DownloadEvent::DownloadStarted { .. } => {
+                self.completed_bytes += self.segment_bytes.take().unwrap_or(0);
+            }
+            DownloadEvent::Progress(progress) => {
+                let bytes = progress.total_bytes.unwrap_or(progress.downloaded_bytes);
+                if bytes > 0 {
+                    self.segment_bytes = Some(bytes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self, filename: String) -> DownloadEvent {
+        let elapsed = self.started.elapsed();
+        self.finish_with_elapsed(filename, elapsed)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn finish_with_elapsed(self, filename: String, elapsed: Duration) -> DownloadEvent {
+        let total = self.completed_bytes + self.segment_bytes.unwrap_or(0);
+        let total_bytes = (total > 0).then_some(total);
+        let secs = elapsed.as_secs_f64();
+        let average_speed = total_bytes
+            .filter(|_| secs > 0.0)
+            .map(|t| t as f64 / secs);
+
+        DownloadEvent::Finished {
+            filename,
+            total_bytes,
+            elapsed: Some(elapsed),
+            average_speed
+        }
+    }
+}
+
+/// The numeric fields of yt-dlp's progress hook dict. Byte counts are
+/// floats in some downloaders (e.g. `total_bytes_estimate`), and any field
+/// may be `null`.
+#[derive(Debug, Deserialize)]
+struct RawProgress {
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+    fragment_index: Option<u32>,
+    fragment_count: Option<u32>
+}
+
+impl RawProgress {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+    fn into_progress(self) -> DownloadProgress {
+        let to_bytes = |value: f64| value.max(0.0) as u64;
+        let downloaded_bytes = self.downloaded_bytes.map_or(0, to_bytes);
+        let total_bytes = self.total_bytes.or(self.total_bytes_estimate).map(to_bytes);
+
+        let percent = match (total_bytes, self.fragment_index, self.fragment_count) {
+            (Some(total), _, _) if total > 0 => {
+                Some((downloaded_bytes as f64 / total as f64 * 100.0).min(100.0))
+            }
+            (_, Some(index), Some(count)) if count > 0 => {
+                Some((f64::from(index) / f64::from(count) * 100.0).min(100.0))
+            }
+            _ => None
+        };
+
+        DownloadProgress {
+            downloaded_bytes,
+            total_bytes,
+            speed: self.speed,
+            eta: self.eta,
+            percent,
+            fragment_index: self.fragment_index,
+            fragment_count: self.fragment_count
+        }
+    }
+}
+
+fn parse_json_progress(json: &str) -> Option<DownloadProgress> {
+    match serde_json::from_str::<RawProgress>(json.trim()) {
+        Ok(raw) => Some(raw.into_progress()),
+        Err(e) => {
+            tracing::debug!(error = %e, "unparseable progress line");
+            None
+        }
+    }
+}
+
+/// Parses human-formatted progress such as
+/// `[download]  45.3% of ~ 50.00MiB at 1.00MiB/s ETA 00:30` or the bare
+/// ` 14.6%  887.84MiB  7.61MiB/s 01:39`. Tokens are classified by shape and
+/// the keyword before them, so `Unknown`, `N/A` and `~` leave only that field
+/// empty.
 fn parse_download_progress(line: &str) -> DownloadProgress {
-    let parts: Vec<&str> = line.split_whitespace().collect();
+    // "Unknown B/s" is one value split over two tokens
+    let line = line.replace("Unknown B/s", "Unknown");
 
     let mut percent: Option<f64> = None;
     let mut total_bytes: Option<u64> = None;
     let mut speed: Option<f64> = None;
     let mut eta: Option<f64> = None;
-
-    for (i, part) in parts.iter().enumerate() {
-        if part.ends_with('%') {
-            percent = part.trim_end_matches('%').parse().ok();
-        } else if part.contains("iB") || part.contains('B') {
-            if i > 0 && parts.get(i - 1).is_some_and(|p| p.ends_with('%')) {
-                total_bytes = parse_size(part);
-            } else if part.contains("/s") {
-                speed = parse_speed(part);
-            }
-        } else if part.contains(':') && !part.starts_with('[') {
-            let prev_is_eta = i > 0 && parts.get(i - 1) == Some(&"ETA");
-            if !prev_is_eta {
-                eta = parse_eta(part);
-            }
+    let mut fragment: Option<(u32, u32)> = None;
+    let mut prev = "";
+
+    for part in line.split_whitespace() {
+        let token = part.trim_start_matches('~');
+        if prev == "(frag" {
+            fragment = parse_fragment(token);
+        } else if percent.is_none() && token.ends_with('%') {
+            percent = token.trim_end_matches('%').parse().ok();
+        } else if prev == "ETA" {
+            eta = parse_eta(token);
+        } else if prev == "in" {
+            // Elapsed time on the final line, not an ETA
+        } else if token.ends_with("/s") {
+            speed = parse_speed(token);
+        } else if percent.is_some() && total_bytes.is_none() && parse_size(token).is_some() {
+            total_bytes = parse_size(token);
+        } else if percent.is_some() && token.contains(':') {
+            eta = parse_eta(token);
         }
+        prev = part;
     }
 
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
@@ -449,11 +1391,17 @@ fn parse_download_progress(line: &str) -> DownloadProgress {
         speed,
         eta,
         percent,
-        fragment_index: None,
-        fragment_count: None
+        fragment_index: fragment.map(|(index, _)| index),
+        fragment_count: fragment.map(|(_, count)| count)
     }
 }
 
+/// The `12/345)` token following `(frag` on DASH/HLS progress lines.
+fn parse_fragment(token: &str) -> Option<(u32, u32)> {
+    let (index, count) = token.trim_end_matches(')').split_once('/')?;
+    Some((index.parse().ok()?, count.parse().ok()?))
+}
+
 fn parse_template_progress(line: &str) -> Option<DownloadProgress> {
     let content = line.trim_start_matches("download:").trim();
     let parts: Vec<&str> = content.split_whitespace().collect();
@@ -615,18 +1563,210 @@ impl DownloadBuilder {
         self
     }
 
+    #[must_use]
+    pub fn write_subtitles(mut self, write: bool) -> Self {
+        self.options.write_subtitles = write;
+        self
+    }
+
+    #[must_use]
+    pub fn write_auto_subtitles(mut self, write: bool) -> Self {
+        self.options.write_auto_subtitles = write;
+        self
+    }
+
+    #[must_use]
+    pub fn subtitles_langs(mut self, langs: Vec<String>) -> Self {
+        self.options.subtitles_langs = langs;
+        self
+    }
+
+    #[must_use]
+    pub fn convert_subtitles(mut self, format: impl Into<String>) -> Self {
+        self.options.convert_subtitles = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn write_thumbnail(mut self, write: bool) -> Self {
+        self.options.write_thumbnail = write;
+        self
+    }
+
+    #[must_use]
+    pub fn convert_thumbnails(mut self, format: impl Into<String>) -> Self {
+        self.options.convert_thumbnails = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn write_info_json(mut self, write: bool) -> Self {
+        self.options.write_info_json = write;
+        self
+    }
+
+    #[must_use]
+    pub fn estimate_size(mut self, estimate: bool) -> Self {
+        self.options.estimate_size = estimate;
+        self
+    }
+
     #[must_use]
     pub fn cookies_file(mut self, path: impl Into<PathBuf>) -> Self {
         self.options.cookies_file = Some(path.into());
         self
     }
 
+    #[must_use]
+    pub fn cookies_from_browser(mut self, browser: impl Into<String>) -> Self {
+        self.options.cookies_from_browser = Some(browser.into());
+        self
+    }
+
     #[must_use]
     pub fn rate_limit(mut self, limit: impl Into<String>) -> Self {
         self.options.rate_limit = Some(limit.into());
         self
     }
 
+    #[must_use]
+    pub fn throttled_rate(mut self, rate: impl Into<String>) -> Self {
+        self.options.throttled_rate = Some(rate.into());
+        self
+    }
+
+    #[must_use]
+    pub fn max_filesize(mut self, size: impl Into<String>) -> Self {
+        self.options.max_filesize = Some(size.into());
+        self
+    }
+
+    #[must_use]
+    pub fn min_filesize(mut self, size: impl Into<String>) -> Self {
+        self.options.min_filesize = Some(size.into());
+        self
+    }
+
+    #[must_use]
+    pub fn concurrent_fragments(mut self, count: u32) -> Self {
+        self.options.concurrent_fragments = Some(count);
+        self
+    }
+
+    #[must_use]
+    pub fn continue_partial(mut self, resume: bool) -> Self {
+        self.options = self.options.continue_partial(resume);
+        self
+    }
+
+    #[must_use]
+    pub fn config_location(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options = self.options.config_location(path);
+        self
+    }
+
+    #[must_use]
+    pub fn live_from_start(mut self, live_from_start: bool) -> Self {
+        self.options.live_from_start = live_from_start;
+        self
+    }
+
+    #[must_use]
+    pub fn wait_for_video(mut self, interval: impl Into<String>) -> Self {
+        self.options.wait_for_video = Some(interval.into());
+        self
+    }
+
+    #[must_use]
+    pub fn match_filter(mut self, filter: impl Into<String>) -> Self {
+        self.options.match_filter = Some(filter.into());
+        self
+    }
+
+    #[must_use]
+    pub fn no_shorts(mut self, no_shorts: bool) -> Self {
+        self.options.no_shorts = no_shorts;
+        self
+    }
+
+    #[must_use]
+    pub fn remux_video(mut self, format: impl Into<String>) -> Self {
+        self.options.remux_video = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn recode_video(mut self, format: impl Into<String>) -> Self {
+        self.options.recode_video = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn postprocessor_args(mut self, postprocessor: impl Into<String>, args: impl Into<String>) -> Self {
+        self.options.postprocessor_args.push((postprocessor.into(), args.into()));
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_items(mut self, items: impl Into<String>) -> Self {
+        self.options.playlist_items = Some(items.into());
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_start(mut self, start: u32) -> Self {
+        self.options.playlist_start = Some(start);
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_end(mut self, end: u32) -> Self {
+        self.options.playlist_end = Some(end);
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_reverse(mut self, reverse: bool) -> Self {
+        self.options.playlist_reverse = reverse;
+        self
+    }
+
+    #[must_use]
+    pub fn max_downloads(mut self, count: u32) -> Self {
+        self.options.max_downloads = Some(count);
+        self
+    }
+
+    #[must_use]
+    pub fn path(mut self, kind: PathType, dir: impl Into<PathBuf>) -> Self {
+        self.options.paths.insert(kind, dir.into());
+        self
+    }
+
+    #[must_use]
+    pub fn restrict_filenames(mut self, restrict: bool) -> Self {
+        self.options.restrict_filenames = restrict;
+        self
+    }
+
+    #[must_use]
+    pub fn windows_filenames(mut self, windows: bool) -> Self {
+        self.options.windows_filenames = windows;
+        self
+    }
+
+    #[must_use]
+    pub fn trim_filenames(mut self, length: u32) -> Self {
+        self.options.trim_filenames = Some(length);
+        self
+    }
+
+    #[must_use]
+    pub fn set_mtime(mut self, set: bool) -> Self {
+        self.options = self.options.set_mtime(set);
+        self
+    }
+
     /// # Errors
     ///
     /// Returns an error if the download command fails.
@@ -658,6 +1798,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::unreadable_literal)]
     fn test_parse_size() {
         assert_eq!(parse_size("100MiB"), Some(104857600));
         assert_eq!(parse_size("1GiB"), Some(1073741824));
@@ -667,6 +1808,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unreadable_literal)]
     fn test_parse_speed() {
         assert_eq!(parse_speed("1MiB/s"), Some(1048576.0));
         assert_eq!(parse_speed("500KiB/s"), Some(512000.0));
@@ -679,6 +1821,185 @@ mod tests {
         assert_eq!(parse_eta("N/A"), None);
     }
 
+    #[test]
+    fn test_final_output_path_uses_audio_extension() {
+        let options = DownloadOptions::new().extract_audio(true).audio_format("mp3");
+        assert_eq!(
+            final_output_path(Path::new("/tmp/out.webm"), &options),
+            PathBuf::from("/tmp/out.mp3")
+        );
+
+        let options = DownloadOptions::new().extract_audio(true).audio_format("vorbis");
+        assert_eq!(final_output_path(Path::new("out"), &options), PathBuf::from("out.ogg"));
+    }
+
+    #[test]
+    fn test_final_output_path_keeps_path_without_known_format() {
+        let options = DownloadOptions::new().extract_audio(true).audio_format("best");
+        assert_eq!(final_output_path(Path::new("out.webm"), &options), PathBuf::from("out.webm"));
+
+        let options = DownloadOptions::new().audio_format("mp3");
+        assert_eq!(final_output_path(Path::new("out.webm"), &options), PathBuf::from("out.webm"));
+    }
+
+    #[test]
+    fn test_download_succeeded_on_max_downloads() {
+        assert!(download_succeeded(Some(0), None));
+        assert!(!download_succeeded(Some(101), None));
+        assert!(!download_succeeded(None, None));
+        assert!(download_succeeded(Some(101), Some(3)));
+        assert!(!download_succeeded(Some(1), Some(3)));
+    }
+
+    #[test]
+    fn test_printed_filepath() {
+        assert_eq!(printed_filepath("[filepath] /media/Video [abc].mkv"), Some("/media/Video [abc].mkv"));
+        assert_eq!(printed_filepath("[filepath] NA"), None);
+        assert_eq!(printed_filepath("[download] Destination: video.mp4"), None);
+    }
+
+    #[test]
+    fn test_download_command_prints_final_path() {
+        let client = YtDlp::new();
+        let options = DownloadOptions::new().output_template("%(title)s.%(ext)s");
+        let args = client
+            .download_command("https://example.com/v", Path::new("/tmp/out"), &options)
+            .get_args()
+            .to_vec();
+        assert!(!args.contains(&"/tmp/out".to_string()));
+        let print = args.iter().position(|arg| arg == "--print").unwrap();
+        assert_eq!(args[print + 1], "after_move:[filepath] %(filepath)s");
+        assert_eq!(args.last().map(String::as_str), Some("https://example.com/v"));
+    }
+
+    #[test]
+    fn test_search_url() {
+        assert_eq!(search_url(&SearchProvider::YouTube, " rust talks ", 5), "ytsearch5:rust talks");
+        assert_eq!(search_url(&SearchProvider::SoundCloud, "ambient", 10), "scsearch10:ambient");
+    }
+
+    #[test]
+    fn test_parse_json_lines() {
+        assert!(parse_json_lines("").is_empty());
+
+        let stdout = "{\"id\":\"a\",\"title\":\"First\"}\n\n{\"id\":\"b\",\"title\":\"Second\"}\n";
+        let results = parse_json_lines(stdout);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].id, "b");
+    }
+
+    #[test]
+    fn test_classify_update_failure() {
+        let err = classify_update_failure(
+            "",
+            "ERROR: You installed yt-dlp with pip or using the wheel from PyPi; Use that to update"
+        );
+        assert!(matches!(err, Error::UpdateUnsupported(ref msg) if msg.starts_with("You installed")));
+
+        let err = classify_update_failure("", "ERROR: Unable to write to /usr/bin/yt-dlp; try running as administrator");
+        assert!(matches!(err, Error::UpdateFailed(ref msg) if msg.starts_with("Unable to write")));
+    }
+
+    #[test]
+    fn test_parse_size_estimate() {
+        assert_eq!(parse_size_estimate("123456789\n"), Some(123_456_789));
+        assert_eq!(parse_size_estimate("\n  52428800  \n"), Some(52_428_800));
+        assert_eq!(parse_size_estimate("NA\n"), None);
+        assert_eq!(parse_size_estimate("0"), None);
+        assert_eq!(parse_size_estimate(""), None);
+    }
+
+    #[test]
+    fn test_size_probe_command() {
+        let client = YtDlp::new();
+        let options = DownloadOptions::new().format(OutputFormat::Custom("bv+ba".to_string()));
+        let args = client.size_probe_command("https://example.com/v", &options).get_args().to_vec();
+        assert_eq!(args, vec![
+            "-f", "bv+ba",
+            "--no-playlist",
+            "--print", "%(filesize,filesize_approx)s",
+            "https://example.com/v"
+        ]);
+    }
+
+    #[test]
+    fn test_playlist_command_modes() {
+        let client = YtDlp::new();
+        let options = DownloadOptions::default();
+        let flat = client.playlist_command("https://example.com/c", true, &options).get_args().to_vec();
+        assert!(flat.contains(&"--flat-playlist".to_string()));
+        assert!(!flat.contains(&"--ignore-errors".to_string()));
+        assert!(!flat.contains(&"--playlist-end".to_string()));
+
+        let full = client.playlist_command("https://example.com/c", false, &options).get_args().to_vec();
+        assert!(!full.contains(&"--flat-playlist".to_string()));
+        assert!(full.contains(&"--ignore-errors".to_string()));
+        assert_eq!(full.last().map(String::as_str), Some("https://example.com/c"));
+    }
+
+    #[test]
+    fn test_playlist_command_selection() {
+        let client = YtDlp::new();
+        let options = DownloadOptions::new().playlist_start(2).playlist_end(20).playlist_reverse(true).max_downloads(5);
+        let args = client.playlist_command("https://example.com/c", true, &options).get_args().to_vec();
+        assert!(args.windows(2).any(|w| w == ["--playlist-start", "2"]));
+        assert!(args.windows(2).any(|w| w == ["--playlist-end", "20"]));
+        assert!(args.contains(&"--playlist-reverse".to_string()));
+        // Nothing is downloaded while listing, so there is no count to stop at
+        assert!(!args.contains(&"--max-downloads".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("https://example.com/c"));
+    }
+
+    #[test]
+    fn test_metadata_command_lists_first_entry_only() {
+        let client = YtDlp::new();
+        let args = client.metadata_command("https://example.com/c").get_args().to_vec();
+        assert!(args.contains(&"--flat-playlist".to_string()));
+        assert!(args.windows(2).any(|w| w == ["--playlist-items", "1"]));
+        assert_eq!(args.last().map(String::as_str), Some("https://example.com/c"));
+    }
+
+    #[test]
+    fn test_parse_playlist_output_flat() {
+        let stdout = concat!(
+            r#"{"id":"a1","title":"First","playlist_id":"UC1","playlist_title":"Chan","channel":"Chan"}"#,
+            "\n",
+            r#"{"id":"b2","title":"Second","playlist_id":"UC1"}"#,
+            "\n"
+        );
+        let info = parse_playlist_output(stdout).unwrap();
+        assert_eq!(info.id, "UC1");
+        assert_eq!(info.channel.as_deref(), Some("Chan"));
+        assert_eq!(info.entries.len(), 2);
+        assert_eq!(info.entries[0].duration, None);
+        assert_eq!(info.entries[0].view_count, None);
+    }
+
+    #[test]
+    fn test_parse_playlist_output_full() {
+        let stdout = concat!(
+            r#"{"id":"a1","title":"First","playlist_id":"UC1","duration":125.0,"view_count":42,"#,
+            r#""thumbnails":[{"url":"https://i.example.com/a1.jpg","width":1280,"height":720}]}"#,
+            "\n",
+            "WARNING: not json\n"
+        );
+        let info = parse_playlist_output(stdout).unwrap();
+        assert_eq!(info.entries.len(), 1);
+        assert_eq!(info.entries[0].duration, Some(125.0));
+        assert_eq!(info.entries[0].view_count, Some(42));
+        assert_eq!(info.entries[0].best_thumbnail(), Some("https://i.example.com/a1.jpg"));
+
+        assert!(parse_playlist_output("").is_none());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("2024.03.10"), Some((2024, 3, 10)));
+        assert_eq!(parse_version("2024.03.10.232650\n"), Some((2024, 3, 10)));
+        assert_eq!(parse_version("not a version"), None);
+        assert_eq!(parse_version("2024.03"), None);
+    }
+
     #[test]
     fn test_parse_progress_line_destination() {
         let mut filename = None;
@@ -690,6 +2011,168 @@ mod tests {
         assert_eq!(filename, Some("video.mp4".to_string()));
     }
 
+    #[test]
+    fn test_parse_progress_line_resolving() {
+        let mut filename = None;
+        let stage = |line: &str| match parse_progress_line(line, &mut None) {
+            Some(DownloadEvent::Resolving { stage }) => Some(stage),
+            _ => None
+        };
+        assert_eq!(
+            stage("[youtube] Extracting URL: https://www.youtube.com/watch?v=dQw4w9WgXcQ").as_deref(),
+            Some("Extracting URL")
+        );
+        assert_eq!(
+            stage("[youtube] dQw4w9WgXcQ: Downloading ios player API JSON").as_deref(),
+            Some("Downloading ios player API JSON")
+        );
+        assert_eq!(
+            stage("[youtube:tab] UCuAXFkgsw1L7xaCfnd5JJOw: Downloading webpage").as_deref(),
+            Some("Downloading webpage")
+        );
+        assert!(stage("[download] Downloading playlist: Uploads").is_none());
+        assert!(stage("[download] Destination: video.mp4").is_none());
+        assert!(matches!(
+            parse_progress_line("[info] dQw4w9WgXcQ: Downloading 1 format(s): 137+140", &mut filename),
+            Some(DownloadEvent::FormatSelected { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_progress_line_format_selected() {
+        let mut filename = None;
+        let event = parse_progress_line(
+            "[info] dQw4w9WgXcQ: Downloading 1 format(s): 137+140",
+            &mut filename
+        );
+        assert!(matches!(
+            event,
+            Some(DownloadEvent::FormatSelected { ref format_id }) if format_id == "137+140"
+        ));
+    }
+
+    #[test]
+    fn test_parse_progress_line_info_json() {
+        let mut filename = None;
+        let event = parse_progress_line(
+            "[info] Writing video metadata as JSON to: /media/Chan/video [abc].info.json",
+            &mut filename
+        );
+        assert!(matches!(
+            event,
+            Some(DownloadEvent::InfoJsonWritten { ref filename }) if filename == "/media/Chan/video [abc].info.json"
+        ));
+        assert_eq!(filename, None);
+    }
+
+    #[test]
+    fn test_parse_download_progress_unknown_fields() {
+        let progress =
+            parse_download_progress("[download]   0.0% of ~ 50.00MiB at Unknown B/s ETA Unknown");
+        assert_eq!(progress.percent, Some(0.0));
+        assert_eq!(progress.total_bytes, Some(52_428_800));
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.eta, None);
+
+        let progress = parse_download_progress("  3.2%   Unknown    Unknown    Unknown");
+        assert_eq!(progress.percent, Some(3.2));
+        assert_eq!(progress.total_bytes, None);
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.eta, None);
+    }
+
+    #[test]
+    fn test_parse_download_progress_full_line() {
+        let progress =
+            parse_download_progress("[download]  45.0% of ~50.00MiB at  1.00MiB/s ETA 00:30 (frag 3/10)");
+        assert_eq!(progress.percent, Some(45.0));
+        assert_eq!(progress.total_bytes, Some(52_428_800));
+        assert_eq!(progress.speed, Some(1_048_576.0));
+        assert_eq!(progress.eta, Some(30.0));
+        assert_eq!(progress.fragment_index, Some(3));
+        assert_eq!(progress.fragment_count, Some(10));
+
+        let progress = parse_download_progress(" 14.6%  887.84MiB    7.61MiB/s 01:39");
+        assert_eq!(progress.percent, Some(14.6));
+        assert_eq!(progress.eta, Some(99.0));
+
+        let progress = parse_download_progress("[download] 100% of 10.00MiB in 00:00:05 at 2.00MiB/s");
+        assert_eq!(progress.percent, Some(100.0));
+        assert_eq!(progress.total_bytes, Some(10_485_760));
+        assert_eq!(progress.speed, Some(2_097_152.0));
+        assert_eq!(progress.eta, None);
+    }
+
+    #[test]
+    fn test_parse_download_progress_fragments() {
+        let progress = parse_download_progress(
+            "[download]  12.3% of ~  1.23GiB at    5.00MiB/s ETA 03:21 (frag 12/345)"
+        );
+        assert_eq!(progress.percent, Some(12.3));
+        assert_eq!(progress.eta, Some(201.0));
+        assert_eq!(progress.fragment_index, Some(12));
+        assert_eq!(progress.fragment_count, Some(345));
+
+        let progress =
+            parse_download_progress("[download]   2.1% of ~ 80.00MiB at Unknown B/s ETA Unknown (frag 0/117)");
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.fragment_index, Some(0));
+        assert_eq!(progress.fragment_count, Some(117));
+
+        let progress = parse_download_progress("[download]  50.0% of 10.00MiB at 1.00MiB/s ETA 00:05");
+        assert_eq!(progress.fragment_index, None);
+        assert_eq!(progress.fragment_count, None);
+    }
+
+    #[test]
+    fn test_parse_download_progress_na_values() {
+        let progress = parse_download_progress("[download]  12.5% of N/A at N/A ETA N/A");
+        assert_eq!(progress.percent, Some(12.5));
+        assert_eq!(progress.total_bytes, None);
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.eta, None);
+    }
+
+    #[test]
+    fn test_parse_json_progress() {
+        let mut filename = None;
+        let line = r#"[progress] {"status": "downloading", "downloaded_bytes": 5242880, "total_bytes": 10485760, "speed": 1048576.5, "eta": 5, "elapsed": 2.1, "_percent_str": " 50.0%"}"#;
+        let Some(DownloadEvent::Progress(progress)) = parse_progress_line(line, &mut filename) else {
+            panic!("expected progress event");
+        };
+        assert_eq!(progress.downloaded_bytes, 5_242_880);
+        assert_eq!(progress.total_bytes, Some(10_485_760));
+        assert_eq!(progress.percent, Some(50.0));
+        assert_eq!(progress.speed, Some(1_048_576.5));
+        assert_eq!(progress.eta, Some(5.0));
+    }
+
+    #[test]
+    fn test_parse_json_progress_estimate_and_nulls() {
+        let progress = parse_json_progress(
+            r#"{"downloaded_bytes": 250, "total_bytes": null, "total_bytes_estimate": 1000.7, "speed": null, "eta": null}"#
+        )
+        .unwrap();
+        assert_eq!(progress.total_bytes, Some(1000));
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.eta, None);
+    }
+
+    #[test]
+    fn test_parse_json_progress_fragments_only() {
+        let progress = parse_json_progress(
+            r#"{"downloaded_bytes": 123456, "fragment_index": 3, "fragment_count": 12}"#
+        )
+        .unwrap();
+        assert_eq!(progress.total_bytes, None);
+        assert_eq!(progress.fragment_index, Some(3));
+        assert_eq!(progress.fragment_count, Some(12));
+        assert_eq!(progress.percent, Some(25.0));
+
+        assert!(parse_json_progress("not json").is_none());
+    }
+
     #[test]
     fn test_parse_progress_line_error() {
         let mut filename = None;
@@ -697,6 +2180,141 @@ mod tests {
         assert!(matches!(event, Some(DownloadEvent::Error { .. })));
     }
 
+    #[test]
+    fn test_parse_progress_line_match_filter_skip() {
+        let mut filename = None;
+        let event = parse_progress_line(
+            "[download] 100% Real Shorts does not pass filter (original_url!*=/shorts/), skipping ..",
+            &mut filename
+        );
+        match event {
+            Some(DownloadEvent::Skipped { reason }) => {
+                assert_eq!(reason, "(original_url!*=/shorts/)");
+            }
+            other => panic!("unexpected event: {other:?}")
+        }
+    }
+
+    #[test]
+    fn test_parse_progress_line_filesize_rejected() {
+        let mut filename = None;
+        for (line, expected) in [
+            (
+                "[download] File is larger than max-filesize (1048576000 bytes > 524288000 bytes). Aborting.",
+                "larger than max-filesize (1048576000 bytes > 524288000 bytes)"
+            ),
+            (
+                "[download] File is smaller than min-filesize (1024 bytes < 51200 bytes). Aborting.",
+                "smaller than min-filesize (1024 bytes < 51200 bytes)"
+            )
+        ] {
+            match parse_progress_line(line, &mut filename) {
+                Some(DownloadEvent::FileSizeRejected { reason }) => assert_eq!(reason, expected),
+                other => panic!("unexpected event: {other:?}")
+            }
+        }
+    }
+
+    #[test]
+    fn test_summary_tracker_sums_formats() {
+        let mut filename = None;
+        let mut summary = SummaryTracker::new();
+        for line in [
+            "[download] Destination: video.f137.mp4",
+            "download: 50.0% 10.00MiB 1.00MiB/s 00:05",
+            "download: 100.0% 10.00MiB 1.00MiB/s 00:00",
+            "[download] Destination: video.f140.m4a",
+            "download: 100.0% 2.00MiB 1.00MiB/s 00:00"
+        ] {
+            if let Some(event) = parse_progress_line(line, &mut filename) {
+                summary.observe(&event);
+            }
+        }
+
+        let event = summary.finish_with_elapsed("video.mp4".to_string(), Duration::from_secs(4));
+        match event {
+            DownloadEvent::Finished { filename, total_bytes, elapsed, average_speed } => {
+                assert_eq!(filename, "video.mp4");
+                assert_eq!(total_bytes, Some(12 * 1024 * 1024));
+                assert_eq!(elapsed, Some(Duration::from_secs(4)));
+                assert_eq!(average_speed, Some(3.0 * 1024.0 * 1024.0));
+            }
+            other => panic!("unexpected event: {other:?}")
+        }
+    }
+
+    #[test]
+    fn test_parse_progress_line_follows_postprocessed_file() {
+        let mut filename = None;
+        for line in [
+            "[download] Destination: Video [abc].webm",
+            "[download] 100% of 10.00MiB in 00:00:05",
+            "[ExtractAudio] Destination: Video [abc].mp3"
+        ] {
+            parse_progress_line(line, &mut filename);
+        }
+        assert_eq!(filename.as_deref(), Some("Video [abc].mp3"));
+
+        parse_progress_line(
+            "[ExtractAudio] Not converting audio Video [abc].m4a; file is already in target format m4a",
+            &mut filename
+        );
+        assert_eq!(filename.as_deref(), Some("Video [abc].m4a"));
+
+        parse_progress_line(
+            "[VideoRemuxer] Remuxing video from webm to mp4; Destination: Video [abc].mp4",
+            &mut filename
+        );
+        assert_eq!(filename.as_deref(), Some("Video [abc].mp4"));
+
+        parse_progress_line(
+            r#"[MoveFiles] Moving file "/tmp/Video [abc].mp4" to "/media/Video [abc].mp4""#,
+            &mut filename
+        );
+        assert_eq!(filename.as_deref(), Some("/media/Video [abc].mp4"));
+
+        assert!(parse_progress_line("[filepath] /media/100% Video [abc].mkv", &mut filename).is_none());
+        assert_eq!(filename.as_deref(), Some("/media/100% Video [abc].mkv"));
+    }
+
+    #[test]
+    fn test_parse_progress_line_reports_renames() {
+        let mut filename = Some("Video [abc].webm".to_string());
+        match parse_progress_line("[ExtractAudio] Destination: Video [abc].mp3", &mut filename) {
+            Some(DownloadEvent::FileRenamed { from, to }) => {
+                assert_eq!(from, "Video [abc].webm");
+                assert_eq!(to, "Video [abc].mp3");
+            }
+            other => panic!("unexpected event: {other:?}")
+        }
+        assert!(matches!(
+            parse_progress_line("[ExtractAudio] Deleting original file Video [abc].webm (pass -k to keep)", &mut filename),
+            Some(DownloadEvent::PostProcessing { .. })
+        ));
+        assert_eq!(filename.as_deref(), Some("Video [abc].mp3"));
+    }
+
+    #[test]
+    fn test_parse_progress_line_forgets_deleted_file() {
+        let mut filename = Some("Video [abc].f137.mp4".to_string());
+        parse_progress_line("Deleting original file Video [abc].f137.mp4 (pass -k to keep)", &mut filename);
+        assert_eq!(filename, None);
+
+        let mut filename = Some("Video [abc].mkv".to_string());
+        parse_progress_line("Deleting original file Video [abc].f140.m4a (pass -k to keep)", &mut filename);
+        assert_eq!(filename.as_deref(), Some("Video [abc].mkv"));
+    }
+
+    #[test]
+    fn test_summary_tracker_without_progress() {
+        let summary = SummaryTracker::new();
+        let event = summary.finish_with_elapsed("video.mp4".to_string(), Duration::from_secs(1));
+        assert!(matches!(
+            event,
+            DownloadEvent::Finished { total_bytes: None, average_speed: None, .. }
+        ));
+    }
+
     #[test]
     fn test_ytdlp_default() {
         let client = YtDlp::default();
@@ -723,6 +2341,16 @@ mod tests {
         assert_eq!(client.extra_args.len(), 2);
     }
 
+    #[test]
+    fn test_ytdlp_cookies_file_wins_over_browser() {
+        let mut client = YtDlp::new();
+        client.set_cookies_from_browser(Some("firefox".to_string()));
+        assert_eq!(client.command().get_args(), &["--cookies-from-browser", "firefox"]);
+
+        client.set_cookies_file(Some(PathBuf::from("/tmp/cookies.txt")));
+        assert_eq!(client.command().get_args(), &["--cookies", "/tmp/cookies.txt"]);
+    }
+
     #[test]
     fn test_ytdlp_set_binary() {
         let mut client = YtDlp::new();
@@ -737,10 +2365,166 @@ mod tests {
         assert_eq!(client.ffmpeg_location, Some(PathBuf::from("/usr/local/bin/ffmpeg")));
     }
 
+    #[test]
+    fn test_ytdlp_cache_dir_emitted() {
+        let mut client = YtDlp::new();
+        assert!(!client.command().get_args().contains(&"--cache-dir".to_string()));
+        client.set_cache_dir(Some(PathBuf::from("/data/cache")));
+        assert_eq!(client.command().get_args(), &["--cache-dir", "/data/cache"]);
+    }
+
+    #[test]
+    fn test_ytdlp_config_location_emitted() {
+        let client = YtDlp::with_config("/config/yt-dlp.conf");
+        assert_eq!(client.command().get_args(), &["--config-locations", "/config/yt-dlp.conf"]);
+
+        let mut client = YtDlp::new();
+        assert!(client.command().get_args().is_empty());
+        client.set_config_location(Some(PathBuf::from("/config")));
+        client.set_cache_dir(Some(PathBuf::from("/data/cache")));
+        assert_eq!(
+            client.command().get_args(),
+            &["--config-locations", "/config", "--cache-dir", "/data/cache"]
+        );
+    }
+
+    #[test]
+    fn test_progress_command_passes_url_once() {
+        let client = YtDlp::with_binary("yt-dlp");
+        let args = client
+            .progress_command("https://example.com/v", Path::new("/media/Chan/v.mp4"), &DownloadOptions::default())
+            .get_args()
+            .to_vec();
+        assert_eq!(args.iter().filter(|arg| *arg == "https://example.com/v").count(), 1);
+        assert!(args.iter().any(|arg| arg == "--newline"));
+    }
+
+    #[tokio::test]
+    async fn test_check_binary_uses_cached_version() {
+        let mut client = YtDlp::with_binary("/nonexistent/yt-dlp");
+        assert!(client.check_binary().await.is_err());
+
+        client.lock_version_cache().replace(("2025.01.01".to_string(), Instant::now()));
+        let clone = client.clone();
+        assert_eq!(clone.check_binary().await.unwrap(), "2025.01.01");
+
+        client.lock_version_cache().replace(("2025.01.01".to_string(), Instant::now().checked_sub(CHECK_BINARY_TTL).unwrap()));
+        assert!(client.check_binary().await.is_err());
+
+        client.lock_version_cache().replace(("2025.01.01".to_string(), Instant::now()));
+        client.set_binary(PathBuf::from("/nonexistent/other"));
+        assert!(client.check_binary().await.is_err());
+        assert_eq!(clone.check_binary().await.unwrap(), "2025.01.01");
+    }
+
+    #[test]
+    fn test_ytdlp_impersonate_emitted() {
+        let mut client = YtDlp::new();
+        client.set_impersonate(Some("chrome".to_string()));
+        assert_eq!(client.command().get_args(), &["--impersonate", "chrome"]);
+    }
+
+    #[test]
+    fn test_command_failed_detects_missing_impersonation() {
+        let err = command_failed(
+            Some(2),
+            "ERROR: Impersonate target \"chrome\" is not available. Use --list-impersonate-targets to see \
+             available targets. You may be missing dependencies required to support this target."
+        );
+        assert!(matches!(err, Error::ImpersonationUnavailable(ref target) if target == "chrome"));
+
+        let err = command_failed(Some(1), "ERROR: [youtube] abc: Video unavailable");
+        assert!(matches!(err, Error::VideoUnavailable(ref message) if message == "[youtube] abc: Video unavailable"));
+
+        let err = command_failed(Some(1), "ERROR: [youtube] abc: HTTP Error 429: Too Many Requests");
+        assert!(matches!(err, Error::RateLimited(_)));
+
+        let err = command_failed(Some(1), "ERROR: something unexpected");
+        assert!(matches!(err, Error::CommandFailed { code: 1, .. }));
+    }
+
+    #[test]
+    fn test_subtitles_command() {
+        let client = YtDlp::with_binary("yt-dlp");
+        let args = client
+            .subtitles_command("https://example.com/v", &["en".to_string(), "de".to_string()], Path::new("/media/Chan"))
+            .get_args()
+            .to_vec();
+        assert_eq!(args, [
+            "--no-quiet", "--skip-download", "--no-playlist", "--write-subs", "--sub-langs", "en,de",
+            "-o", "/media/Chan/%(id)s.%(ext)s", "https://example.com/v"
+        ]);
+
+        let args = client.subtitles_command("https://example.com/v", &[], Path::new("/tmp")).get_args().to_vec();
+        assert!(args.windows(2).any(|pair| pair == ["--sub-langs", "all"]));
+    }
+
+    #[test]
+    fn test_written_subtitle() {
+        assert_eq!(
+            written_subtitle("[info] Writing video subtitles to: /media/Chan/abc.en.vtt"),
+            Some(PathBuf::from("/media/Chan/abc.en.vtt"))
+        );
+        assert_eq!(written_subtitle("[info] There are no subtitles for the requested languages"), None);
+        assert_eq!(written_subtitle("[info] abc: Downloading subtitles: en"), None);
+    }
+
+    #[test]
+    fn test_command_failed_detects_unavailable_format() {
+        let err = command_failed(
+            Some(1),
+            "WARNING: something else\nERROR: [youtube] abc: Requested format is not available. \
+             Use --list-formats for a list of available formats\n"
+        );
+        assert!(matches!(err, Error::FormatUnavailable(ref message) if message.starts_with("[youtube] abc")));
+    }
+
+    #[test]
+    fn test_impersonate_target_available() {
+        let listing = "[info] Available impersonate targets\n\
+            Client      OS          Source\n\
+            ---------------------------------------\n\
+            Chrome-124  Macos-14    curl_cffi\n\
+            Safari-17.0 Ios-17      curl_cffi\n\
+            Edge        -           curl_cffi (unavailable)\n";
+        assert!(impersonate_target_available(listing, "chrome"));
+        assert!(impersonate_target_available(listing, "Chrome-124:macos"));
+        assert!(impersonate_target_available(listing, "safari:ios-17"));
+        assert!(!impersonate_target_available(listing, "edge"));
+        assert!(!impersonate_target_available(listing, "chrome:windows"));
+        assert!(!impersonate_target_available(listing, "firefox"));
+    }
+
+    #[test]
+    fn test_ytdlp_credentials_emitted() {
+        let mut client = YtDlp::new();
+        client.set_username(Some("user".to_string()));
+        client.set_password(Some("hunter2".to_string()));
+        client.set_video_password(Some("vidpass".to_string()));
+        client.set_twofactor(Some("123456".to_string()));
+        let builder = client.command();
+        assert_eq!(builder.get_args(), &[
+            "--username", "user",
+            "--password", "hunter2",
+            "--video-password", "vidpass",
+            "--twofactor", "123456"
+        ]);
+    }
+
+    #[test]
+    fn test_ytdlp_debug_redacts_credentials() {
+        let mut client = YtDlp::new();
+        client.set_password(Some("hunter2".to_string()));
+        let debug = format!("{client:?}");
+        assert!(!debug.contains("hunter2"));
+    }
+
     #[test]
     fn test_ytdlp_env_vars() {
         let mut client = YtDlp::new();
         client.set_env("PATH_PREPEND".to_string(), "/opt/bin".to_string());
         assert_eq!(client.env_vars.get("PATH_PREPEND"), Some(&"/opt/bin".to_string()));
+        client.remove_env("PATH_PREPEND");
+        assert!(client.env_vars.is_empty());
     }
 }
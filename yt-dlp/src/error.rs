@@ -14,6 +14,9 @@ pub enum Error {
     #[error("yt-dlp command failed with exit code {code}: {stderr}")]
     CommandFailed { code: i32, stderr: String },
 
+    #[error("impersonate target {0:?} is not available; install yt-dlp with curl_cffi support (yt-dlp[default,curl-cffi])")]
+    ImpersonationUnavailable(String),
+
     #[error("failed to parse JSON output: {0}")]
     JsonParseFailed(#[from] serde_json::Error),
 
@@ -23,20 +26,170 @@ pub enum Error {
     #[error("download failed: {0}")]
     DownloadFailed(String),
 
+    #[error("invalid download option: {0}")]
+    InvalidOption(String),
+
+    #[error("invalid output template: {0}")]
+    InvalidTemplate(String),
+
     #[error("unsupported format: {0}")]
     UnsupportedFormat(String),
 
+    #[error("requested format is not available: {0}")]
+    FormatUnavailable(String),
+
     #[error("no formats available")]
     NoFormatsAvailable,
 
     #[error("video unavailable: {0}")]
     VideoUnavailable(String),
 
+    #[error("video is not available in this country: {0}")]
+    GeoRestricted(String),
+
+    #[error("video is age-restricted: {0}")]
+    AgeRestricted(String),
+
+    #[error("video is for channel members only: {0}")]
+    MembersOnly(String),
+
+    #[error("rate limited by the site: {0}")]
+    RateLimited(String),
+
     #[error("playlist is empty")]
     EmptyPlaylist,
 
     #[error("operation cancelled")]
-    Cancelled
+    Cancelled,
+
+    #[error("yt-dlp cannot update itself: {0}")]
+    UpdateUnsupported(String),
+
+    #[error("yt-dlp update failed: {0}")]
+    UpdateFailed(String),
+
+    #[error("unrecognized yt-dlp version: {0}")]
+    InvalidVersion(String),
+
+    #[error("yt-dlp {installed} is older than required {required}")]
+    VersionTooOld { installed: String, required: String }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// Lowercase substrings and the variant a message containing one maps to.
+type MessagePattern = (&'static [&'static str], fn(String) -> Error);
+
+/// Checked in order: geo blocks and members-only videos are also reported as
+/// "Video unavailable", so those patterns come first.
+const MESSAGE_PATTERNS: &[MessagePattern] = &[
+    (&["http error 429", "too many requests", "rate-limited", "rate limited"], Error::RateLimited),
+    (
+        &["members-only", "join this channel to get access", "available to this channel's members"],
+        Error::MembersOnly
+    ),
+    (&["confirm your age", "age-restricted", "inappropriate for some users"], Error::AgeRestricted),
+    (&["available in your country", "geo restriction", "geo-restricted"], Error::GeoRestricted),
+    (
+        &[
+            "video unavailable",
+            "private video",
+            "video is private",
+            "has been removed",
+            "no longer available",
+            "has been terminated",
+            "copyright claim"
+        ],
+        Error::VideoUnavailable
+    ),
+    (&["requested format is not available"], Error::FormatUnavailable),
+    (&["unsupported url"], Error::InvalidUrl)
+];
+
+impl Error {
+    /// The specific error for a yt-dlp `ERROR:` message, with or without the
+    /// prefix, or `None` when it matches no known pattern.
+    #[must_use]
+    pub fn from_message(message: &str) -> Option<Self> {
+        let message = message.trim().trim_start_matches("ERROR:").trim();
+        let lower = message.to_lowercase();
+        MESSAGE_PATTERNS
+            .iter()
+            .find(|(patterns, _)| patterns.iter().any(|pattern| lower.contains(pattern)))
+            .map(|(_, variant)| variant(message.to_string()))
+    }
+
+    /// The first `ERROR:` line of a command's stderr that [`Self::from_message`]
+    /// recognizes.
+    #[must_use]
+    pub fn from_stderr(stderr: &str) -> Option<Self> {
+        stderr
+            .lines()
+            .filter(|line| line.trim_start().starts_with("ERROR:"))
+            .find_map(Self::from_message)
+    }
+
+    /// Whether the same request may succeed when tried again later.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::RateLimited(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(message: &str) -> Option<String> {
+        Error::from_message(message).map(|e| format!("{e:?}"))
+    }
+
+    #[test]
+    fn test_from_message() {
+        let kind = |message: &str| classify(message).map(|debug| debug.split('(').next().unwrap().to_string());
+        assert_eq!(kind("ERROR: [youtube] abc: Video unavailable. This video is private").as_deref(), Some("VideoUnavailable"));
+        assert_eq!(kind("ERROR: [youtube] abc: Private video. Sign in if you've been granted access to this video").as_deref(), Some("VideoUnavailable"));
+        assert_eq!(
+            kind("ERROR: [youtube] abc: Video unavailable. This video has been removed by the uploader").as_deref(),
+            Some("VideoUnavailable")
+        );
+        assert_eq!(
+            kind("ERROR: [youtube] abc: Video unavailable. The uploader has not made this video available in your country").as_deref(),
+            Some("GeoRestricted")
+        );
+        assert_eq!(
+            kind("ERROR: [youtube] abc: Sign in to confirm your age. This video may be inappropriate for some users.").as_deref(),
+            Some("AgeRestricted")
+        );
+        assert_eq!(
+            kind("ERROR: [youtube] abc: Join this channel to get access to members-only content like this video, and other exclusive perks.").as_deref(),
+            Some("MembersOnly")
+        );
+        assert_eq!(
+            kind("ERROR: unable to download video data: HTTP Error 429: Too Many Requests").as_deref(),
+            Some("RateLimited")
+        );
+        assert_eq!(
+            kind("ERROR: [youtube] abc: Requested format is not available. Use --list-formats for a list of available formats").as_deref(),
+            Some("FormatUnavailable")
+        );
+        assert_eq!(kind("ERROR: Unsupported URL: https://example.com/").as_deref(), Some("InvalidUrl"));
+        assert_eq!(kind("ERROR: [Errno 104] Connection reset by peer"), None);
+    }
+
+    #[test]
+    fn test_from_message_keeps_the_message() {
+        let err = Error::from_message("ERROR: [youtube] abc: Private video").unwrap();
+        assert!(matches!(err, Error::VideoUnavailable(ref message) if message == "[youtube] abc: Private video"));
+        assert!(!err.is_transient());
+        assert!(Error::from_message("HTTP Error 429: Too Many Requests").unwrap().is_transient());
+    }
+
+    #[test]
+    fn test_from_stderr() {
+        let stderr = "WARNING: [youtube] abc: video unavailable in some formats\n\
+                      ERROR: [youtube] abc: Sign in to confirm your age\n";
+        assert!(matches!(Error::from_stderr(stderr), Some(Error::AgeRestricted(_))));
+        assert!(Error::from_stderr("ERROR: something else went wrong").is_none());
+    }
+}
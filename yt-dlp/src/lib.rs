@@ -32,9 +32,10 @@ mod command;
 pub mod error;
 pub mod types;
 
-pub use client::{DownloadBuilder, YtDlp};
+pub use client::{DownloadBuilder, YtDlp, parse_version};
 pub use error::{Error, Result};
 pub use types::{
-    Chapter, Container, DownloadEvent, DownloadOptions, DownloadProgress, Format, OutputFormat,
-    PlaylistInfo, Thumbnail, VideoInfo
+    Chapter, Container, DateRange, DownloadEvent, DownloadOptions, DownloadProgress, Format, OutputFormat,
+    PathType, PlaylistInfo, SearchProvider, Thumbnail, VideoInfo, YtDlpVersion, format_bytes,
+    is_valid_browser_spec, is_valid_date, is_valid_filesize, validate_output_template
 };
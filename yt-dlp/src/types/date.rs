@@ -0,0 +1,262 @@
+use std::fmt;
+
+use super::version::{civil_from_days, days_from_civil, today_utc};
+
+/// An inclusive upload date range using yt-dlp's `--dateafter`/`--datebefore`
+/// syntax. Bounds are resolved against today's date when parsed, so relative
+/// forms such as `now-1month` move with the calendar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DateRange {
+    after: Option<String>,
+    before: Option<String>
+}
+
+impl DateRange {
+    /// Parses optional `after` and `before` bounds; blank strings are unset.
+    /// Accepts `YYYYMMDD`, `YYYY-MM-DD`, `now`, `today` or `yesterday`,
+    /// optionally followed by `+N`/`-N` and a unit of `day`, `week`, `month`
+    /// or `year` (plural allowed).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidOption`] for an unrecognised date.
+    pub fn parse(after: Option<&str>, before: Option<&str>) -> crate::Result<Self> {
+        Self::parse_relative_to(after, before, today_utc())
+    }
+
+    fn parse_relative_to(
+        after: Option<&str>,
+        before: Option<&str>,
+        today: (u32, u32, u32)
+    ) -> crate::Result<Self> {
+        let resolve = |expr: Option<&str>| -> crate::Result<Option<String>> {
+            let Some(expr) = expr.map(str::trim).filter(|e| !e.is_empty()) else {
+                return Ok(None);
+            };
+            resolve_date(expr, today)
+                .map(|date| Some(format_date(date)))
+                .ok_or_else(|| crate::Error::InvalidOption(format!("date: {expr}")))
+        };
+        Ok(Self { after: resolve(after)?, before: resolve(before)? })
+    }
+
+    #[must_use]
+    pub fn is_unbounded(&self) -> bool {
+        self.after.is_none() && self.before.is_none()
+    }
+
+    /// Whether a `YYYYMMDD` upload date falls inside the range.
+    #[must_use]
+    pub fn contains(&self, upload_date: &str) -> bool {
+        self.after.as_deref().is_none_or(|after| upload_date >= after)
+            && self.before.as_deref().is_none_or(|before| upload_date <= before)
+    }
+
+    /// The range as a `--match-filter` expression, which yt-dlp checks once
+    /// the full metadata is known, unlike the flat listing used for syncs.
+    #[must_use]
+    pub fn match_filter(&self) -> Option<String> {
+        let bounds: Vec<String> = [(">=", &self.after), ("<=", &self.before)]
+            .into_iter()
+            .filter_map(|(op, bound)| bound.as_ref().map(|date| format!("upload_date {op} {date}")))
+            .collect();
+        (!bounds.is_empty()).then(|| bounds.join(" & "))
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}..{}",
+            self.after.as_deref().unwrap_or(""),
+            self.before.as_deref().unwrap_or("")
+        )
+    }
+}
+
+/// Whether `expr` is a date yt-dlp's `--dateafter`/`--datebefore` accept.
+#[must_use]
+pub fn is_valid_date(expr: &str) -> bool {
+    resolve_date(expr.trim(), today_utc()).is_some()
+}
+
+fn resolve_date(expr: &str, today: (u32, u32, u32)) -> Option<(u32, u32, u32)> {
+    let expr = expr.to_ascii_lowercase();
+    if let Some((start, offset)) = split_offset(&expr) {
+        return apply_offset(resolve_start(start, today)?, offset);
+    }
+    resolve_start(&expr, today)
+}
+
+fn resolve_start(start: &str, today: (u32, u32, u32)) -> Option<(u32, u32, u32)> {
+    match start {
+        "now" | "today" => Some(today),
+        "yesterday" => Some(civil_from_days(days_from_civil(today) - 1)),
+        _ => parse_calendar_date(start)
+    }
+}
+
+/// The four-digit years yt-dlp's dates can express.
+const MIN_YEAR: u32 = 1;
+const MAX_YEAR: u32 = 9999;
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year
+}
+
+/// Splits `now-2weeks` into `("now", -2 weeks)`.
+fn split_offset(expr: &str) -> Option<(&str, (i64, Unit))> {
+    let body = expr.strip_suffix('s').unwrap_or(expr);
+    let (rest, unit) = [("day", Unit::Day), ("week", Unit::Week), ("month", Unit::Month), ("year", Unit::Year)]
+        .into_iter()
+        .find_map(|(name, unit)| body.strip_suffix(name).map(|rest| (rest, unit)))?;
+
+    let digits_start = rest.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (signed, digits) = rest.split_at(digits_start);
+    let amount: i64 = digits.parse().ok()?;
+    let (start, sign) = if let Some(start) = signed.strip_suffix('-') {
+        (start, -1)
+    } else {
+        (signed.strip_suffix('+')?, 1)
+    };
+    Some((start, (amount.checked_mul(sign)?, unit)))
+}
+
+/// `None` when the result overflows or leaves years 1 to 9999.
+fn apply_offset(date: (u32, u32, u32), (amount, unit): (i64, Unit)) -> Option<(u32, u32, u32)> {
+    match unit {
+        Unit::Day => add_days(date, amount),
+        Unit::Week => add_days(date, amount.checked_mul(7)?),
+        Unit::Month => add_months(date, amount),
+        Unit::Year => add_months(date, amount.checked_mul(12)?)
+    }
+}
+
+fn add_days(date: (u32, u32, u32), days: i64) -> Option<(u32, u32, u32)> {
+    let days = days_from_civil(date).checked_add(days)?;
+    let supported = days_from_civil((MIN_YEAR, 1, 1))..=days_from_civil((MAX_YEAR, 12, 31));
+    supported.contains(&days).then(|| civil_from_days(days))
+}
+
+/// Calendar month arithmetic; the day is clamped so Mar 31 - 1 month is Feb 28/29.
+fn add_months((year, month, day): (u32, u32, u32), months: i64) -> Option<(u32, u32, u32)> {
+    let total = (i64::from(year) * 12 + i64::from(month) - 1).checked_add(months)?;
+    let year = u32::try_from(total.div_euclid(12)).ok().filter(|y| (MIN_YEAR..=MAX_YEAR).contains(y))?;
+    let month = u32::try_from(total.rem_euclid(12) + 1).ok()?;
+    Some((year, month, day.min(days_in_month(year, month))))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+fn parse_calendar_date(date: &str) -> Option<(u32, u32, u32)> {
+    let digits: String = date.chars().filter(|&c| c != '-').collect();
+    if digits.len() != 8 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if date.contains('-') && !(date.len() == 10 && &date[4..5] == "-" && &date[7..8] == "-") {
+        return None;
+    }
+    let year = digits[..4].parse().ok()?;
+    let month = digits[4..6].parse().ok()?;
+    let day = digits[6..].parse().ok()?;
+    if year < MIN_YEAR || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+fn format_date((year, month, day): (u32, u32, u32)) -> String {
+    format!("{year:04}{month:02}{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TODAY: (u32, u32, u32) = (2024, 3, 31);
+
+    fn resolve(expr: &str) -> Option<String> {
+        resolve_date(expr, TODAY).map(format_date)
+    }
+
+    #[test]
+    fn test_resolve_absolute_dates() {
+        assert_eq!(resolve("20240115").as_deref(), Some("20240115"));
+        assert_eq!(resolve("2024-01-15").as_deref(), Some("20240115"));
+        assert_eq!(resolve("20240230"), None);
+        assert_eq!(resolve("2024-0115"), None);
+        assert_eq!(resolve("last tuesday"), None);
+    }
+
+    #[test]
+    fn test_resolve_relative_dates() {
+        assert_eq!(resolve("now").as_deref(), Some("20240331"));
+        assert_eq!(resolve("Today").as_deref(), Some("20240331"));
+        assert_eq!(resolve("yesterday").as_deref(), Some("20240330"));
+        assert_eq!(resolve("now-10days").as_deref(), Some("20240321"));
+        assert_eq!(resolve("today+1day").as_deref(), Some("20240401"));
+        assert_eq!(resolve("now-2weeks").as_deref(), Some("20240317"));
+        assert_eq!(resolve("now-1month").as_deref(), Some("20240229"));
+        assert_eq!(resolve("now-13months").as_deref(), Some("20230228"));
+        assert_eq!(resolve("now-1year").as_deref(), Some("20230331"));
+        assert_eq!(resolve("20240115-1week").as_deref(), Some("20240108"));
+        assert_eq!(resolve("yesterday-1day").as_deref(), Some("20240329"));
+        assert_eq!(resolve("now-month"), None);
+        assert_eq!(resolve("now*1day"), None);
+    }
+
+    #[test]
+    fn test_resolve_extreme_offsets() {
+        assert_eq!(resolve("now-9223372036854775807days"), None);
+        assert_eq!(resolve("now+9223372036854775807days"), None);
+        assert_eq!(resolve("now-9223372036854775807weeks"), None);
+        assert_eq!(resolve("now+9223372036854775807months"), None);
+        assert_eq!(resolve("now-9223372036854775807years"), None);
+        assert_eq!(resolve("now-9223372036854775808days"), None);
+        assert_eq!(resolve("now-2024years"), None);
+        assert_eq!(resolve("now+7976years"), None);
+        assert_eq!(resolve("99991231+1day"), None);
+        assert_eq!(resolve("00010101-1day"), None);
+        assert_eq!(resolve("00000101"), None);
+        assert_eq!(resolve("now-2023years").as_deref(), Some("00010331"));
+        assert_eq!(resolve("99991231").as_deref(), Some("99991231"));
+    }
+
+    #[test]
+    fn test_date_range_contains() {
+        let range = DateRange::parse_relative_to(Some("now-1month"), None, TODAY).unwrap();
+        assert!(!range.is_unbounded());
+        assert!(range.contains("20240229"));
+        assert!(range.contains("20240331"));
+        assert!(!range.contains("20240228"));
+
+        let range = DateRange::parse_relative_to(Some(" "), Some("20231231"), TODAY).unwrap();
+        assert!(range.contains("20000101"));
+        assert!(!range.contains("20240101"));
+        assert_eq!(range.to_string(), "..20231231");
+        assert_eq!(range.match_filter().as_deref(), Some("upload_date <= 20231231"));
+
+        let range = DateRange::parse_relative_to(Some("20240101"), Some("20240131"), TODAY).unwrap();
+        assert_eq!(
+            range.match_filter().as_deref(),
+            Some("upload_date >= 20240101 & upload_date <= 20240131")
+        );
+
+        assert!(DateRange::parse_relative_to(None, None, TODAY).unwrap().is_unbounded());
+        assert_eq!(DateRange::default().match_filter(), None);
+        assert!(DateRange::parse_relative_to(Some("soon"), None, TODAY).is_err());
+    }
+}
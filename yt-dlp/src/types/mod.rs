@@ -1,7 +1,14 @@
+mod date;
 mod options;
 mod progress;
+mod version;
 mod video_info;
 
-pub use options::{Container, DownloadOptions, OutputFormat};
-pub use progress::{DownloadEvent, DownloadProgress};
+pub use date::{DateRange, is_valid_date};
+pub use options::{
+    Container, DownloadOptions, OutputFormat, PathType, SearchProvider, is_valid_browser_spec,
+    is_valid_filesize, validate_output_template
+};
+pub use progress::{DownloadEvent, DownloadProgress, format_bytes};
+pub use version::YtDlpVersion;
 pub use video_info::{Chapter, Format, PlaylistInfo, Thumbnail, VideoInfo};
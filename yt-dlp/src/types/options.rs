@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Default)]
@@ -8,6 +9,8 @@ pub enum OutputFormat {
     Worst,
     BestVideo,
     BestAudio,
+    /// The best video no taller than the given height, e.g. 1080.
+    MaxHeight(u32),
     Custom(String)
 }
 
@@ -18,11 +21,70 @@ impl OutputFormat {
             OutputFormat::Default => None,
             OutputFormat::Best => Some("best".to_string()),
             OutputFormat::Worst => Some("worst".to_string()),
-            OutputFormat::BestVideo => Some("bestvideo".to_string()),
-            OutputFormat::BestAudio => Some("bestaudio".to_string()),
+            OutputFormat::BestVideo => Some("bestvideo/best".to_string()),
+            OutputFormat::BestAudio => Some("bestaudio/best".to_string()),
+            OutputFormat::MaxHeight(height) => {
+                Some(format!("bv*[height<={height}]+ba/b[height<={height}]/best"))
+            }
             OutputFormat::Custom(s) => Some(s.clone())
         }
     }
+
+    /// The format to retry with when yt-dlp reports this one as not
+    /// available, or `None` when there is nothing broader to fall back to.
+    #[must_use]
+    pub fn fallback(&self) -> Option<OutputFormat> {
+        match self {
+            OutputFormat::Default | OutputFormat::Best | OutputFormat::Worst => None,
+            _ => Some(OutputFormat::Best)
+        }
+    }
+}
+
+/// File categories that `--paths` can route to their own directory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathType {
+    Home,
+    Temp,
+    Subtitle,
+    Thumbnail,
+    Description,
+    InfoJson,
+    Custom(String)
+}
+
+impl PathType {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            PathType::Home => "home",
+            PathType::Temp => "temp",
+            PathType::Subtitle => "subtitle",
+            PathType::Thumbnail => "thumbnail",
+            PathType::Description => "description",
+            PathType::InfoJson => "infojson",
+            PathType::Custom(s) => s.as_str()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum SearchProvider {
+    #[default]
+    YouTube,
+    SoundCloud,
+    Custom(String)
+}
+
+impl SearchProvider {
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        match self {
+            SearchProvider::YouTube => "ytsearch",
+            SearchProvider::SoundCloud => "scsearch",
+            SearchProvider::Custom(s) => s.as_str()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -69,11 +131,53 @@ pub struct DownloadOptions {
     pub audio_format: Option<String>,
     pub audio_quality: Option<String>,
     pub subtitles_langs: Vec<String>,
+    pub convert_subtitles: Option<String>,
     pub write_subtitles: bool,
+    pub write_auto_subtitles: bool,
     pub write_thumbnail: bool,
+    pub convert_thumbnails: Option<String>,
+    pub write_info_json: bool,
     pub cookies_file: Option<PathBuf>,
+    /// `BROWSER[+KEYRING][:PROFILE]`; ignored when `cookies_file` is set.
+    pub cookies_from_browser: Option<String>,
     pub rate_limit: Option<String>,
+    pub throttled_rate: Option<String>,
+    /// Size limits such as `500M` (`--max-filesize`/`--min-filesize`).
+    pub max_filesize: Option<String>,
+    pub min_filesize: Option<String>,
     pub concurrent_fragments: Option<u32>,
+    /// Restart partially downloaded files (`--no-continue`) instead of
+    /// resuming them. Set through [`Self::continue_partial`].
+    pub no_continue: bool,
+    pub remux_video: Option<String>,
+    pub recode_video: Option<String>,
+    /// `(postprocessor, args)` pairs, each emitted as `--postprocessor-args PP:ARGS`.
+    pub postprocessor_args: Vec<(String, String)>,
+    pub playlist_items: Option<String>,
+    /// 1-based bounds of the entries to download; `playlist_items` wins when
+    /// both are set.
+    pub playlist_start: Option<u32>,
+    pub playlist_end: Option<u32>,
+    pub playlist_reverse: bool,
+    /// Stop after this many files have been downloaded.
+    pub max_downloads: Option<u32>,
+    pub paths: BTreeMap<PathType, PathBuf>,
+    pub restrict_filenames: bool,
+    pub windows_filenames: bool,
+    /// Maximum filename length in characters, extension excluded.
+    pub trim_filenames: Option<u32>,
+    /// Leave the file's modification time at the download time
+    /// (`--no-mtime`) instead of the server's `Last-Modified`. Set through
+    /// [`Self::set_mtime`].
+    pub no_mtime: bool,
+    pub estimate_size: bool,
+    pub live_from_start: bool,
+    pub wait_for_video: Option<String>,
+    pub match_filter: Option<String>,
+    pub no_shorts: bool,
+    /// A yt-dlp config file or directory loaded for this download on top of
+    /// the client's (`--config-locations`).
+    pub config_location: Option<PathBuf>,
     pub extra_args: Vec<String>
 }
 
@@ -101,6 +205,17 @@ impl DownloadOptions {
         self
     }
 
+    /// Like [`Self::output_template`], but rejects a template that
+    /// [`validate_output_template`] finds malformed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidTemplate`] describing the first mistake.
+    pub fn with_validated_template(self, template: &str) -> crate::Result<Self> {
+        validate_output_template(template)?;
+        Ok(self.output_template(template))
+    }
+
     #[must_use]
     pub fn embed_thumbnail(mut self, embed: bool) -> Self {
         self.embed_thumbnail = embed;
@@ -143,36 +258,309 @@ impl DownloadOptions {
         self
     }
 
+    /// Converts downloaded subtitles to `srt`, `vtt`, `ass` or `lrc`
+    /// (`--convert-subs`).
+    #[must_use]
+    pub fn convert_subtitles(mut self, format: impl Into<String>) -> Self {
+        self.convert_subtitles = Some(format.into());
+        self
+    }
+
     #[must_use]
     pub fn write_subtitles(mut self, write: bool) -> Self {
         self.write_subtitles = write;
         self
     }
 
+    #[must_use]
+    pub fn write_auto_subtitles(mut self, write: bool) -> Self {
+        self.write_auto_subtitles = write;
+        self
+    }
+
     #[must_use]
     pub fn write_thumbnail(mut self, write: bool) -> Self {
         self.write_thumbnail = write;
         self
     }
 
+    /// Converts written thumbnails, e.g. to `jpg` (`--convert-thumbnails`).
+    #[must_use]
+    pub fn convert_thumbnails(mut self, format: impl Into<String>) -> Self {
+        self.convert_thumbnails = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn write_info_json(mut self, write: bool) -> Self {
+        self.write_info_json = write;
+        self
+    }
+
+    /// Probe the expected size before downloading and report it as
+    /// `DownloadEvent::SizeEstimated`. Costs one extra extraction.
+    #[must_use]
+    pub fn estimate_size(mut self, estimate: bool) -> Self {
+        self.estimate_size = estimate;
+        self
+    }
+
+    /// Record a live stream from its beginning rather than the current
+    /// point (`--live-from-start`).
+    #[must_use]
+    pub fn live_from_start(mut self, live_from_start: bool) -> Self {
+        self.live_from_start = live_from_start;
+        self
+    }
+
+    /// Wait for a scheduled stream or premiere to start, retrying every
+    /// `MIN[-MAX]` seconds (`--wait-for-video`).
+    #[must_use]
+    pub fn wait_for_video(mut self, interval: impl Into<String>) -> Self {
+        self.wait_for_video = Some(interval.into());
+        self
+    }
+
+    /// Only download videos matching a yt-dlp filter expression such as
+    /// `duration > 600 & !is_live` (`--match-filter`). Rejected videos are
+    /// reported as `DownloadEvent::Skipped`.
+    #[must_use]
+    pub fn match_filter(mut self, filter: impl Into<String>) -> Self {
+        self.match_filter = Some(filter.into());
+        self
+    }
+
+    /// Skip `YouTube` Shorts by rejecting `/shorts/` URLs; combined with any
+    /// `match_filter` so both must pass.
+    #[must_use]
+    pub fn no_shorts(mut self, no_shorts: bool) -> Self {
+        self.no_shorts = no_shorts;
+        self
+    }
+
+    /// The `--match-filter` expression built from `match_filter` and
+    /// `no_shorts`. Conditions are joined with `&` because repeating the flag
+    /// would OR them.
+    #[must_use]
+    pub fn effective_match_filter(&self) -> Option<String> {
+        let filter = self.match_filter.as_deref().map(str::trim).filter(|f| !f.is_empty());
+        match (filter, self.no_shorts) {
+            (Some(filter), true) => Some(format!("{filter} & {NO_SHORTS_FILTER}")),
+            (Some(filter), false) => Some(filter.to_string()),
+            (None, true) => Some(NO_SHORTS_FILTER.to_string()),
+            (None, false) => None
+        }
+    }
+
     #[must_use]
     pub fn cookies_file(mut self, path: impl Into<PathBuf>) -> Self {
         self.cookies_file = Some(path.into());
         self
     }
 
+    #[must_use]
+    pub fn cookies_from_browser(mut self, browser: impl Into<String>) -> Self {
+        self.cookies_from_browser = Some(browser.into());
+        self
+    }
+
     #[must_use]
     pub fn rate_limit(mut self, limit: impl Into<String>) -> Self {
         self.rate_limit = Some(limit.into());
         self
     }
 
+    /// Re-extract when the speed drops below this rate, e.g. `100K`
+    /// (`--throttled-rate`); works around throttled `YouTube` streams.
+    #[must_use]
+    pub fn throttled_rate(mut self, rate: impl Into<String>) -> Self {
+        self.throttled_rate = Some(rate.into());
+        self
+    }
+
+    /// Abort downloads larger than `size`, e.g. `500M`; reported as
+    /// [`crate::DownloadEvent::FileSizeRejected`] rather than an error.
+    #[must_use]
+    pub fn max_filesize(mut self, size: impl Into<String>) -> Self {
+        self.max_filesize = Some(size.into());
+        self
+    }
+
+    /// Abort downloads smaller than `size`, e.g. `50k`.
+    #[must_use]
+    pub fn min_filesize(mut self, size: impl Into<String>) -> Self {
+        self.min_filesize = Some(size.into());
+        self
+    }
+
     #[must_use]
     pub fn concurrent_fragments(mut self, count: u32) -> Self {
         self.concurrent_fragments = Some(count);
         self
     }
 
+    /// Whether yt-dlp resumes `.part` files left by an earlier run; on by
+    /// default, `false` emits `--no-continue`.
+    #[must_use]
+    pub fn continue_partial(mut self, resume: bool) -> Self {
+        self.no_continue = !resume;
+        self
+    }
+
+    #[must_use]
+    pub fn remux_video(mut self, format: impl Into<String>) -> Self {
+        self.remux_video = Some(format.into());
+        self
+    }
+
+    #[must_use]
+    pub fn recode_video(mut self, format: impl Into<String>) -> Self {
+        self.recode_video = Some(format.into());
+        self
+    }
+
+    /// Passes `args` to a postprocessor or executable, e.g.
+    /// `("VideoConvertor", "-crf 23")` or `("Merger+ffmpeg_i1", "-v quiet")`.
+    /// Entries are emitted in the order they were added.
+    #[must_use]
+    pub fn postprocessor_args(mut self, postprocessor: impl Into<String>, args: impl Into<String>) -> Self {
+        self.postprocessor_args.push((postprocessor.into(), args.into()));
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_items(mut self, items: impl Into<String>) -> Self {
+        self.playlist_items = Some(items.into());
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_start(mut self, start: u32) -> Self {
+        self.playlist_start = Some(start);
+        self
+    }
+
+    #[must_use]
+    pub fn playlist_end(mut self, end: u32) -> Self {
+        self.playlist_end = Some(end);
+        self
+    }
+
+    /// Stops once `count` files have been downloaded; with a channel's
+    /// newest-first listing this keeps only the latest uploads.
+    #[must_use]
+    pub fn max_downloads(mut self, count: u32) -> Self {
+        self.max_downloads = Some(count);
+        self
+    }
+
+    /// Downloads playlist entries in reverse order. `%(playlist_index)s` keeps
+    /// each entry's original position; use `%(playlist_autonumber)s` in the
+    /// output template for numbering that follows download order.
+    #[must_use]
+    pub fn playlist_reverse(mut self, reverse: bool) -> Self {
+        self.playlist_reverse = reverse;
+        self
+    }
+
+    /// Routes a file category to its own directory. Unset categories stay
+    /// next to the video.
+    #[must_use]
+    pub fn path(mut self, kind: PathType, dir: impl Into<PathBuf>) -> Self {
+        self.paths.insert(kind, dir.into());
+        self
+    }
+
+    #[must_use]
+    pub fn restrict_filenames(mut self, restrict: bool) -> Self {
+        self.restrict_filenames = restrict;
+        self
+    }
+
+    #[must_use]
+    pub fn windows_filenames(mut self, windows: bool) -> Self {
+        self.windows_filenames = windows;
+        self
+    }
+
+    #[must_use]
+    pub fn trim_filenames(mut self, length: u32) -> Self {
+        self.trim_filenames = Some(length);
+        self
+    }
+
+    /// Whether yt-dlp sets the file's modification time from the server's
+    /// `Last-Modified` header; on by default, `false` emits `--no-mtime`.
+    #[must_use]
+    pub fn set_mtime(mut self, set: bool) -> Self {
+        self.no_mtime = !set;
+        self
+    }
+
+    /// The output template is not checked here: templates saved before
+    /// [`validate_output_template`] existed must keep downloading, and yt-dlp
+    /// reports the ones it cannot use itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidOption` if an option value is malformed.
+    pub fn validate(&self) -> crate::Result<()> {
+        if let Some(ref items) = self.playlist_items
+            && !is_valid_playlist_items(items)
+        {
+            return Err(crate::Error::InvalidOption(format!("playlist items: {items}")));
+        }
+        if self.playlist_start == Some(0) || self.playlist_end == Some(0) {
+            return Err(crate::Error::InvalidOption("playlist start and end count from 1".to_string()));
+        }
+        if let (Some(start), Some(end)) = (self.playlist_start, self.playlist_end)
+            && start > end
+        {
+            return Err(crate::Error::InvalidOption(format!("playlist range: {start} > {end}")));
+        }
+        if self.max_downloads == Some(0) {
+            return Err(crate::Error::InvalidOption("max downloads: 0".to_string()));
+        }
+        if let Some(ref interval) = self.wait_for_video
+            && !is_valid_wait_interval(interval)
+        {
+            return Err(crate::Error::InvalidOption(format!("wait for video: {interval}")));
+        }
+        if let Some((name, _)) = self
+            .postprocessor_args
+            .iter()
+            .find(|(name, _)| name.is_empty() || name.contains(|c: char| c == ':' || c.is_whitespace()))
+        {
+            return Err(crate::Error::InvalidOption(format!("postprocessor name: {name:?}")));
+        }
+        if let Some(ref browser) = self.cookies_from_browser
+            && !is_valid_browser_spec(browser)
+        {
+            return Err(crate::Error::InvalidOption(format!("cookies from browser: {browser}")));
+        }
+        for (name, size) in [("max filesize", &self.max_filesize), ("min filesize", &self.min_filesize)] {
+            if let Some(size) = size
+                && !is_valid_filesize(size)
+            {
+                return Err(crate::Error::InvalidOption(format!("{name}: {size}")));
+            }
+        }
+        if let Some(ref format) = self.convert_thumbnails
+            && !is_valid_thumbnail_conversion(format)
+        {
+            return Err(crate::Error::InvalidOption(format!("convert thumbnails: {format}")));
+        }
+        Ok(())
+    }
+
+    /// Options from the config are read before the command line, so flags
+    /// set on these options win over the config's.
+    #[must_use]
+    pub fn config_location(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_location = Some(path.into());
+        self
+    }
+
     #[must_use]
     pub fn extra_arg(mut self, arg: impl Into<String>) -> Self {
         self.extra_args.push(arg.into());
@@ -185,3 +573,154 @@ impl DownloadOptions {
         self
     }
 }
+
+/// Rejects videos whose requested URL is a `YouTube` Shorts link.
+const NO_SHORTS_FILTER: &str = "original_url!*=/shorts/";
+
+/// Fields yt-dlp fills in for output templates, see "OUTPUT TEMPLATE" in its
+/// README.
+const TEMPLATE_FIELDS: &[&str] = &[
+    "id", "title", "fulltitle", "ext", "alt_title", "description", "display_id", "uploader",
+    "uploader_id", "uploader_url", "license", "creators", "creator", "timestamp", "upload_date",
+    "release_timestamp", "release_date", "release_year", "modified_timestamp", "modified_date",
+    "channel", "channel_id", "channel_url", "channel_follower_count", "channel_is_verified",
+    "location", "duration", "duration_string", "view_count", "concurrent_view_count", "like_count",
+    "dislike_count", "repost_count", "average_rating", "comment_count", "age_limit", "live_status",
+    "is_live", "was_live", "playable_in_embed", "availability", "media_type", "start_time",
+    "end_time", "extractor", "extractor_key", "epoch", "autonumber", "video_autonumber",
+    "n_entries", "playlist_id", "playlist_title", "playlist", "playlist_count", "playlist_index",
+    "playlist_autonumber", "playlist_uploader", "playlist_uploader_id", "playlist_channel",
+    "playlist_channel_id", "playlist_webpage_url", "webpage_url", "webpage_url_basename",
+    "webpage_url_domain", "original_url", "categories", "tags", "cast", "chapter",
+    "chapter_number", "chapter_id", "series", "series_id", "season", "season_number", "season_id",
+    "episode", "episode_number", "episode_id", "track", "track_number", "track_id", "artists",
+    "artist", "genres", "genre", "composers", "composer", "album", "album_type", "album_artists",
+    "album_artist", "disc_number", "section_title", "section_number", "section_start",
+    "section_end", "format", "format_id", "format_note", "width", "height", "aspect_ratio",
+    "resolution", "dynamic_range", "tbr", "abr", "acodec", "asr", "audio_channels", "vbr", "fps",
+    "vcodec", "container", "filesize", "filesize_approx", "protocol", "language", "thumbnail",
+    "formats", "requested_formats", "requested_downloads", "subtitles", "automatic_captions",
+    "thumbnails", "chapters", "heatmap", "now"
+];
+
+/// Conversion types accepted after `%(field)`, e.g. `s` or `05d`.
+const TEMPLATE_CONVERSIONS: &str = "diouxXeEfFgGcrsaBjlqDSUh";
+
+/// Catches obvious output template mistakes before yt-dlp is launched:
+/// an unclosed `%(`, a field without a conversion type such as `%(title)`,
+/// and unknown field names. Only the leading field name of each token is
+/// checked, so alternatives, defaults and date formats pass through.
+///
+/// # Errors
+///
+/// Returns [`crate::Error::InvalidTemplate`] describing the first mistake.
+pub fn validate_output_template(template: &str) -> crate::Result<()> {
+    let invalid = |message: String| Err(crate::Error::InvalidTemplate(message));
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('%') {
+            rest = escaped;
+            continue;
+        }
+        let Some(body) = after.strip_prefix('(') else {
+            rest = after;
+            continue;
+        };
+        let Some(end) = closing_paren(body) else {
+            return invalid(format!("unclosed \"%({body}\""));
+        };
+        let field = &body[..end];
+        let name: String = field.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+        if field.is_empty() {
+            return invalid("empty field \"%()\"".to_string());
+        }
+        if name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && !TEMPLATE_FIELDS.contains(&name.as_str())
+        {
+            return invalid(format!("unknown field \"{name}\""));
+        }
+        let tail = &body[end + 1..];
+        let spec_len = tail
+            .find(|c: char| !(c.is_ascii_digit() || "#-+ .".contains(c)))
+            .unwrap_or(tail.len());
+        let Some(conversion) = tail[spec_len..].chars().next().filter(|c| TEMPLATE_CONVERSIONS.contains(*c))
+        else {
+            return invalid(format!("missing conversion type after \"%({field})\", e.g. \"%({field})s\""));
+        };
+        rest = &tail[spec_len + conversion.len_utf8()..];
+    }
+    Ok(())
+}
+
+/// Index of the `)` closing a `%(` token, allowing nested parentheses in
+/// defaults such as `%(title|Untitled (draft))s`.
+fn closing_paren(body: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (index, c) in body.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+const COOKIE_BROWSERS: &[&str] =
+    &["brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale"];
+
+const COOKIE_KEYRINGS: &[&str] = &["basictext", "gnomekeyring", "kwallet", "kwallet5", "kwallet6"];
+
+/// Whether `spec` is a `--cookies-from-browser` value:
+/// `BROWSER[+KEYRING][:PROFILE]`, e.g. `firefox` or `chrome:Profile 1`.
+#[must_use]
+pub fn is_valid_browser_spec(spec: &str) -> bool {
+    let (browser, profile) = spec.split_once(':').map_or((spec, None), |(b, p)| (b, Some(p)));
+    let (browser, keyring) = browser.split_once('+').map_or((browser, None), |(b, k)| (b, Some(k)));
+    COOKIE_BROWSERS.contains(&browser.to_ascii_lowercase().as_str())
+        && keyring.is_none_or(|k| COOKIE_KEYRINGS.contains(&k.to_ascii_lowercase().as_str()))
+        && profile.is_none_or(|p| !p.trim().is_empty())
+}
+
+/// Whether `size` is a byte count yt-dlp parses for `--max-filesize` and
+/// `--min-filesize`: a number with an optional single unit letter out of
+/// `kMGTPEZY`, case-insensitive, e.g. `50k` or `44.6M`.
+#[must_use]
+pub fn is_valid_filesize(size: &str) -> bool {
+    let number = size
+        .strip_suffix(|c: char| "KMGTPEZY".contains(c.to_ascii_uppercase()))
+        .unwrap_or(size);
+    let (whole, fraction) = number.split_once('.').map_or((number, None), |(w, f)| (w, Some(f)));
+    let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    is_digits(whole) && fraction.is_none_or(is_digits)
+}
+
+/// Accepts `--convert-thumbnails`: a target out of `jpg`, `png` and `webp`,
+/// or `/`-separated rules such as `webp>jpg/png` as for `--remux-video`.
+fn is_valid_thumbnail_conversion(format: &str) -> bool {
+    format.split('/').all(|rule| {
+        let (source, target) = rule.split_once('>').map_or((None, rule), |(s, t)| (Some(s), t));
+        source.is_none_or(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric()))
+            && ["jpg", "png", "webp"].contains(&target)
+    })
+}
+
+/// Accepts `--wait-for-video`'s `MIN[-MAX]` seconds.
+fn is_valid_wait_interval(interval: &str) -> bool {
+    let (min, max) = interval.split_once('-').map_or((interval, None), |(min, max)| (min, Some(max)));
+    let is_seconds = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    is_seconds(min) && max.is_none_or(is_seconds)
+}
+
+/// Accepts yt-dlp's `--playlist-items` syntax: comma-separated indices,
+/// ranges (`1-10`, `20-`) and slices (`::2`), negative indices included.
+fn is_valid_playlist_items(items: &str) -> bool {
+    !items.is_empty()
+        && items.split(',').all(|item| {
+            !item.is_empty()
+                && item.chars().any(|c| c.is_ascii_digit() || c == ':')
+                && item.chars().all(|c| c.is_ascii_digit() || c == '-' || c == ':')
+        })
+}
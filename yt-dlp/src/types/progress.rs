@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 #[derive(Debug, Clone)]
 pub struct DownloadProgress {
     pub downloaded_bytes: u64,
@@ -48,8 +50,9 @@ impl DownloadProgress {
     }
 }
 
+#[must_use]
 #[allow(clippy::cast_precision_loss)]
-fn format_bytes(bytes: u64) -> String {
+pub fn format_bytes(bytes: u64) -> String {
     if bytes >= 1_000_000_000 {
         format!("{:.2} GB", bytes as f64 / 1_000_000_000.0)
     } else if bytes >= 1_000_000 {
@@ -64,15 +67,37 @@ fn format_bytes(bytes: u64) -> String {
 #[derive(Debug, Clone)]
 pub enum DownloadEvent {
     Extracting { url: String },
+    /// yt-dlp is still fetching pages and player data before the download,
+    /// e.g. `Extracting URL` or `Downloading ios player API JSON`.
+    Resolving { stage: String },
+    SizeEstimated { total_bytes: u64 },
     DownloadStarted { filename: String },
+    FormatSelected { format_id: String },
+    InfoJsonWritten { filename: String },
     Progress(DownloadProgress),
     PostProcessing { status: String },
     MergingFormats,
     EmbeddingThumbnail,
     EmbeddingMetadata,
-    Finished { filename: String },
+    /// A post-processor replaced the file, e.g. audio extraction, remuxing
+    /// or moving it out of the temp directory.
+    FileRenamed { from: String, to: String },
+    Finished {
+        filename: String,
+        total_bytes: Option<u64>,
+        elapsed: Option<Duration>,
+        average_speed: Option<f64>
+    },
     Error { message: String },
-    Warning { message: String }
+    /// The requested format was not available; the download is retried once
+    /// with `fallback`.
+    FormatFallback { requested: String, fallback: String },
+    Warning { message: String },
+    /// The video was rejected by `--match-filter` and nothing was downloaded.
+    Skipped { reason: String },
+    /// The file fell outside `max_filesize`/`min_filesize` and the download
+    /// was aborted, e.g. `larger than max-filesize (1000 bytes > 500 bytes)`.
+    FileSizeRejected { reason: String }
 }
 
 impl DownloadEvent {
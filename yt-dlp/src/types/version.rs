@@ -0,0 +1,155 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A yt-dlp release version. Releases are named after their build date
+/// (`YYYY.MM.DD`); nightly builds append a time-of-day revision such as
+/// `2024.03.10.232650`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct YtDlpVersion {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+    pub revision: Option<u32>
+}
+
+impl YtDlpVersion {
+    /// Parses `--version` output. A leading channel such as `nightly@` is ignored.
+    #[must_use]
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.split_once('@').map_or(version, |(_, v)| v);
+
+        let mut parts = version.split('.');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        let revision = match parts.next() {
+            Some(rev) => Some(rev.parse().ok()?),
+            None => None
+        };
+        if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        Some(Self { year, month, day, revision })
+    }
+
+    #[must_use]
+    pub fn date(&self) -> (u32, u32, u32) {
+        (self.year, self.month, self.day)
+    }
+
+    /// Days elapsed between this release and `today`, negative if `today` is earlier.
+    #[must_use]
+    pub fn age_in_days(&self, today: (u32, u32, u32)) -> i64 {
+        days_from_civil(today) - days_from_civil(self.date())
+    }
+
+    /// Whether this release is more than `days` days old according to the system clock.
+    #[must_use]
+    pub fn is_older_than(&self, days: i64) -> bool {
+        self.age_in_days(today_utc()) > days
+    }
+}
+
+impl fmt::Display for YtDlpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}.{:02}", self.year, self.month, self.day)?;
+        if let Some(revision) = self.revision {
+            write!(f, ".{revision}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub(super) fn days_from_civil((year, month, day): (u32, u32, u32)) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+pub(super) fn civil_from_days(days: i64) -> (u32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (
+        u32::try_from(year).unwrap_or(0),
+        u32::try_from(month).unwrap_or(1),
+        u32::try_from(day).unwrap_or(1)
+    )
+}
+
+pub(super) fn today_utc() -> (u32, u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    civil_from_days(i64::try_from(secs / 86_400).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_release() {
+        let version = YtDlpVersion::parse("2024.03.10\n").unwrap();
+        assert_eq!(version, YtDlpVersion { year: 2024, month: 3, day: 10, revision: None });
+        assert_eq!(version.to_string(), "2024.03.10");
+    }
+
+    #[test]
+    fn test_parse_nightly_revision() {
+        let version = YtDlpVersion::parse("2024.03.10.232650").unwrap();
+        assert_eq!(version.revision, Some(232_650));
+        assert_eq!(version.to_string(), "2024.03.10.232650");
+        assert!(version > YtDlpVersion::parse("2024.03.10").unwrap());
+
+        let version = YtDlpVersion::parse("nightly@2024.03.10.123456").unwrap();
+        assert_eq!(version.date(), (2024, 3, 10));
+        assert_eq!(version.revision, Some(123_456));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert_eq!(YtDlpVersion::parse("not a version"), None);
+        assert_eq!(YtDlpVersion::parse("2024.03"), None);
+        assert_eq!(YtDlpVersion::parse("2024.13.01"), None);
+        assert_eq!(YtDlpVersion::parse("2024.03.10.abc"), None);
+        assert_eq!(YtDlpVersion::parse("2024.03.10.1.2"), None);
+    }
+
+    #[test]
+    fn test_age_in_days() {
+        let version = YtDlpVersion::parse("2024.02.28").unwrap();
+        assert_eq!(version.age_in_days((2024, 2, 28)), 0);
+        assert_eq!(version.age_in_days((2024, 3, 1)), 2);
+        assert_eq!(version.age_in_days((2025, 2, 28)), 366);
+        assert_eq!(version.age_in_days((2024, 2, 1)), -27);
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        assert_eq!(days_from_civil((1970, 1, 1)), 0);
+        for days in [0, 59, 365, 11_016, 19_782, 20_000] {
+            assert_eq!(days_from_civil(civil_from_days(days)), days);
+        }
+    }
+
+    #[test]
+    fn test_is_older_than() {
+        assert!(YtDlpVersion::parse("2021.01.01").unwrap().is_older_than(30));
+        assert!(!YtDlpVersion::parse("2999.01.01").unwrap().is_older_than(30));
+    }
+}
@@ -30,12 +30,21 @@ pub struct VideoInfo {
     pub comment_count: Option<u64>,
     #[serde(default)]
     pub upload_date: Option<String>,
+    /// Unix time of the upload. Some extractors report fractional seconds.
+    #[serde(default)]
+    pub timestamp: Option<f64>,
     #[serde(default)]
     pub release_date: Option<String>,
     #[serde(default)]
     pub webpage_url: Option<String>,
     #[serde(default)]
     pub original_url: Option<String>,
+    /// Set on `--flat-playlist` entries, which often lack `webpage_url`.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Extractor for a `--flat-playlist` entry that has not been resolved yet.
+    #[serde(default)]
+    pub ie_key: Option<String>,
     #[serde(default)]
     pub thumbnail: Option<String>,
     #[serde(default)]
@@ -79,6 +88,8 @@ pub struct VideoInfo {
 }
 
 impl VideoInfo {
+    /// yt-dlp's own `thumbnail` pick, otherwise the highest ranked entry of
+    /// `thumbnails` by preference and then pixel count.
     #[must_use]
     pub fn best_thumbnail(&self) -> Option<&str> {
         if let Some(ref url) = self.thumbnail {
@@ -86,9 +97,25 @@ impl VideoInfo {
         }
         self.thumbnails
             .iter()
-            .max_by_key(|t| t.width.unwrap_or(0))
+            .max_by_key(|t| t.rank())
             .map(|t| t.url.as_str())
     }
+
+    /// The best thumbnail whose URL has one of `exts`, earlier extensions
+    /// winning over later ones, e.g. `&["jpg", "png"]` to avoid WebP. `None`
+    /// when no thumbnail matches.
+    #[must_use]
+    pub fn best_thumbnail_by_format(&self, exts: &[&str]) -> Option<&str> {
+        self.thumbnails
+            .iter()
+            .filter_map(|t| {
+                let ext = t.extension()?;
+                let position = exts.iter().position(|e| e.eq_ignore_ascii_case(ext))?;
+                Some((std::cmp::Reverse(position), t.rank(), t))
+            })
+            .max_by_key(|(position, rank, _)| (*position, *rank))
+            .map(|(_, _, t)| t.url.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -182,6 +209,34 @@ pub struct Thumbnail {
     pub preference: Option<i32>
 }
 
+impl Thumbnail {
+    /// `width` and `height`, falling back to a `resolution` such as `1920x1080`.
+    #[must_use]
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            return Some((width, height));
+        }
+        let (width, height) = self.resolution.as_deref()?.trim().split_once('x')?;
+        Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+    }
+
+    /// The URL's file extension, ignoring any query string.
+    #[must_use]
+    pub fn extension(&self) -> Option<&str> {
+        let path = self.url.split(['?', '#']).next()?;
+        let (_, ext) = path.rsplit_once('/').map_or(path, |(_, name)| name).rsplit_once('.')?;
+        (!ext.is_empty()).then_some(ext)
+    }
+
+    /// yt-dlp treats a missing preference as -1.
+    fn rank(&self) -> (i32, u64) {
+        let pixels = self
+            .dimensions()
+            .map_or(0, |(width, height)| u64::from(width) * u64::from(height));
+        (self.preference.unwrap_or(-1), pixels)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chapter {
     pub start_time: f64,
@@ -218,3 +273,64 @@ pub struct PlaylistInfo {
     #[serde(default)]
     pub extractor_key: Option<String>
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(thumbnails: &serde_json::Value) -> VideoInfo {
+        serde_json::from_value(serde_json::json!({"id": "abc", "title": "Video", "thumbnails": thumbnails}))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_best_thumbnail_ranks_preference_then_pixels() {
+        let info = info(&serde_json::json!([
+            {"url": "https://i.ytimg.com/vi/abc/maxres.webp", "preference": -2, "width": 1920, "height": 1080},
+            {"url": "https://i.ytimg.com/vi/abc/hq.jpg", "preference": 1, "width": 480, "height": 360},
+            {"url": "https://i.ytimg.com/vi/abc/sd.jpg", "preference": 1, "resolution": "640x480"},
+            {"url": "https://i.ytimg.com/vi/abc/default.jpg"}
+        ]));
+        assert_eq!(info.best_thumbnail(), Some("https://i.ytimg.com/vi/abc/sd.jpg"));
+    }
+
+    #[test]
+    fn test_best_thumbnail_without_sizes() {
+        let info = info(&serde_json::json!([
+            {"url": "https://example.com/a.jpg"},
+            {"url": "https://example.com/b.jpg", "resolution": "320x180"},
+            {"url": "https://example.com/c.jpg", "width": 1280}
+        ]));
+        assert_eq!(info.best_thumbnail(), Some("https://example.com/b.jpg"));
+        assert_eq!(info.thumbnails[2].dimensions(), None);
+        assert_eq!(self::info(&serde_json::json!([])).best_thumbnail(), None);
+    }
+
+    #[test]
+    fn test_best_thumbnail_prefers_top_level_pick() {
+        let mut info = info(&serde_json::json!([{"url": "https://example.com/a.jpg", "width": 1280, "height": 720}]));
+        info.thumbnail = Some("https://example.com/picked.jpg".to_string());
+        assert_eq!(info.best_thumbnail(), Some("https://example.com/picked.jpg"));
+    }
+
+    #[test]
+    fn test_best_thumbnail_by_format() {
+        let info = info(&serde_json::json!([
+            {"url": "https://i.ytimg.com/vi_webp/abc/maxresdefault.webp", "width": 1280, "height": 720},
+            {"url": "https://i.ytimg.com/vi/abc/hqdefault.jpg?sqp=-oaymw", "width": 480, "height": 360},
+            {"url": "https://i.ytimg.com/vi/abc/mqdefault.JPG", "width": 320, "height": 180},
+            {"url": "https://i.ytimg.com/vi/abc/frame0.png", "width": 1280, "height": 720}
+        ]));
+        assert_eq!(
+            info.best_thumbnail_by_format(&["jpg"]),
+            Some("https://i.ytimg.com/vi/abc/hqdefault.jpg?sqp=-oaymw")
+        );
+        assert_eq!(info.best_thumbnail_by_format(&["png", "jpg"]), Some("https://i.ytimg.com/vi/abc/frame0.png"));
+        assert_eq!(
+            info.best_thumbnail_by_format(&["webp"]),
+            Some("https://i.ytimg.com/vi_webp/abc/maxresdefault.webp")
+        );
+        assert_eq!(info.best_thumbnail_by_format(&["gif"]), None);
+        assert_eq!(info.thumbnails[1].extension(), Some("jpg"));
+    }
+}